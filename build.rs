@@ -1,10 +1,18 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-fn find_prolog_files(libraries: &mut File, prefix: &str, current_dir: &Path) {
+fn find_prolog_files(
+    libraries: &mut File,
+    exports: &mut Vec<(String, String, usize)>,
+    prefix: &str,
+    current_dir: &Path,
+) {
     let entries = match current_dir.read_dir() {
         Ok(entries) => entries,
         Err(_) => return,
@@ -14,7 +22,7 @@ fn find_prolog_files(libraries: &mut File, prefix: &str, current_dir: &Path) {
         if entry.is_dir() {
             if let Some(file_name) = entry.file_name() {
                 let new_prefix = prefix.to_owned() + file_name.to_str().unwrap() + "/";
-                find_prolog_files(libraries, &new_prefix, &entry);
+                find_prolog_files(libraries, exports, &new_prefix, &entry);
             }
         } else if entry.is_file() {
             let ext = std::ffi::OsStr::new("pl");
@@ -29,9 +37,145 @@ fn find_prolog_files(libraries: &mut File, prefix: &str, current_dir: &Path) {
                 );
 
                 libraries.write_all(line.as_bytes()).unwrap();
+
+                for (export_name, arity) in module_exports(&contain) {
+                    exports.push((prefix.to_owned() + name, export_name, arity));
+                }
+            }
+        }
+    }
+}
+
+// the predicates exported by the module declaration of a library, as
+// (name, arity) pairs. Operator exports are skipped.
+fn module_exports(source: &str) -> Vec<(String, usize)> {
+    let start = match source.find(":- module(") {
+        Some(start) => start + ":- module(".len(),
+        None => return vec![],
+    };
+
+    let mut exports = vec![];
+    let mut entry = String::new();
+
+    let mut depth = 0;
+    let mut in_list = false;
+    let mut in_comment = false;
+    let mut quote = None;
+
+    for c in source[start..].chars() {
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+
+        if let Some(q) = quote {
+            entry.push(c);
+
+            if c == q {
+                quote = None;
+            }
+
+            continue;
+        }
+
+        match c {
+            '%' => in_comment = true,
+            '[' if depth == 0 && !in_list => in_list = true,
+            ']' | ')' if depth == 0 => {
+                exports.extend(predicate_indicator(&entry));
+                break;
+            }
+            ',' if depth == 0 && in_list => {
+                exports.extend(predicate_indicator(&entry));
+                entry.clear();
             }
+            _ if !in_list => {}
+            '\'' | '"' => {
+                quote = Some(c);
+                entry.push(c);
+            }
+            '(' | '[' => {
+                depth += 1;
+                entry.push(c);
+            }
+            ')' | ']' => {
+                depth -= 1;
+                entry.push(c);
+            }
+            _ => entry.push(c),
         }
     }
+
+    exports
+}
+
+// reads Name/Arity or Name//Arity, whose arity counts the two
+// arguments of the difference list.
+fn predicate_indicator(entry: &str) -> Option<(String, usize)> {
+    let entry = entry.trim();
+
+    if entry.starts_with("op(") {
+        return None;
+    }
+
+    let slash = entry.rfind('/')?;
+    let arity: usize = entry[slash + 1..].trim().parse().ok()?;
+
+    let (name, arity) = match entry[..slash].strip_suffix('/') {
+        Some(name) => (name, arity + 2),
+        None => (&entry[..slash], arity),
+    };
+
+    let mut name = name.trim();
+
+    if name.len() > 1 && name.starts_with('(') && name.ends_with(')') {
+        name = name[1..name.len() - 1].trim();
+    }
+
+    if name.len() > 1 && name.starts_with('\'') && name.ends_with('\'') {
+        name = &name[1..name.len() - 1];
+    }
+
+    Some((name.to_string(), arity))
+}
+
+fn find_source_files(files: &mut Vec<PathBuf>, current_dir: &Path) {
+    let entries = match current_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if entry.is_dir() {
+            find_source_files(files, &entry);
+        } else if let Some(ext) = entry.extension() {
+            if ext == "rs" || ext == "pl" || ext == "toml" {
+                files.push(entry);
+            }
+        }
+    }
+}
+
+// a hash of the sources of the crate, which keys the boot images of
+// machine/image.rs to the build that wrote them.
+fn image_key() -> String {
+    let mut files = vec![PathBuf::from("Cargo.toml")];
+
+    find_source_files(&mut files, Path::new("src"));
+    find_source_files(&mut files, Path::new("crates"));
+
+    files.sort();
+
+    let mut hasher = DefaultHasher::new();
+
+    env::var("CARGO_PKG_VERSION").unwrap().hash(&mut hasher);
+
+    for file in files {
+        file.hash(&mut hasher);
+        fs::read(&file).unwrap().hash(&mut hasher);
+    }
+
+    format!("{:016x}", hasher.finish())
 }
 
 fn main() {
@@ -48,6 +192,38 @@ fn main() {
         let mut m = IndexMap::new();\n",
         )
         .unwrap();
-    find_prolog_files(&mut libraries, "", &lib_path);
+    let mut exports = vec![];
+
+    find_prolog_files(&mut libraries, &mut exports, "", &lib_path);
     libraries.write_all(b"\n        m\n    };\n}\n").unwrap();
+
+    // the autoload index maps the predicates exported by libraries to
+    // the first library exporting them. builtins is imported into
+    // every module, so none of its exports are ever autoloaded.
+    exports.sort();
+
+    libraries
+        .write_all(
+            b"\nref_thread_local::ref_thread_local! {
+    pub(crate) static managed AUTOLOAD: IndexMap<(&'static str, usize), &'static str> = {
+        let mut m = IndexMap::new();\n",
+        )
+        .unwrap();
+
+    let mut indexed = HashSet::new();
+
+    for (library, name, arity) in exports {
+        if library != "builtins" && indexed.insert((name.clone(), arity)) {
+            let line = format!(
+                "        m.insert(({:?}, {}), {:?});\n",
+                name, arity, library
+            );
+            libraries.write_all(line.as_bytes()).unwrap();
+        }
+    }
+
+    libraries.write_all(b"\n        m\n    };\n}\n").unwrap();
+
+    let image_key = format!("\npub(crate) const IMAGE_KEY: &str = {:?};\n", image_key());
+    libraries.write_all(image_key.as_bytes()).unwrap();
 }
@@ -48,6 +48,28 @@ impl<T: Hash + Eq> TabledData<T> {
     pub fn borrow_mut(&self) -> RefMut<HashSet<Rc<T>>> {
         self.table.borrow_mut()
     }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.table.borrow().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.table.borrow().is_empty()
+    }
+
+    // TabledRc removes its entry when it drops the last outside
+    // reference, but entries whose last reference was an Rc obtained
+    // through inner() or detached by a table swap linger. sweep
+    // removes them and returns how many were removed.
+    pub fn sweep(&self) -> usize {
+        let mut table = self.table.borrow_mut();
+        let len = table.len();
+
+        table.retain(|entry| Rc::strong_count(entry) > 1);
+        len - table.len()
+    }
 }
 
 pub struct TabledRc<T: Hash + Eq> {
@@ -0,0 +1,121 @@
+#ifndef SCRYER_PROLOG_H
+#define SCRYER_PROLOG_H
+
+/* Generated from crates/scryer-prolog-capi/src/lib.rs by cbindgen. Don't edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ A Prolog machine. Standard input is empty, and standard output and
+ error are those of the process.
+ */
+typedef struct ScryerMachine ScryerMachine;
+
+/*
+ The answers to a query. It borrows its machine, which mustn't be
+ used for anything else until the query is freed.
+ */
+typedef struct ScryerQuery ScryerQuery;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Creates a machine, which is freed by scryer_machine_free. Returns
+ NULL if the machine can't be created.
+
+ The machine starts from the boot image of this build in the cache
+ directory of the user, writing it first if need be, unless the
+ environment variable SCRYER_PROLOG_NO_BOOT_IMAGE is set.
+ */
+struct ScryerMachine *scryer_machine_new(void);
+
+/*
+ Creates a machine, which is freed by scryer_machine_free, by
+ compiling the libraries without reading or writing a boot image.
+ Returns NULL if the machine can't be created.
+ */
+struct ScryerMachine *scryer_machine_new_without_boot_image(void);
+
+/*
+ Frees a machine. Its queries must be freed first.
+
+ # Safety
+
+ `machine` must be NULL or have come from scryer_machine_new.
+ */
+void scryer_machine_free(struct ScryerMachine *machine);
+
+/*
+ Compiles the clauses and directives of the UTF-8 string `src` into
+ the user module. Returns 0 on success and -1 otherwise, in which
+ case scryer_last_error describes the error.
+
+ # Safety
+
+ `machine` must have come from scryer_machine_new, and `src` must be
+ a NUL terminated string.
+ */
+int scryer_consult(struct ScryerMachine *machine, const char *src);
+
+/*
+ The error of the last call on `machine` that failed, or NULL if
+ the last call succeeded. The string belongs to the machine.
+
+ # Safety
+
+ `machine` must have come from scryer_machine_new.
+ */
+const char *scryer_last_error(const struct ScryerMachine *machine);
+
+/*
+ Starts the query `query`, whose final period is optional. Its
+ answers are fetched by scryer_next_solution, and it's freed by
+ scryer_query_free. Returns NULL if `query` isn't valid UTF-8, in
+ which case scryer_last_error describes the error.
+
+ # Safety
+
+ `machine` must have come from scryer_machine_new, and mustn't have
+ another query running. `query` must be a NUL terminated string.
+ */
+struct ScryerQuery *scryer_run_query(struct ScryerMachine *machine, const char *query);
+
+/*
+ The next answer to `query` as JSON, or NULL if there are no more
+ answers or the machine panicked, in which case scryer_last_error
+ describes the panic. The string is freed by scryer_free.
+
+ # Safety
+
+ `query` must have come from scryer_run_query.
+ */
+char *scryer_next_solution(struct ScryerQuery *query);
+
+/*
+ Frees a query, discarding the answers it hasn't produced.
+
+ # Safety
+
+ `query` must be NULL or have come from scryer_run_query.
+ */
+void scryer_query_free(struct ScryerQuery *query);
+
+/*
+ Frees a string returned by scryer_next_solution.
+
+ # Safety
+
+ `s` must be NULL or have come from scryer_next_solution.
+ */
+void scryer_free(char *s);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SCRYER_PROLOG_H */
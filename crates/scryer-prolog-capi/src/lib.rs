@@ -0,0 +1,391 @@
+/* The C interface to the embedding API, exported by the cdylib build
+ * of this crate. Its header, include/scryer_prolog.h, is generated
+ * from this file by running
+ *
+ *   cbindgen --config cbindgen.toml --output include/scryer_prolog.h
+ *
+ * in the directory of the crate.
+ *
+ * No panic unwinds into C. A function that panics returns -1 or NULL
+ * instead, and the panic message becomes the last error of the
+ * machine, which can only be freed afterwards.
+ *
+ * Answers are returned as JSON objects. A successful answer is
+ * {"result":"true","bindings":{...}}, mapping the names of the query
+ * variables to their values, and a query that throws a ball gives
+ * {"result":"exception","exception":Ball}. Terms are encoded as:
+ *
+ *   integers and floats   numbers
+ *   atoms                 {"atom":Name}
+ *   strings               strings
+ *   lists                 arrays
+ *   compound terms        {"functor":Name,"args":[...]}
+ *   variables             {"var":Name}
+ *   rationals             {"rational":"N/D"}
+ *
+ * Floats with no JSON number, like inf and nan, are encoded as
+ * {"float":Text}. */
+
+use scryer_prolog::machine::embedding::{Bindings, PrologError, QueryIter, Term};
+use scryer_prolog::machine::{Machine, Stream};
+
+use libc::{c_char, c_int};
+
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// A Prolog machine. Standard input is empty, and standard output and
+/// error are those of the process.
+pub struct ScryerMachine {
+    machine: Machine,
+    last_error: Option<CString>,
+}
+
+/// The answers to a query. It borrows its machine, which mustn't be
+/// used for anything else until the query is freed.
+pub struct ScryerQuery {
+    answers: QueryIter<'static>,
+    machine: *mut ScryerMachine,
+}
+
+fn json_string(s: &str, json: &mut String) {
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(json, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+fn json_term(term: &Term, json: &mut String) {
+    match term {
+        Term::Atom(atom) => {
+            json.push_str("{\"atom\":");
+            json_string(atom, json);
+            json.push('}');
+        }
+        Term::Integer(n) => {
+            write!(json, "{}", n).unwrap();
+        }
+        Term::Rational(r) => {
+            json.push_str("{\"rational\":");
+            json_string(&r.to_string(), json);
+            json.push('}');
+        }
+        Term::Float(f) if f.is_finite() => {
+            write!(json, "{:?}", f).unwrap();
+        }
+        Term::Float(f) => {
+            json.push_str("{\"float\":");
+            json_string(&f.to_string(), json);
+            json.push('}');
+        }
+        Term::String(s) => {
+            json_string(s, json);
+        }
+        Term::List(terms) => {
+            json.push('[');
+            json_terms(terms, json);
+            json.push(']');
+        }
+        Term::Compound(name, args) => {
+            json.push_str("{\"functor\":");
+            json_string(name, json);
+            json.push_str(",\"args\":[");
+            json_terms(args, json);
+            json.push_str("]}");
+        }
+        Term::Var(name) => {
+            json.push_str("{\"var\":");
+            json_string(name, json);
+            json.push('}');
+        }
+    }
+}
+
+fn json_terms(terms: &[Term], json: &mut String) {
+    for (idx, term) in terms.iter().enumerate() {
+        if idx > 0 {
+            json.push(',');
+        }
+
+        json_term(term, json);
+    }
+}
+
+fn json_answer(answer: Result<Bindings, PrologError>) -> String {
+    let mut json = String::new();
+
+    match answer {
+        Ok(bindings) => {
+            json.push_str("{\"result\":\"true\",\"bindings\":{");
+
+            for (idx, (name, term)) in bindings.iter().enumerate() {
+                if idx > 0 {
+                    json.push(',');
+                }
+
+                json_string(name, &mut json);
+                json.push(':');
+                json_term(term, &mut json);
+            }
+
+            json.push_str("}}");
+        }
+        Err(PrologError::Syntax(ball)) | Err(PrologError::Exception(ball)) => {
+            json.push_str("{\"result\":\"exception\",\"exception\":");
+            json_term(&ball, &mut json);
+            json.push('}');
+        }
+    }
+
+    json
+}
+
+// C strings can't contain NULs, so the few Rust strings that do are cut short.
+fn c_string(s: String) -> CString {
+    match CString::new(s) {
+        Ok(s) => s,
+        Err(e) => {
+            let nul = e.nul_position();
+            let mut bytes = e.into_vec();
+
+            bytes.truncate(nul);
+            CString::new(bytes).unwrap()
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("unknown cause"),
+        },
+    };
+
+    format!("panicked: {}", message)
+}
+
+// runs f, returning on_panic in place of unwinding into C if it
+// panics. The panic message becomes the last error of machine unless
+// it's NULL.
+unsafe fn catch_panic<T, F: FnOnce() -> T>(machine: *mut ScryerMachine, on_panic: T, f: F) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            if !machine.is_null() {
+                (*machine).last_error = Some(c_string(panic_message(payload)));
+            }
+
+            on_panic
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+fn new_machine(machine: Machine) -> *mut ScryerMachine {
+    Box::into_raw(Box::new(ScryerMachine {
+        machine,
+        last_error: None,
+    }))
+}
+
+/// Creates a machine, which is freed by scryer_machine_free. Returns
+/// NULL if the machine can't be created.
+///
+/// The machine starts from the boot image of this build in the cache
+/// directory of the user, writing it first if need be, unless the
+/// environment variable SCRYER_PROLOG_NO_BOOT_IMAGE is set.
+#[no_mangle]
+pub extern "C" fn scryer_machine_new() -> *mut ScryerMachine {
+    unsafe {
+        catch_panic(ptr::null_mut(), ptr::null_mut(), || {
+            new_machine(Machine::new(
+                Stream::from(""),
+                Stream::stdout(),
+                Stream::stderr(),
+            ))
+        })
+    }
+}
+
+/// Creates a machine, which is freed by scryer_machine_free, by
+/// compiling the libraries without reading or writing a boot image.
+/// Returns NULL if the machine can't be created.
+#[no_mangle]
+pub extern "C" fn scryer_machine_new_without_boot_image() -> *mut ScryerMachine {
+    unsafe {
+        catch_panic(ptr::null_mut(), ptr::null_mut(), || {
+            new_machine(Machine::without_boot_image(
+                Stream::from(""),
+                Stream::stdout(),
+                Stream::stderr(),
+            ))
+        })
+    }
+}
+
+/// Frees a machine. Its queries must be freed first.
+///
+/// # Safety
+///
+/// `machine` must be NULL or have come from scryer_machine_new.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_machine_free(machine: *mut ScryerMachine) {
+    catch_panic(ptr::null_mut(), (), || {
+        if !machine.is_null() {
+            drop(Box::from_raw(machine));
+        }
+    })
+}
+
+/// Compiles the clauses and directives of the UTF-8 string `src` into
+/// the user module. Returns 0 on success and -1 otherwise, in which
+/// case scryer_last_error describes the error.
+///
+/// # Safety
+///
+/// `machine` must have come from scryer_machine_new, and `src` must be
+/// a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_consult(machine: *mut ScryerMachine, src: *const c_char) -> c_int {
+    catch_panic(machine, -1, || {
+        let machine = &mut *machine;
+
+        let result = match str_arg(src) {
+            Some(src) => machine.machine.consult_str(src).map_err(|e| e.to_string()),
+            None => Err(String::from("the source isn't valid UTF-8")),
+        };
+
+        match result {
+            Ok(()) => {
+                machine.last_error = None;
+                0
+            }
+            Err(e) => {
+                machine.last_error = Some(c_string(e));
+                -1
+            }
+        }
+    })
+}
+
+/// The error of the last call on `machine` that failed, or NULL if
+/// the last call succeeded. The string belongs to the machine.
+///
+/// # Safety
+///
+/// `machine` must have come from scryer_machine_new.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_last_error(machine: *const ScryerMachine) -> *const c_char {
+    catch_panic(ptr::null_mut(), ptr::null(), || {
+        match &(*machine).last_error {
+            Some(e) => e.as_ptr(),
+            None => ptr::null(),
+        }
+    })
+}
+
+/// Starts the query `query`, whose final period is optional. Its
+/// answers are fetched by scryer_next_solution, and it's freed by
+/// scryer_query_free. Returns NULL if `query` isn't valid UTF-8, in
+/// which case scryer_last_error describes the error.
+///
+/// # Safety
+///
+/// `machine` must have come from scryer_machine_new, and mustn't have
+/// another query running. `query` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_run_query(
+    machine: *mut ScryerMachine,
+    query: *const c_char,
+) -> *mut ScryerQuery {
+    catch_panic(machine, ptr::null_mut(), || {
+        let query = match str_arg(query) {
+            Some(query) => query,
+            None => {
+                (*machine).last_error = Some(c_string(String::from("the query isn't valid UTF-8")));
+                return ptr::null_mut();
+            }
+        };
+
+        (*machine).last_error = None;
+
+        let answers = (*machine).machine.query(query);
+
+        Box::into_raw(Box::new(ScryerQuery {
+            answers: std::mem::transmute::<QueryIter<'_>, QueryIter<'static>>(answers),
+            machine,
+        }))
+    })
+}
+
+/// The next answer to `query` as JSON, or NULL if there are no more
+/// answers or the machine panicked, in which case scryer_last_error
+/// describes the panic. The string is freed by scryer_free.
+///
+/// # Safety
+///
+/// `query` must have come from scryer_run_query.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_next_solution(query: *mut ScryerQuery) -> *mut c_char {
+    catch_panic((*query).machine, ptr::null_mut(), || {
+        match (*query).answers.next() {
+            Some(answer) => c_string(json_answer(answer)).into_raw(),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Frees a query, discarding the answers it hasn't produced.
+///
+/// # Safety
+///
+/// `query` must be NULL or have come from scryer_run_query.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_query_free(query: *mut ScryerQuery) {
+    if query.is_null() {
+        return;
+    }
+
+    catch_panic((*query).machine, (), || {
+        drop(Box::from_raw(query));
+    })
+}
+
+/// Frees a string returned by scryer_next_solution.
+///
+/// # Safety
+///
+/// `s` must be NULL or have come from scryer_next_solution.
+#[no_mangle]
+pub unsafe extern "C" fn scryer_free(s: *mut c_char) {
+    catch_panic(ptr::null_mut(), (), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// the C interface is exported by the cdylib build of the crate, which
+// cargo puts in the same directory as the test executable.
+#[test]
+fn c_program() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let test_exe = std::env::current_exe().unwrap();
+    let lib_dir = test_exe.parent().unwrap();
+    let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("capi_test");
+
+    let status = Command::new("cc")
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-o")
+        .arg(&exe)
+        .arg(root.join("tests/capi_test.c"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-lscryer_prolog_capi")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .status()
+        .expect("failed to run cc");
+
+    assert!(status.success());
+
+    let output = Command::new(&exe).output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        r#"{"result":"true","bindings":{"X":{"atom":"bob"}}}
+{"result":"true","bindings":{"X":"liz"}}
+end
+{"result":"true","bindings":{"R":{"rational":"1/3"},"X":{"functor":"f","args":[{"var":"Y"},[1,2.5,{"atom":"a"}]]},"Y":{"var":"Y"}}}
+end
+end
+{"result":"exception","exception":{"functor":"error","args":[{"atom":"instantiation_error"},{"functor":"/","args":[{"atom":"atom_length"},2]}]}}
+end
+{"result":"true","bindings":{"X":{"atom":"a"}}}
+{"result":"true","bindings":{"X":{"atom":"it's"}}}
+end
+error
+error
+{"result":"true","bindings":{"N":3}}
+end
+"#
+    );
+}
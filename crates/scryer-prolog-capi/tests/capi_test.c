@@ -0,0 +1,63 @@
+/* Exercises the C interface of include/scryer_prolog.h. Each answer is
+ * printed on a line of its own, followed by "end" once a query has no
+ * more answers. */
+
+#include <stdio.h>
+
+#include "scryer_prolog.h"
+
+static void print_answers(ScryerMachine *machine, const char *query)
+{
+    ScryerQuery *q = scryer_run_query(machine, query);
+    char *answer;
+
+    while ((answer = scryer_next_solution(q)) != NULL) {
+        printf("%s\n", answer);
+        scryer_free(answer);
+    }
+
+    printf("end\n");
+    scryer_query_free(q);
+}
+
+int main(void)
+{
+    ScryerMachine *machine = scryer_machine_new();
+    ScryerQuery *q;
+    char *answer;
+
+    if (scryer_consult(machine, "parent(tom, bob). parent(tom, \"liz\").") != 0) {
+        return 1;
+    }
+
+    print_answers(machine, "parent(tom, X)");
+    print_answers(machine, "X = f(Y, [1, 2.5, a]), R is 1 rdiv 3");
+    print_answers(machine, "parent(bob, _)");
+    print_answers(machine, "atom_length(X, _)");
+
+    /* a query freed before its answers are exhausted */
+    q = scryer_run_query(machine, "(X = a ; X = b)");
+    answer = scryer_next_solution(q);
+    printf("%s\n", answer);
+    scryer_free(answer);
+    scryer_query_free(q);
+
+    print_answers(machine, "X = 'it''s'");
+
+    if (scryer_consult(machine, "p :- .") == 0) {
+        return 1;
+    }
+
+    printf("%s\n", scryer_last_error(machine) != NULL ? "error" : "no error");
+
+    q = scryer_run_query(machine, "\xff");
+    printf("%s\n", q == NULL && scryer_last_error(machine) != NULL ? "error" : "no error");
+
+    scryer_machine_free(machine);
+
+    machine = scryer_machine_new_without_boot_image();
+    print_answers(machine, "atom_length(abc, N)");
+    scryer_machine_free(machine);
+
+    return 0;
+}
@@ -6,11 +6,61 @@ fn main() {
     let handler = signal::SigHandler::Handler(handle_sigint);
     unsafe { signal::signal(signal::Signal::SIGINT, handler) }.unwrap();
 
-    let mut wam = machine::Machine::new(
+    // a stand-alone executable starts from the state it carries and
+    // leaves all of its arguments to the program.
+    if let Some(mut wam) = machine::Machine::restore_embedded(
         readline::input_stream(),
         machine::Stream::stdout(),
         machine::Stream::stderr(),
-    );
+    ) {
+        wam.run_top_level();
+        return;
+    }
+
+    // the options the toplevel reads end at "--".
+    let args: Vec<_> = std::env::args()
+        .skip(1)
+        .take_while(|arg| arg != "--")
+        .collect();
+
+    let state = args
+        .iter()
+        .position(|arg| arg == "-x" || arg == "--state")
+        .map(|i| args.get(i + 1));
+
+    let mut wam = match state {
+        Some(Some(path)) => {
+            match machine::Machine::restore(
+                readline::input_stream(),
+                machine::Stream::stdout(),
+                machine::Stream::stderr(),
+                std::path::Path::new(path),
+            ) {
+                Ok(wam) => wam,
+                Err(err) => {
+                    eprintln!("scryer-prolog: {}: {}", path, err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(None) => {
+            eprintln!("scryer-prolog: --state requires a FILE argument");
+            std::process::exit(1);
+        }
+        None if args.iter().any(|arg| arg == "--no-boot-image") => {
+            machine::Machine::without_boot_image(
+                readline::input_stream(),
+                machine::Stream::stdout(),
+                machine::Stream::stderr(),
+            )
+        }
+        None => machine::Machine::with_boot_image(
+            readline::input_stream(),
+            machine::Stream::stdout(),
+            machine::Stream::stderr(),
+        ),
+    };
+
     wam.run_top_level();
 }
 
@@ -179,13 +179,20 @@ pub(crate) enum SystemClauseType {
     DeleteDirectory,
     WorkingDirectory,
     PathCanonical,
+    LoadStatePayloadModule,
     FileTime,
     DeleteAttribute,
     DeleteHeadAttribute,
     DynamicModuleResolution(usize),
     EnqueueAttributedVar,
     FetchGlobalVar,
+    FFICall,
+    FFIDefine,
+    FFILoadLibrary,
     FirstStream,
+    GarbageCollect,
+    GarbageCollectAtoms,
+    Statistics,
     FlushOutput,
     GetByte,
     GetChar,
@@ -212,6 +219,10 @@ pub(crate) enum SystemClauseType {
     HeadIsDynamic,
     InstallSCCCleaner,
     InstallInferenceCounter,
+    InstallTimeLimit,
+    InstallDebugger,
+    InstallProfiler,
+    InstallCoverage,
     LiftedHeapLength,
     LoadLibraryAsStream,
     ModuleExists,
@@ -235,10 +246,14 @@ pub(crate) enum SystemClauseType {
     ReadQueryTerm,
     ReadTerm,
     RedoAttrVarBinding,
-    RemoveCallPolicyCheck,
+    RemoveDebugger,
+    RemoveProfiler,
+    RemoveCoverage,
     RemoveInferenceCounter,
+    RemoveTimeLimit,
     ResetContinuationMarker,
     RestoreCutPolicy,
+    ResumeInterrupted,
     SetCutPoint(RegType),
     SetInput,
     SetOutput,
@@ -250,10 +265,13 @@ pub(crate) enum SystemClauseType {
     CleanUpBlock,
     EraseBall,
     Fail,
+    GetAutoload,
+    GetBacktrace,
     GetBall,
     GetCurrentBlock,
     GetCutPoint,
     GetDoubleQuotes,
+    GetMemoryLimit,
     InstallNewBlock,
     Maybe,
     CpuNow,
@@ -261,10 +279,14 @@ pub(crate) enum SystemClauseType {
     QuotedToken,
     ReadTermFromChars,
     ResetBlock,
+    Rethrow,
     ReturnFromVerifyAttr,
+    SaveBacktrace,
+    SetAutoload,
     SetBall,
     SetCutPointByDefault(RegType),
     SetDoubleQuotes,
+    SetMemoryLimit,
     SetSeed,
     SkipMaxList,
     Sleep,
@@ -310,6 +332,10 @@ pub(crate) enum SystemClauseType {
     SetSTOWithErrorAsUnify,
     HomeDirectory,
     DebugHook,
+    DebugCall,
+    DebuggerCreep,
+    ProfileBody,
+    ProfilePort,
     PopCount
 }
 
@@ -345,6 +371,9 @@ impl SystemClauseType {
             &SystemClauseType::DeleteDirectory => clause_name!("$delete_directory"),
             &SystemClauseType::WorkingDirectory => clause_name!("$working_directory"),
             &SystemClauseType::PathCanonical => clause_name!("$path_canonical"),
+            &SystemClauseType::LoadStatePayloadModule => {
+                clause_name!("$load_state_payload_module")
+            }
             &SystemClauseType::FileTime => clause_name!("$file_time"),
             &SystemClauseType::REPL(REPLCodePtr::AddDiscontiguousPredicate) => {
                 clause_name!("$add_discontiguous_predicate")
@@ -375,6 +404,9 @@ impl SystemClauseType {
             &SystemClauseType::REPL(REPLCodePtr::PushLoadStatePayload) => {
                 clause_name!("$push_load_state_payload")
             }
+            &SystemClauseType::REPL(REPLCodePtr::PushModuleLoadStatePayload) => {
+                clause_name!("$push_module_load_state_payload")
+            }
             &SystemClauseType::REPL(REPLCodePtr::AddInSituFilenameModule) => {
                 clause_name!("$add_in_situ_filename_module")
             }
@@ -382,12 +414,18 @@ impl SystemClauseType {
             &SystemClauseType::REPL(REPLCodePtr::Assertz) => clause_name!("$assertz"),
             &SystemClauseType::REPL(REPLCodePtr::Retract) => clause_name!("$retract_clause"),
             &SystemClauseType::REPL(REPLCodePtr::UseModule) => clause_name!("$use_module"),
+            &SystemClauseType::REPL(REPLCodePtr::PushIncludeContext) => {
+                clause_name!("$push_include_context")
+            }
             &SystemClauseType::REPL(REPLCodePtr::PushLoadContext) => {
                 clause_name!("$push_load_context")
             }
             &SystemClauseType::REPL(REPLCodePtr::PopLoadContext) => {
                 clause_name!("$pop_load_context")
             }
+            &SystemClauseType::REPL(REPLCodePtr::SetClauseLocation) => {
+                clause_name!("$set_clause_location")
+            }
             &SystemClauseType::REPL(REPLCodePtr::PopLoadStatePayload) => {
                 clause_name!("$pop_load_state_payload")
             }
@@ -415,6 +453,9 @@ impl SystemClauseType {
             &SystemClauseType::REPL(REPLCodePtr::DynamicProperty) => {
                 clause_name!("$cpp_dynamic_property")
             }
+            &SystemClauseType::REPL(REPLCodePtr::PredicateClauses) => {
+                clause_name!("$cpp_predicate_clauses")
+            }
             &SystemClauseType::REPL(REPLCodePtr::MultifileProperty) => {
                 clause_name!("$cpp_multifile_property")
             }
@@ -431,6 +472,36 @@ impl SystemClauseType {
             &SystemClauseType::REPL(REPLCodePtr::RemoveModuleExports) => {
                 clause_name!("$remove_module_exports")
             }
+            &SystemClauseType::REPL(REPLCodePtr::MutexLock) => clause_name!("$mutex_lock"),
+            &SystemClauseType::REPL(REPLCodePtr::MutexUnlock) => clause_name!("$mutex_unlock"),
+            &SystemClauseType::REPL(REPLCodePtr::ThreadCreate) => clause_name!("$thread_create"),
+            &SystemClauseType::REPL(REPLCodePtr::ThreadFinish) => clause_name!("$thread_finish"),
+            &SystemClauseType::REPL(REPLCodePtr::ThreadJoin) => clause_name!("$thread_join"),
+            &SystemClauseType::REPL(REPLCodePtr::ThreadPeekMessage) => {
+                clause_name!("$thread_peek_message")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadPreempt) => {
+                clause_name!("$thread_preempt")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadRemoveMessage) => {
+                clause_name!("$thread_remove_message")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadSelf) => clause_name!("$thread_self"),
+            &SystemClauseType::REPL(REPLCodePtr::ThreadSendMessage) => {
+                clause_name!("$thread_send_message")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadWaitJoin) => {
+                clause_name!("$thread_wait_join")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadWaitMessage) => {
+                clause_name!("$thread_wait_message")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::ThreadWaitReadable) => {
+                clause_name!("$thread_wait_readable")
+            }
+            &SystemClauseType::REPL(REPLCodePtr::YieldAnswer) => clause_name!("$yield_answer"),
+            &SystemClauseType::REPL(REPLCodePtr::ForeignCall) => clause_name!("$foreign_call"),
+            &SystemClauseType::REPL(REPLCodePtr::QSaveProgram) => clause_name!("$qsave_program"),
             &SystemClauseType::REPL(REPLCodePtr::AddNonCountedBacktracking) => {
                 clause_name!("$add_non_counted_backtracking")
             }
@@ -441,7 +512,13 @@ impl SystemClauseType {
             &SystemClauseType::DynamicModuleResolution(_) => clause_name!("$module_call"),
             &SystemClauseType::EnqueueAttributedVar => clause_name!("$enqueue_attr_var"),
             &SystemClauseType::FetchGlobalVar => clause_name!("$fetch_global_var"),
+            &SystemClauseType::FFICall => clause_name!("$ffi_call"),
+            &SystemClauseType::FFIDefine => clause_name!("$ffi_define"),
+            &SystemClauseType::FFILoadLibrary => clause_name!("$ffi_load_library"),
             &SystemClauseType::FirstStream => clause_name!("$first_stream"),
+            &SystemClauseType::GarbageCollect => clause_name!("$garbage_collect"),
+            &SystemClauseType::GarbageCollectAtoms => clause_name!("$garbage_collect_atoms"),
+            &SystemClauseType::Statistics => clause_name!("$statistics"),
             &SystemClauseType::FlushOutput => clause_name!("$flush_output"),
             &SystemClauseType::GetByte => clause_name!("$get_byte"),
             &SystemClauseType::GetChar => clause_name!("$get_char"),
@@ -472,6 +549,7 @@ impl SystemClauseType {
             &SystemClauseType::LookupDBRef => clause_name!("$lookup_db_ref"),
             &SystemClauseType::LookupOpDBRef => clause_name!("$lookup_op_db_ref"),
             &SystemClauseType::GetDoubleQuotes => clause_name!("$get_double_quotes"),
+            &SystemClauseType::GetMemoryLimit => clause_name!("$get_memory_limit"),
             //          &SystemClauseType::GetModuleClause => clause_name!("$get_module_clause"),
             &SystemClauseType::GetSCCCleaner => clause_name!("$get_scc_cleaner"),
             &SystemClauseType::Halt => clause_name!("$halt"),
@@ -482,6 +560,10 @@ impl SystemClauseType {
             &SystemClauseType::InstallInferenceCounter => {
                 clause_name!("$install_inference_counter")
             }
+            &SystemClauseType::InstallTimeLimit => clause_name!("$install_time_limit"),
+            &SystemClauseType::InstallDebugger => clause_name!("$install_debugger"),
+            &SystemClauseType::InstallProfiler => clause_name!("$install_profiler"),
+            &SystemClauseType::InstallCoverage => clause_name!("$install_coverage"),
             &SystemClauseType::IsPartialString => clause_name!("$is_partial_string"),
             &SystemClauseType::PartialStringTail => clause_name!("$partial_string_tail"),
             &SystemClauseType::PeekByte => clause_name!("$peek_byte"),
@@ -522,9 +604,13 @@ impl SystemClauseType {
                 clause_name!("$quoted_token")
             }
             &SystemClauseType::RedoAttrVarBinding => clause_name!("$redo_attr_var_binding"),
-            &SystemClauseType::RemoveCallPolicyCheck => clause_name!("$remove_call_policy_check"),
+            &SystemClauseType::RemoveDebugger => clause_name!("$remove_debugger"),
+            &SystemClauseType::RemoveProfiler => clause_name!("$remove_profiler"),
+            &SystemClauseType::RemoveCoverage => clause_name!("$remove_coverage"),
             &SystemClauseType::RemoveInferenceCounter => clause_name!("$remove_inference_counter"),
+            &SystemClauseType::RemoveTimeLimit => clause_name!("$remove_time_limit"),
             &SystemClauseType::RestoreCutPolicy => clause_name!("$restore_cut_policy"),
+            &SystemClauseType::ResumeInterrupted => clause_name!("$resume_interrupted"),
             &SystemClauseType::SetCutPoint(_) => clause_name!("$set_cp"),
             &SystemClauseType::SetInput => clause_name!("$set_input"),
             &SystemClauseType::SetOutput => clause_name!("$set_output"),
@@ -532,13 +618,15 @@ impl SystemClauseType {
             &SystemClauseType::StreamProperty => clause_name!("$stream_property"),
             &SystemClauseType::SetStreamPosition => clause_name!("$set_stream_position"),
             &SystemClauseType::StoreBacktrackableGlobalVar => {
-                clause_name!("$store_back_trackable_global_var")
+                clause_name!("$store_backtrackable_global_var")
             }
             &SystemClauseType::StoreGlobalVar => clause_name!("$store_global_var"),
             &SystemClauseType::InferenceLevel => clause_name!("$inference_level"),
             &SystemClauseType::CleanUpBlock => clause_name!("$clean_up_block"),
             &SystemClauseType::EraseBall => clause_name!("$erase_ball"),
             &SystemClauseType::Fail => clause_name!("$fail"),
+            &SystemClauseType::GetAutoload => clause_name!("$get_autoload"),
+            &SystemClauseType::GetBacktrace => clause_name!("$get_backtrace"),
             &SystemClauseType::GetBall => clause_name!("$get_ball"),
             &SystemClauseType::GetCutPoint => clause_name!("$get_cp"),
             &SystemClauseType::GetCurrentBlock => clause_name!("$get_current_block"),
@@ -549,10 +637,14 @@ impl SystemClauseType {
             &SystemClauseType::ReadTermFromChars => clause_name!("$read_term_from_chars"),
             &SystemClauseType::ResetBlock => clause_name!("$reset_block"),
             &SystemClauseType::ResetContinuationMarker => clause_name!("$reset_cont_marker"),
+            &SystemClauseType::Rethrow => clause_name!("$rethrow"),
             &SystemClauseType::ReturnFromVerifyAttr => clause_name!("$return_from_verify_attr"),
+            &SystemClauseType::SaveBacktrace => clause_name!("$save_backtrace"),
+            &SystemClauseType::SetAutoload => clause_name!("$set_autoload"),
             &SystemClauseType::SetBall => clause_name!("$set_ball"),
             &SystemClauseType::SetCutPointByDefault(_) => clause_name!("$set_cp_by_default"),
             &SystemClauseType::SetDoubleQuotes => clause_name!("$set_double_quotes"),
+            &SystemClauseType::SetMemoryLimit => clause_name!("$set_memory_limit"),
             &SystemClauseType::SkipMaxList => clause_name!("$skip_max_list"),
             &SystemClauseType::Sleep => clause_name!("$sleep"),
             &SystemClauseType::SocketClientOpen => clause_name!("$socket_client_open"),
@@ -604,6 +696,10 @@ impl SystemClauseType {
                 clause_name!("$set_sto_with_error_as_unify")
             }
             &SystemClauseType::DebugHook => clause_name!("$debug_hook"),
+            &SystemClauseType::DebugCall => clause_name!("$debug_call"),
+            &SystemClauseType::DebuggerCreep => clause_name!("$debugger_creep"),
+            &SystemClauseType::ProfileBody => clause_name!("$profile_body"),
+            &SystemClauseType::ProfilePort => clause_name!("$profile_port"),
             &SystemClauseType::PopCount => clause_name!("$popcount"),
         }
     }
@@ -660,6 +756,12 @@ impl SystemClauseType {
             ("$peek_code", 2) => Some(SystemClauseType::PeekCode),
             ("$is_partial_string", 1) => Some(SystemClauseType::IsPartialString),
             ("$fetch_global_var", 2) => Some(SystemClauseType::FetchGlobalVar),
+            ("$ffi_call", 3) => Some(SystemClauseType::FFICall),
+            ("$ffi_define", 3) => Some(SystemClauseType::FFIDefine),
+            ("$ffi_load_library", 1) => Some(SystemClauseType::FFILoadLibrary),
+            ("$garbage_collect", 0) => Some(SystemClauseType::GarbageCollect),
+            ("$garbage_collect_atoms", 0) => Some(SystemClauseType::GarbageCollectAtoms),
+            ("$statistics", 2) => Some(SystemClauseType::Statistics),
             ("$get_byte", 2) => Some(SystemClauseType::GetByte),
             ("$get_char", 2) => Some(SystemClauseType::GetChar),
             ("$get_n_chars", 3) => Some(SystemClauseType::GetNChars),
@@ -684,11 +786,16 @@ impl SystemClauseType {
             ("$get_lh_from_offset", 2) => Some(SystemClauseType::GetLiftedHeapFromOffset),
             ("$get_lh_from_offset_diff", 3) => Some(SystemClauseType::GetLiftedHeapFromOffsetDiff),
             ("$get_double_quotes", 1) => Some(SystemClauseType::GetDoubleQuotes),
+            ("$get_memory_limit", 2) => Some(SystemClauseType::GetMemoryLimit),
             ("$get_scc_cleaner", 1) => Some(SystemClauseType::GetSCCCleaner),
             ("$halt", 1) => Some(SystemClauseType::Halt),
             ("$head_is_dynamic", 2) => Some(SystemClauseType::HeadIsDynamic),
             ("$install_scc_cleaner", 2) => Some(SystemClauseType::InstallSCCCleaner),
             ("$install_inference_counter", 3) => Some(SystemClauseType::InstallInferenceCounter),
+            ("$install_time_limit", 2) => Some(SystemClauseType::InstallTimeLimit),
+            ("$install_debugger", 1) => Some(SystemClauseType::InstallDebugger),
+            ("$install_profiler", 0) => Some(SystemClauseType::InstallProfiler),
+            ("$install_coverage", 0) => Some(SystemClauseType::InstallCoverage),
             ("$lh_length", 1) => Some(SystemClauseType::LiftedHeapLength),
             ("$maybe", 0) => Some(SystemClauseType::Maybe),
             ("$cpu_now", 1) => Some(SystemClauseType::CpuNow),
@@ -700,9 +807,13 @@ impl SystemClauseType {
             ("$op", 3) => Some(SystemClauseType::OpDeclaration),
             ("$open", 7) => Some(SystemClauseType::Open),
             ("$redo_attr_var_binding", 2) => Some(SystemClauseType::RedoAttrVarBinding),
-            ("$remove_call_policy_check", 1) => Some(SystemClauseType::RemoveCallPolicyCheck),
+            ("$remove_debugger", 0) => Some(SystemClauseType::RemoveDebugger),
+            ("$remove_profiler", 1) => Some(SystemClauseType::RemoveProfiler),
+            ("$remove_coverage", 1) => Some(SystemClauseType::RemoveCoverage),
             ("$remove_inference_counter", 2) => Some(SystemClauseType::RemoveInferenceCounter),
+            ("$remove_time_limit", 1) => Some(SystemClauseType::RemoveTimeLimit),
             ("$restore_cut_policy", 0) => Some(SystemClauseType::RestoreCutPolicy),
+            ("$resume_interrupted", 2) => Some(SystemClauseType::ResumeInterrupted),
             ("$set_cp", 1) => Some(SystemClauseType::SetCutPoint(temp_v!(1))),
             ("$set_input", 1) => Some(SystemClauseType::SetInput),
             ("$set_output", 1) => Some(SystemClauseType::SetOutput),
@@ -714,6 +825,8 @@ impl SystemClauseType {
             ("$fail", 0) => Some(SystemClauseType::Fail),
             ("$get_attr_var_queue_beyond", 2) => Some(SystemClauseType::GetAttrVarQueueBeyond),
             ("$get_attr_var_queue_delim", 1) => Some(SystemClauseType::GetAttrVarQueueDelimiter),
+            ("$get_autoload", 1) => Some(SystemClauseType::GetAutoload),
+            ("$get_backtrace", 1) => Some(SystemClauseType::GetBacktrace),
             ("$get_ball", 1) => Some(SystemClauseType::GetBall),
             ("$get_cont_chunk", 3) => Some(SystemClauseType::GetContinuationChunk),
             ("$get_current_block", 1) => Some(SystemClauseType::GetCurrentBlock),
@@ -726,10 +839,14 @@ impl SystemClauseType {
             ("$read_term_from_chars", 2) => Some(SystemClauseType::ReadTermFromChars),
             ("$reset_block", 1) => Some(SystemClauseType::ResetBlock),
             ("$reset_cont_marker", 0) => Some(SystemClauseType::ResetContinuationMarker),
+            ("$rethrow", 2) => Some(SystemClauseType::Rethrow),
             ("$return_from_verify_attr", 0) => Some(SystemClauseType::ReturnFromVerifyAttr),
+            ("$save_backtrace", 1) => Some(SystemClauseType::SaveBacktrace),
+            ("$set_autoload", 1) => Some(SystemClauseType::SetAutoload),
             ("$set_ball", 1) => Some(SystemClauseType::SetBall),
             ("$set_cp_by_default", 1) => Some(SystemClauseType::SetCutPointByDefault(temp_v!(1))),
             ("$set_double_quotes", 1) => Some(SystemClauseType::SetDoubleQuotes),
+            ("$set_memory_limit", 2) => Some(SystemClauseType::SetMemoryLimit),
             ("$set_seed", 1) => Some(SystemClauseType::SetSeed),
             ("$skip_max_list", 4) => Some(SystemClauseType::SkipMaxList),
             ("$sleep", 1) => Some(SystemClauseType::Sleep),
@@ -759,6 +876,7 @@ impl SystemClauseType {
             ("$delete_directory", 1) => Some(SystemClauseType::DeleteDirectory),
             ("$working_directory", 2) => Some(SystemClauseType::WorkingDirectory),
             ("$path_canonical", 2) => Some(SystemClauseType::PathCanonical),
+            ("$load_state_payload_module", 2) => Some(SystemClauseType::LoadStatePayloadModule),
             ("$file_time", 3) => Some(SystemClauseType::FileTime),
             ("$clause_to_evacuable", 2) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::ClauseToEvacuable))
@@ -775,6 +893,9 @@ impl SystemClauseType {
             ("$push_load_state_payload", 1) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::PushLoadStatePayload))
             }
+            ("$push_module_load_state_payload", 2) => Some(SystemClauseType::REPL(
+                REPLCodePtr::PushModuleLoadStatePayload,
+            )),
             ("$add_in_situ_filename_module", 1) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::AddInSituFilenameModule))
             }
@@ -788,6 +909,31 @@ impl SystemClauseType {
             ("$remove_module_exports", 2) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::RemoveModuleExports))
             }
+            ("$mutex_lock", 1) => Some(SystemClauseType::REPL(REPLCodePtr::MutexLock)),
+            ("$mutex_unlock", 1) => Some(SystemClauseType::REPL(REPLCodePtr::MutexUnlock)),
+            ("$thread_create", 4) => Some(SystemClauseType::REPL(REPLCodePtr::ThreadCreate)),
+            ("$thread_finish", 1) => Some(SystemClauseType::REPL(REPLCodePtr::ThreadFinish)),
+            ("$thread_join", 2) => Some(SystemClauseType::REPL(REPLCodePtr::ThreadJoin)),
+            ("$thread_peek_message", 2) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::ThreadPeekMessage))
+            }
+            ("$thread_remove_message", 1) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::ThreadRemoveMessage))
+            }
+            ("$thread_self", 1) => Some(SystemClauseType::REPL(REPLCodePtr::ThreadSelf)),
+            ("$thread_send_message", 2) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::ThreadSendMessage))
+            }
+            ("$thread_wait_join", 1) => Some(SystemClauseType::REPL(REPLCodePtr::ThreadWaitJoin)),
+            ("$thread_wait_message", 1) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::ThreadWaitMessage))
+            }
+            ("$thread_wait_readable", 1) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::ThreadWaitReadable))
+            }
+            ("$yield_answer", 2) => Some(SystemClauseType::REPL(REPLCodePtr::YieldAnswer)),
+            ("$foreign_call", 5) => Some(SystemClauseType::REPL(REPLCodePtr::ForeignCall)),
+            ("$qsave_program", 3) => Some(SystemClauseType::REPL(REPLCodePtr::QSaveProgram)),
             ("$add_non_counted_backtracking", 3) => Some(SystemClauseType::REPL(
                 REPLCodePtr::AddNonCountedBacktracking,
             )),
@@ -816,11 +962,17 @@ impl SystemClauseType {
             ("$pid", 1) => Some(SystemClauseType::PID),
             ("$chars_base64", 4) => Some(SystemClauseType::CharsBase64),
             ("$load_library_as_stream", 3) => Some(SystemClauseType::LoadLibraryAsStream),
+            ("$push_include_context", 2) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::PushIncludeContext))
+            }
             ("$push_load_context", 2) => Some(SystemClauseType::REPL(REPLCodePtr::PushLoadContext)),
             ("$pop_load_state_payload", 1) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::PopLoadStatePayload))
             }
             ("$pop_load_context", 0) => Some(SystemClauseType::REPL(REPLCodePtr::PopLoadContext)),
+            ("$set_clause_location", 0) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::SetClauseLocation))
+            }
             ("$prolog_lc_source", 1) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::LoadContextSource))
             }
@@ -843,6 +995,9 @@ impl SystemClauseType {
             ("$cpp_dynamic_property", 3) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::DynamicProperty))
             }
+            ("$cpp_predicate_clauses", 4) => {
+                Some(SystemClauseType::REPL(REPLCodePtr::PredicateClauses))
+            }
             ("$cpp_multifile_property", 3) => {
                 Some(SystemClauseType::REPL(REPLCodePtr::MultifileProperty))
             }
@@ -856,6 +1011,10 @@ impl SystemClauseType {
             ("$set_sto_with_error_as_unify", 0) => Some(SystemClauseType::SetSTOWithErrorAsUnify),
             ("$home_directory", 1) => Some(SystemClauseType::HomeDirectory),
             ("$debug_hook", 0) => Some(SystemClauseType::DebugHook),
+            ("$debug_call", 2) => Some(SystemClauseType::DebugCall),
+            ("$debugger_creep", 1) => Some(SystemClauseType::DebuggerCreep),
+            ("$profile_body", 1) => Some(SystemClauseType::ProfileBody),
+            ("$profile_port", 2) => Some(SystemClauseType::ProfilePort),
             ("$popcount", 2) => Some(SystemClauseType::PopCount),
             _ => None,
         }
@@ -1158,14 +1158,21 @@ impl<'a, TermMarker: Allocator<'a>> CodeGenerator<TermMarker> {
                 code.push(Line::Choice(self.settings.try_me_else(0)));
             }
 
-            if self.settings.is_extensible {
-                let segment_is_indexed = to_indexing_line(&code_segment[0]).is_some();
+            let segment_is_indexed = to_indexing_line(&code_segment[0]).is_some();
 
+            if self.settings.is_extensible {
                 for clause_index_info in self.skeleton.clauses[skel_lower_bound..].iter_mut() {
                     clause_index_info.clause_start +=
                         clause_start_offset + 2 * (segment_is_indexed as usize);
                     clause_index_info.opt_arg_index_key += clause_start_offset + 1;
                 }
+            } else {
+                // the clause starts are offsets into the segment, past
+                // its choice instruction and indexing code.
+                for clause_index_info in self.skeleton.clauses[skel_lower_bound..].iter_mut() {
+                    clause_index_info.clause_start +=
+                        clause_start_offset + multi_seq as usize + segment_is_indexed as usize;
+                }
             }
 
             self.increment_jmp_by_locs_by(code.len());
@@ -42,6 +42,7 @@ impl<'a> HCPreOrderIterator<'a> {
             | HeapCellValue::Integer(_)
             | HeapCellValue::Rational(_) => Addr::Con(h),
             HeapCellValue::LoadStatePayload(_) => Addr::LoadStatePayload(h),
+            HeapCellValue::Pointer(_) => Addr::Pointer(h),
             HeapCellValue::Stream(_) => Addr::Stream(h),
             HeapCellValue::TcpListener(_) => Addr::TcpListener(h),
         }
@@ -1326,6 +1326,19 @@ impl<'a, Outputter: HCValueOutputter> HCPrinter<'a, Outputter> {
         }
     }
 
+    fn print_pointer(&mut self, iter: &mut HCPreOrderIterator, ptr: *const u8, max_depth: usize) {
+        if self.format_struct(iter, max_depth, 1, clause_name!("$pointer")) {
+            let pointer_root = self.state_stack.pop().unwrap();
+
+            self.state_stack.pop();
+            self.state_stack.pop();
+
+            self.state_stack.push(TokenOrRedirect::RawPtr(ptr));
+            self.state_stack.push(TokenOrRedirect::Open);
+            self.state_stack.push(pointer_root);
+        }
+    }
+
     fn print_stream(&mut self, iter: &mut HCPreOrderIterator, stream: &Stream, max_depth: usize) {
         if let Some(alias) = &stream.options().alias {
             self.print_atom(alias);
@@ -1456,6 +1469,9 @@ impl<'a, Outputter: HCValueOutputter> HCPrinter<'a, Outputter> {
             &HeapCellValue::Rational(ref n) => {
                 self.print_number(Number::Rational(n.clone()), &op);
             }
+            &HeapCellValue::Pointer(ptr) => {
+                self.print_pointer(iter, ptr as *const u8, max_depth);
+            }
             &HeapCellValue::Stream(ref stream) => {
                 self.print_stream(iter, stream, max_depth);
             }
@@ -10,6 +10,7 @@ use indexmap::IndexMap;
 
 use slice_deque::{sdeq, SliceDeque};
 
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::hash::Hash;
 use std::iter::once;
@@ -205,6 +206,8 @@ impl<'a> IndexingCodeMergingPtr<'a> {
     }
 
     fn extend_indexed_choice(&mut self, index: usize) {
+        flatten_subterm_index(self.indexing_code, self.offset);
+
         match &mut self.indexing_code[self.offset] {
             IndexingLine::IndexedChoice(ref mut indexed_choice_instrs)
                 if self.append_or_prepend.is_append() =>
@@ -526,6 +529,9 @@ impl<'a> IndexingCodeMergingPtr<'a> {
 
                     break;
                 }
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(..)) => {
+                    flatten_subterm_index(self.indexing_code, self.offset);
+                }
                 IndexingLine::IndexedChoice(_) | IndexingLine::DynamicIndexedChoice(_) => {
                     // replace this value, at self.offset, with
                     // SwitchOnStructures, and swap this IndexedChoice
@@ -837,6 +843,9 @@ pub(crate) fn remove_structure_index(
                     }
                 }
             }
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(..)) => {
+                flatten_subterm_index(indexing_code, index);
+            }
             IndexingLine::IndexedChoice(ref mut indexed_choice_instrs) => {
                 StaticCodeIndices::remove_instruction_with_offset(indexed_choice_instrs, offset);
 
@@ -1398,6 +1407,8 @@ pub(crate) struct CodeOffsets<I: Indexer> {
     atom_tbl: TabledData<Atom>,
     indices: I,
     optimal_index: usize,
+    // the keys of the subterms of indexed structures, by clause offset.
+    subterm_keys: IndexMap<usize, Vec<Option<ArgKey>>>,
 }
 
 impl<I: Indexer> CodeOffsets<I> {
@@ -1410,6 +1421,7 @@ impl<I: Indexer> CodeOffsets<I> {
             atom_tbl,
             indices,
             optimal_index,
+            subterm_keys: IndexMap::new(),
         }
     }
 
@@ -1463,6 +1475,9 @@ impl<I: Indexer> CodeOffsets<I> {
                     OptArgIndexKey::Structure(self.optimal_index, 0, name.clone(), terms.len());
 
                 self.index_structure(name, terms.len(), index);
+
+                let keys = terms.iter().map(|term| subterm_key(term)).collect();
+                self.subterm_keys.insert(index + 1, keys);
             }
             &Term::Cons(..) | &Term::Constant(_, Constant::String(_)) => {
                 clause_index_info.opt_arg_index_key = OptArgIndexKey::List(self.optimal_index, 0);
@@ -1483,6 +1498,20 @@ impl<I: Indexer> CodeOffsets<I> {
         }
     }
 
+    pub(crate) fn index_arg_key(&mut self, key: &ArgKey, index: usize) {
+        match key {
+            ArgKey::Constant(ref constant) => {
+                self.index_constant(constant, index);
+            }
+            ArgKey::List => {
+                self.index_list(index);
+            }
+            ArgKey::Structure(ref name, arity) => {
+                self.index_structure(name, *arity, index);
+            }
+        }
+    }
+
     pub(crate) fn no_indices(&mut self) -> bool {
         let no_constants = self.indices.constants().is_empty();
         let no_structures = self.indices.structures().is_empty();
@@ -1546,6 +1575,295 @@ impl<I: Indexer> CodeOffsets<I> {
             str_loc,
         )));
 
-        prelude.into_iter().collect()
+        let mut indexing_code = prelude.into_iter().collect();
+        self.index_subterms(&mut indexing_code);
+
+        indexing_code
     }
+
+    // index the clauses sharing a principal functor on the first
+    // subterm that's bound in all of them and not to the same key. The
+    // choice line of the clauses is swapped to the end of the indexing
+    // code, behind a SwitchOnSubterm that falls back on it when the
+    // subterm is unbound.
+    fn index_subterms(&self, indexing_code: &mut Vec<IndexingLine>) {
+        for choice_loc in structure_choice_locs(indexing_code) {
+            let offsets = choice_offsets(&indexing_code[choice_loc]);
+            let keys = offsets
+                .iter()
+                .map(|offset| self.subterm_keys.get(offset))
+                .collect::<Option<Vec<_>>>();
+
+            let keys = match keys {
+                Some(keys) => keys,
+                None => continue,
+            };
+
+            let subterm = (0..keys[0].len()).find(|&i| {
+                keys.iter().all(|clause_keys| clause_keys[i].is_some())
+                    && keys.iter().any(|clause_keys| clause_keys[i] != keys[0][i])
+            });
+
+            let subterm = match subterm {
+                Some(subterm) => subterm,
+                None => continue,
+            };
+
+            let mut code_offsets = CodeOffsets::new(self.atom_tbl.clone(), I::new(), subterm + 1);
+
+            for (offset, clause_keys) in offsets.iter().zip(keys) {
+                if let Some(ref key) = clause_keys[subterm] {
+                    code_offsets.index_arg_key(key, offset - 1);
+                }
+            }
+
+            let mut subterm_code = code_offsets.compute_indices(false);
+            let subterm_loc = indexing_code.len();
+
+            // the SwitchOnSubterm is swapped in for the choice line.
+            let internalize = |ptr: IndexingCodePtr| match ptr {
+                IndexingCodePtr::Internal(o) => {
+                    IndexingCodePtr::Internal(o + subterm_loc - choice_loc)
+                }
+                ptr => ptr,
+            };
+
+            subterm_code[0] = match subterm_code[0] {
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, _, c, l, s)) => {
+                    IndexingLine::from(IndexingInstruction::SwitchOnSubterm(
+                        arg,
+                        IndexingCodePtr::Internal(subterm_loc - choice_loc),
+                        internalize(c),
+                        internalize(l),
+                        internalize(s),
+                    ))
+                }
+                _ => unreachable!(),
+            };
+
+            indexing_code.extend(subterm_code);
+            indexing_code.swap(choice_loc, subterm_loc);
+        }
+    }
+}
+
+// the key of a subterm of an indexed structure, or None if it's a
+// variable.
+fn subterm_key(term: &Term) -> Option<ArgKey> {
+    match term {
+        Term::Clause(_, name, terms, _) => Some(ArgKey::Structure(name.clone(), terms.len())),
+        Term::Cons(..) | Term::Constant(_, Constant::String(_)) => Some(ArgKey::List),
+        Term::Constant(_, constant) => Some(ArgKey::Constant(constant.clone())),
+        _ => None,
+    }
+}
+
+// the locations of the choice lines reached by structures in the
+// indexing code.
+fn structure_choice_locs(indexing_code: &[IndexingLine]) -> Vec<usize> {
+    let str_loc = match &indexing_code[0] {
+        &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(
+            _,
+            _,
+            _,
+            _,
+            IndexingCodePtr::Internal(o),
+        )) => o,
+        _ => return vec![],
+    };
+
+    match &indexing_code[str_loc] {
+        IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(ref structures)) => {
+            structures
+                .values()
+                .filter_map(|ptr| match ptr {
+                    &IndexingCodePtr::Internal(o) => Some(str_loc + o),
+                    _ => None,
+                })
+                .collect()
+        }
+        _ => vec![str_loc],
+    }
+}
+
+fn choice_offsets(line: &IndexingLine) -> Vec<usize> {
+    match line {
+        IndexingLine::IndexedChoice(ref indexed_choice_instrs) => indexed_choice_instrs
+            .iter()
+            .map(|indexed_choice_instr| indexed_choice_instr.offset())
+            .collect(),
+        IndexingLine::DynamicIndexedChoice(ref indexed_choice_instrs) => {
+            indexed_choice_instrs.iter().cloned().collect()
+        }
+        _ => vec![],
+    }
+}
+
+// clauses are added to and removed from the structures indexed by a
+// SwitchOnSubterm through the choice line it falls back on, which is
+// swapped back in for it.
+fn flatten_subterm_index(indexing_code: &mut [IndexingLine], index: usize) {
+    if let IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(
+        _,
+        IndexingCodePtr::Internal(o),
+        ..
+    )) = indexing_code[index]
+    {
+        indexing_code.swap(index, index + o);
+    }
+}
+
+/* Indices on arguments other than the one a SwitchOnTerm indexes are
+ * built on demand, from the compiled clauses of its subsequence, the
+ * first time the subsequence is called with that argument bound and
+ * the indexed argument unbound. The code of an index reaches the
+ * clauses through lines appended after it that jump back to them, and
+ * the keys of the clauses are kept so the code can be rebuilt in place
+ * once clauses are asserted into the subsequence. */
+
+// the principal functor of an argument in the head of a compiled clause.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ArgKey {
+    Constant(Constant),
+    List,
+    Structure(ClauseName, usize),
+}
+
+// the locations of the clauses of the subsequence indexed by the
+// SwitchOnTerm at index_loc, in the order they're tried when its
+// argument is unbound.
+pub(crate) fn indexed_clause_locs(code: &Code, index_loc: usize) -> Vec<usize> {
+    let mut p = match to_indexing_line(&code[index_loc]).map(|lines| &lines[0]) {
+        Some(IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(
+            _,
+            IndexingCodePtr::External(v),
+            ..
+        )))
+        | Some(IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(
+            _,
+            IndexingCodePtr::DynamicExternal(v),
+            ..
+        ))) => index_loc + v,
+        _ => return vec![],
+    };
+
+    let mut clause_locs = vec![];
+
+    loop {
+        match &code[p] {
+            Line::Choice(ChoiceInstruction::TryMeElse(o))
+            | Line::Choice(ChoiceInstruction::RetryMeElse(o))
+            | Line::Choice(ChoiceInstruction::DefaultRetryMeElse(o)) => {
+                clause_locs.push(p + 1);
+
+                if *o == 0 {
+                    break;
+                }
+
+                p += o;
+            }
+            Line::Choice(ChoiceInstruction::DynamicElse(_, _, next_or_fail))
+            | Line::Choice(ChoiceInstruction::DynamicInternalElse(_, _, next_or_fail)) => {
+                clause_locs.push(p + 1);
+
+                match next_or_fail {
+                    NextOrFail::Next(o) if *o > 0 => p += o,
+                    _ => break,
+                }
+            }
+            Line::Choice(ChoiceInstruction::TrustMe(_))
+            | Line::Choice(ChoiceInstruction::DefaultTrustMe(_)) => {
+                clause_locs.push(p + 1);
+                break;
+            }
+            Line::Control(ControlInstruction::RevJmpBy(o)) => {
+                p -= o;
+            }
+            _ => {
+                // a lone clause with no choice instruction.
+                clause_locs.push(p);
+                break;
+            }
+        }
+    }
+
+    clause_locs
+}
+
+// the key of argument arg in the head of the clause at clause_loc, or
+// None if the argument is a variable.
+pub(crate) fn clause_arg_key(code: &Code, clause_loc: usize, arg: usize) -> Option<ArgKey> {
+    // the registers of subterms, which may reuse argument registers.
+    let mut subterm_regs = vec![];
+
+    for line in &code[clause_loc..] {
+        let instr = match line {
+            Line::Control(ControlInstruction::Allocate(_))
+            | Line::Cut(CutInstruction::GetLevel(_)) => continue,
+            Line::Fact(instr) => instr,
+            _ => return None,
+        };
+
+        match instr {
+            &FactInstruction::GetConstant(Level::Shallow, ref constant, RegType::Temp(r))
+                if r == arg =>
+            {
+                return Some(ArgKey::Constant(constant.clone()));
+            }
+            &FactInstruction::GetList(Level::Shallow, RegType::Temp(r))
+            | &FactInstruction::GetPartialString(Level::Shallow, _, RegType::Temp(r), _)
+                if r == arg =>
+            {
+                return Some(ArgKey::List);
+            }
+            &FactInstruction::GetStructure(ref ct, arity, reg)
+                if reg == RegType::Temp(arg) && !subterm_regs.contains(&reg) =>
+            {
+                return Some(ArgKey::Structure(ct.name(), arity));
+            }
+            &FactInstruction::GetValue(_, r) | &FactInstruction::GetVariable(_, r) if r == arg => {
+                return None;
+            }
+            &FactInstruction::UnifyVariable(reg) => {
+                subterm_regs.push(reg);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// an index on an argument other than the one a SwitchOnTerm indexes.
+#[derive(Debug)]
+pub(crate) struct ArgIndex {
+    pub(crate) loc: usize, // the location of the IndexingCode line.
+    // the keys of the clauses, in order, each with the offset from loc
+    // of a line that jumps back to the clause.
+    pub(crate) clauses: VecDeque<(ArgKey, usize)>,
+    pub(crate) is_current: bool, // false if clauses were added since the code was built.
+}
+
+// the indexing code of an argument index. Only called with the
+// argument bound, it fails on variables.
+pub(crate) fn arg_indexing_code<I: Indexer>(
+    arg_index: &ArgIndex,
+    arg: usize,
+    atom_tbl: TabledData<Atom>,
+) -> Vec<IndexingLine> {
+    let mut code_offsets = CodeOffsets::new(atom_tbl, I::new(), arg);
+
+    for (key, offset) in &arg_index.clauses {
+        code_offsets.index_arg_key(key, offset - 1);
+    }
+
+    let mut indexing_code = code_offsets.compute_indices(false);
+
+    if let Some(IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, ref mut v, ..))) =
+        indexing_code.first_mut()
+    {
+        *v = IndexingCodePtr::Fail;
+    }
+
+    indexing_code
 }
@@ -566,6 +566,16 @@ pub(crate) enum IndexingInstruction {
     ),
     SwitchOnConstant(IndexMap<Constant, IndexingCodePtr>),
     SwitchOnStructure(IndexMap<(ClauseName, usize), IndexingCodePtr>),
+    // Switches on the subterm at the first index of the structure
+    // being indexed, like SwitchOnTerm. Unbound subterms fall back on
+    // the choice of all the clauses with the structure's functor.
+    SwitchOnSubterm(
+        usize,
+        IndexingCodePtr,
+        IndexingCodePtr,
+        IndexingCodePtr,
+        IndexingCodePtr,
+    ),
 }
 
 impl IndexingInstruction {
@@ -583,6 +593,18 @@ impl IndexingInstruction {
                     ]
                 )
             }
+            &IndexingInstruction::SwitchOnSubterm(arg, vars, constants, lists, structures) => {
+                functor!(
+                    "switch_on_subterm",
+                    [
+                        integer(arg),
+                        indexing_code_ptr(h, vars),
+                        indexing_code_ptr(h, constants),
+                        indexing_code_ptr(h, lists),
+                        indexing_code_ptr(h, structures)
+                    ]
+                )
+            }
             &IndexingInstruction::SwitchOnConstant(ref constants) => {
                 let mut key_value_list_stub = vec![];
                 let orig_h = h;
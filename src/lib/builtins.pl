@@ -136,6 +136,12 @@ current_prolog_flag(Flag, OccursCheckEnabled) :-
     Flag == occurs_check,
     !,
     '$is_sto_enabled'(OccursCheckEnabled).
+current_prolog_flag(Flag, Limit) :- Flag == heap_limit, !, '$get_memory_limit'(heap, Limit).
+current_prolog_flag(heap_limit, Limit) :- '$get_memory_limit'(heap, Limit).
+current_prolog_flag(Flag, Limit) :- Flag == stack_limit, !, '$get_memory_limit'(stack, Limit).
+current_prolog_flag(stack_limit, Limit) :- '$get_memory_limit'(stack, Limit).
+current_prolog_flag(Flag, Value) :- Flag == autoload, !, '$get_autoload'(Value).
+current_prolog_flag(autoload, Value) :- '$get_autoload'(Value).
 current_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), current_prolog_flag/2)). % 8.17.2.3 b
@@ -175,12 +181,35 @@ set_prolog_flag(occurs_check, error) :-
 set_prolog_flag(double_quotes, Value) :-
     throw(error(domain_error(flag_value, double_quotes + Value),
                 set_prolog_flag/2)). % 8.17.1.3 e
+set_prolog_flag(heap_limit, Limit) :-
+    memory_limit(Limit), !, '$set_memory_limit'(heap, Limit).
+set_prolog_flag(heap_limit, Limit) :-
+    throw(error(domain_error(flag_value, heap_limit + Limit),
+                set_prolog_flag/2)).
+set_prolog_flag(stack_limit, Limit) :-
+    memory_limit(Limit), !, '$set_memory_limit'(stack, Limit).
+set_prolog_flag(stack_limit, Limit) :-
+    throw(error(domain_error(flag_value, stack_limit + Limit),
+                set_prolog_flag/2)).
+set_prolog_flag(autoload, true) :-
+    !, '$set_autoload'(true).
+set_prolog_flag(autoload, false) :-
+    !, '$set_autoload'(false).
+set_prolog_flag(autoload, Value) :-
+    throw(error(domain_error(flag_value, autoload + Value),
+                set_prolog_flag/2)).
 set_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), set_prolog_flag/2)). % 8.17.1.3 d
 set_prolog_flag(Flag, _) :-
     throw(error(type_error(atom, Flag), set_prolog_flag/2)). % 8.17.1.3 c
 
+% the heap_limit and stack_limit flags are sizes in bytes, or
+% infinite. Limits below 1MB would leave too little memory to the
+% toplevel to report the errors they raise.
+memory_limit(infinite).
+memory_limit(Limit) :- integer(Limit), Limit >= 1048576.
+
 % control operators.
 
 fail :- '$fail'.
@@ -548,6 +577,7 @@ parse_read_term_options_(E,_) :-
 
 read_term(Stream, Term, Options) :-
     parse_read_term_options(Options, [Singletons, VariableNames, Variables], read_term/3),
+    '$thread_wait_readable'(Stream),
     '$read_term'(Stream, Term, Singletons, Variables, VariableNames).
 
 read_term(Term, Options) :-
@@ -1312,9 +1342,11 @@ char_code(Char, Code) :-
 
 get_char(C) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$get_char'(S, C).
 
 get_char(S, C) :-
+    '$thread_wait_readable'(S),
     '$get_char'(S, C).
 
 can_be_number(N, PI) :-
@@ -1500,10 +1532,12 @@ flush_output :-
 
 
 get_byte(S, B) :-
+    '$thread_wait_readable'(S),
     '$get_byte'(S, B).
 
 get_byte(B) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$get_byte'(S, B).
 
 
@@ -1533,33 +1567,41 @@ put_code(S, C) :-
 
 get_code(C) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$get_code'(S, C).
 
 get_code(S, C) :-
+    '$thread_wait_readable'(S),
     '$get_code'(S, C).
 
 
 peek_byte(S, B) :-
+    '$thread_wait_readable'(S),
     '$peek_byte'(S, B).
 
 peek_byte(B) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$peek_byte'(S, B).
 
 
 peek_code(C) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$peek_code'(S, C).
 
 peek_code(S, C) :-
+    '$thread_wait_readable'(S),
     '$peek_code'(S, C).
 
 
 peek_char(C) :-
     current_input(S),
+    '$thread_wait_readable'(S),
     '$peek_char'(S, C).
 
 peek_char(S, C) :-
+    '$thread_wait_readable'(S),
     '$peek_char'(S, C).
 
 
@@ -1648,3 +1690,24 @@ nl :-
 
 nl(Stream) :-
     put_char(Stream, '\n').
+
+% the body of the stub clauses of foreign predicates. see
+% machine/foreign.rs. the foreign predicate binds the arguments of
+% Solution, which are unified with those of Goal only once the
+% choice point for the next redo is in place, so that backtracking
+% undoes them.
+'$call_foreign'(Id, Goal) :-
+    '$call_foreign'(Id, Goal, first).
+
+'$call_foreign'(Id, Goal, Redo) :-
+    functor(Goal, Name, Arity),
+    functor(Solution, Name, Arity),
+    '$foreign_call'(Id, Goal, Solution, Redo, Control),
+    (  Control == det ->
+       Goal = Solution
+    ;  Control = throw(Ball) ->
+       throw(Ball)
+    ;  (  Goal = Solution
+       ;  '$call_foreign'(Id, Goal, Control)
+       )
+    ).
@@ -107,6 +107,11 @@ ctype(upper).
 ctype(whitespace).
 
 
+%% reads a key press from the terminal without waiting for RETURN.
+%% if user_input isn't a terminal, as when it's piped, the next
+%% character of user_input is read instead, and get_single_char/1
+%% fails at its end.
+
 get_single_char(C) :-
     (  var(C) -> '$get_single_char'(C)
     ;  atom_length(C, 1) -> '$get_single_char'(C)
@@ -190,6 +195,7 @@ continuation(_, ['\xFFFD\'|T], _) --> [_], decode_utf8(T).
 
 
 read_line_to_chars(Stream, Cs0, Cs) :-
+        '$thread_wait_readable'(Stream),
         '$get_n_chars'(Stream, 1, Char), % this also works for binary streams
         (   Char == [] -> Cs0 = Cs
         ;   Char = [C],
@@ -0,0 +1,253 @@
+/*  Clause coverage, which records the clauses, and the goals in their
+    bodies, run by a goal.
+
+    ?- cover(Goal).
+
+    calls Goal once, as once/1 does, and prints a summary of the
+    clauses and body goals of each source file that ran, followed by
+    the clauses that didn't run, by file and line. Only the files of
+    which at least one clause ran are covered, and the libraries of
+    src/lib are left out of the summary unless named by files/1.
+    cover/2 takes a list of options:
+
+    - lcov(File): also write the coverage to File in the lcov format
+      read by genhtml and most coverage services.
+    - files(Files): cover the source files in Files, either as paths
+      or as library(Name) for the libraries of src/lib. A relative
+      path names each loaded file it ends in.
+    - report(Boolean): print the summary, or not. The default is true.
+
+    coverage_data/1 yields the data of the latest coverage as a list of
+
+    clause(File, Line, Module:Name/Arity, Count, GoalsRun, Goals)
+
+    where File is the file of the clause, as clause_property/2 has it,
+    src/lib/Name.pl for the libraries of src/lib, Count is the number
+    of times the clause was entered, and GoalsRun is the number of its
+    Goals called at least once.
+*/
+
+:- module(cover, [cover/1,
+                  cover/2,
+                  coverage_data/1,
+                  show_coverage/1,
+                  write_lcov/2]).
+
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+
+:- meta_predicate cover(0).
+:- meta_predicate cover(0, +).
+
+cover(Goal) :-
+    cover(Goal, []).
+
+cover(Goal, Options) :-
+    must_be(list, Options),
+    maplist(must_be_cover_option, Options),
+    setup_call_cleanup(start_coverage,
+                       (  call(Goal) ->
+                          Succeeded = true
+                       ;  Succeeded = false
+                       ),
+                       stop_coverage),
+    (  option(report(false), Options, true) ->
+       true
+    ;  show_coverage(Options)
+    ),
+    (  member(lcov(File), Options) ->
+       write_lcov(File, Options)
+    ;  true
+    ),
+    Succeeded == true.
+
+start_coverage :-
+    '$install_coverage'.
+
+stop_coverage :-
+    '$remove_coverage'(Data),
+    bb_put('$coverage_data', Data).
+
+coverage_data(Data) :-
+    (  bb_get('$coverage_data', Data0) ->
+       Data = Data0
+    ;  Data = []
+    ).
+
+show_coverage(Options) :-
+    must_be(list, Options),
+    maplist(must_be_cover_option, Options),
+    covered_clauses(Options, Clauses),
+    clause_files(Clauses, Files),
+    format("~w~t~50|~t~w~16+~t~w~16+~n", ['File', 'Clauses', 'Goals']),
+    format("~`-t~82|~n", []),
+    maplist(show_file_coverage(Clauses), Files),
+    findall(File-Line-PI, member(clause(File, Line, PI, 0, _, _), Clauses), NotRun),
+    (  NotRun == [] ->
+       true
+    ;  format("~nClauses not run:~n", []),
+       maplist(show_clause_not_run, NotRun)
+    ).
+
+show_file_coverage(Clauses, File) :-
+    file_clauses(Clauses, File, FileClauses),
+    foldl(clause_totals, FileClauses, totals(0, 0, 0, 0),
+          totals(ClausesRun, ClauseCount, GoalsRun, GoalCount)),
+    percentage(ClausesRun, ClauseCount, ClausePercentage),
+    percentage(GoalsRun, GoalCount, GoalPercentage),
+    format("~a~t~50|~t~d/~d~9+~t~1f%~7+~t~d/~d~9+~t~1f%~7+~n",
+           [File, ClausesRun, ClauseCount, ClausePercentage,
+            GoalsRun, GoalCount, GoalPercentage]).
+
+show_clause_not_run(File-Line-PI) :-
+    format("~a:~d: ~q~n", [File, Line, PI]).
+
+clause_totals(clause(_, _, _, Count, GoalsRun, Goals),
+              totals(ClausesRun0, ClauseCount0, GoalsRun0, GoalCount0),
+              totals(ClausesRun, ClauseCount, GoalsRun1, GoalCount)) :-
+    (  Count > 0 ->
+       ClausesRun is ClausesRun0 + 1
+    ;  ClausesRun = ClausesRun0
+    ),
+    ClauseCount is ClauseCount0 + 1,
+    GoalsRun1 is GoalsRun0 + GoalsRun,
+    GoalCount is GoalCount0 + Goals.
+
+percentage(_, 0, 100.0) :-
+    !.
+percentage(Part, Total, Percentage) :-
+    Percentage is 100 * Part / Total.
+
+write_lcov(File, Options) :-
+    must_be(atom, File),
+    must_be(list, Options),
+    maplist(must_be_cover_option, Options),
+    covered_clauses(Options, Clauses),
+    clause_files(Clauses, Files),
+    setup_call_cleanup(open(File, write, Stream),
+                       maplist(write_lcov_record(Stream, Clauses), Files),
+                       close(Stream)).
+
+% an lcov record holds the functions of a source file, here its
+% predicates, which begin on the line of their first clause, and the
+% number of times each line beginning a clause ran.
+
+write_lcov_record(Stream, Clauses, File) :-
+    file_clauses(Clauses, File, FileClauses),
+    format(Stream, "TN:~nSF:~a~n", [File]),
+    findall(PI-(Line-Count), member(clause(_, Line, PI, Count, _, _), FileClauses), PIPairs),
+    keysort(PIPairs, SortedPIPairs),
+    group_pairs_by_key(SortedPIPairs, PIGroups),
+    maplist(write_lcov_function(Stream), PIGroups),
+    maplist(write_lcov_function_count(Stream), PIGroups, FunctionsHit),
+    length(PIGroups, FunctionCount),
+    sum_list(FunctionsHit, FunctionHitCount),
+    format(Stream, "FNF:~d~nFNH:~d~n", [FunctionCount, FunctionHitCount]),
+    findall(Line-Count, member(clause(_, Line, _, Count, _, _), FileClauses), LinePairs),
+    keysort(LinePairs, SortedLinePairs),
+    group_pairs_by_key(SortedLinePairs, LineGroups),
+    maplist(write_lcov_line(Stream), LineGroups, LinesHit),
+    length(LineGroups, LineCount),
+    sum_list(LinesHit, LineHitCount),
+    format(Stream, "LF:~d~nLH:~d~nend_of_record~n", [LineCount, LineHitCount]).
+
+write_lcov_function(Stream, PI-[Line-_ | LineCounts]) :-
+    foldl(first_line, LineCounts, Line, FirstLine),
+    format(Stream, "FN:~d,~q~n", [FirstLine, PI]).
+
+write_lcov_function_count(Stream, PI-LineCounts, Hit) :-
+    pairs_values(LineCounts, Counts),
+    sum_list(Counts, Count),
+    format(Stream, "FNDA:~d,~q~n", [Count, PI]),
+    hit(Count, Hit).
+
+write_lcov_line(Stream, Line-Counts, Hit) :-
+    sum_list(Counts, Count),
+    format(Stream, "DA:~d,~d~n", [Line, Count]),
+    hit(Count, Hit).
+
+first_line(Line-_, Line0, FirstLine) :-
+    FirstLine is min(Line, Line0).
+
+hit(Count, Hit) :-
+    (  Count > 0 ->
+       Hit = 1
+    ;  Hit = 0
+    ).
+
+covered_clauses(Options, Clauses) :-
+    coverage_data(Data),
+    clause_files(Data, DataFiles),
+    (  member(files(Files), Options) ->
+       findall(File,
+               (  member(File, DataFiles),
+                  member(File0, Files),
+                  names_source_file(File0, File)
+               ),
+               CoveredFiles)
+    ;  findall(File,
+               (  member(File, DataFiles),
+                  \+ atom_concat('src/lib/', _, File)
+               ),
+               CoveredFiles)
+    ),
+    findall(Clause,
+            (  member(Clause, Data),
+               arg(1, Clause, File),
+               memberchk(File, CoveredFiles)
+            ),
+            Clauses).
+
+names_source_file(library(Name), File) :-
+    !,
+    atom_concat('src/lib/', Name, File0),
+    atom_concat(File0, '.pl', File).
+names_source_file(File, File) :-
+    !.
+names_source_file(File0, File) :-
+    \+ atom_concat('/', _, File0),
+    atom_concat('/', File0, Suffix),
+    atom_concat(_, Suffix, File).
+
+clause_files(Clauses, Files) :-
+    findall(File, member(clause(File, _, _, _, _, _), Clauses), Files0),
+    sort(Files0, Files).
+
+file_clauses(Clauses, File, FileClauses) :-
+    findall(Clause,
+            (  member(Clause, Clauses),
+               arg(1, Clause, File)
+            ),
+            FileClauses).
+
+option(Option, Options, Default) :-
+    (  member(Option0, Options),
+       functor(Option0, Name, 1),
+       functor(Option, Name, 1) ->
+       Option = Option0
+    ;  arg(1, Option, Default)
+    ).
+
+must_be_cover_option(Option) :-
+    (  var(Option) ->
+       instantiation_error(cover/2)
+    ;  Option = lcov(File) ->
+       must_be(atom, File)
+    ;  Option = files(Files) ->
+       must_be(list, Files),
+       maplist(must_be_source_file, Files)
+    ;  Option = report(Boolean) ->
+       must_be(boolean, Boolean)
+    ;  domain_error(cover_option, Option, cover/2)
+    ).
+
+must_be_source_file(File) :-
+    (  var(File) ->
+       instantiation_error(cover/2)
+    ;  File = library(Name) ->
+       must_be(atom, Name)
+    ;  must_be(atom, File)
+    ).
@@ -6,10 +6,23 @@
     op(950, fy, *),
     (*)/1,
     ($)/1,
-    ($-)/1
+    ($-)/1,
+    debug/0,
+    debugging/0,
+    leash/1,
+    nodebug/0,
+    nospy/1,
+    nospyall/0,
+    notrace/0,
+    spy/1,
+    trace/0
 ]).
 
+:- use_module(library(charsio), [get_single_char/1]).
+:- use_module(library(error)).
 :- use_module(library(format), [portray_clause/1]).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
 
 :- meta_predicate *(0).
 :- meta_predicate $(0).
@@ -24,3 +37,382 @@ $(G_0) :-
    portray_clause(exit:G_0).
 
 *(_).
+
+
+%% The debugger, after the box model of Byrd. Each traced call is a
+%% box with the ports call, exit, redo, fail and exception, shown as
+%%
+%%    Call: (Depth) Goal ?
+%%
+%% where Depth counts the boxes the call is nested in. trace/0 creeps,
+%% tracing every call of a predicate that isn't from a library, and
+%% every call of a library predicate from outside of the libraries.
+%% debug/0 leaps, tracing only the predicates that have a spy point,
+%% set with spy/1. At a leashed port, the debugger reads a command:
+%%
+%%   c, SPACE or RETURN: creep
+%%   s: skip to the exit or failure of the call
+%%   l: leap to the next spy point
+%%   r: retry the call
+%%   f: fail the call
+%%   a: abort the query
+%%   n: turn off the debugger and continue
+%%   e: exit Scryer Prolog
+%%   h: display the commands
+%%
+%% Unleashed ports are shown without reading a command. All ports are
+%% leashed until leash/1 changes them.
+
+:- dynamic(spy_point/3).
+:- dynamic(leashed_port/1).
+
+leashed_port(call).
+leashed_port(exit).
+leashed_port(redo).
+leashed_port(fail).
+leashed_port(exception).
+
+trace :-
+    set_debug_mode(creep).
+
+notrace :-
+    (  debug_mode(off) ->
+       true
+    ;  set_debug_mode(leap)
+    ).
+
+debug :-
+    set_debug_mode(leap).
+
+nodebug :-
+    set_debug_mode(off).
+
+spy(Spec) :-
+    spy_specs(Spec, spy/1, Specs),
+    maplist(add_spy_point, Specs),
+    (  debug_mode(off) ->
+       set_debug_mode(leap)
+    ;  update_debugger
+    ).
+
+nospy(Spec) :-
+    spy_specs(Spec, nospy/1, Specs),
+    maplist(remove_spy_point, Specs),
+    update_debugger.
+
+nospyall :-
+    retractall(spy_point(_, _, _)),
+    update_debugger.
+
+leash(Spec) :-
+    leash_ports(Spec, Ports),
+    retractall(leashed_port(_)),
+    maplist(assert_leashed_port, Ports).
+
+debugging :-
+    debug_mode(Mode),
+    (  Mode == off ->
+       write(user_output, 'The debugger is off.\n')
+    ;  Mode == creep ->
+       write(user_output, 'The debugger traces every call.\n')
+    ;  write(user_output, 'The debugger traces calls to spy points.\n')
+    ),
+    findall(PI, spy_point_indicator(PI), PIs),
+    (  PIs == [] ->
+       write(user_output, 'There are no spy points.\n')
+    ;  write(user_output, 'Spy points:\n'),
+       maplist(write_indicator, PIs)
+    ),
+    findall(Port, leashed_port(Port), Ports),
+    write(user_output, 'Leashed ports: '),
+    writeq(user_output, Ports),
+    nl(user_output).
+
+spy_point_indicator(PI) :-
+    spy_point(Module, Name, Arity),
+    (  var(Module) ->
+       PI = Name/Arity
+    ;  PI = Module:Name/Arity
+    ).
+
+write_indicator(PI) :-
+    write(user_output, '   '),
+    writeq(user_output, PI),
+    nl(user_output).
+
+spy_specs(Spec, PI, Specs) :-
+    (  var(Spec) ->
+       throw(error(instantiation_error, PI))
+    ;  Spec = [_|_] ->
+       maplist(spy_spec(PI), Spec, Specs)
+    ;  Spec == [] ->
+       Specs = []
+    ;  spy_spec(PI, Spec, Spec0),
+       Specs = [Spec0]
+    ).
+
+spy_spec(PI, Spec, spy(Module, Name, Arity)) :-
+    (  var(Spec) ->
+       throw(error(instantiation_error, PI))
+    ;  Spec = Module0:Name/Arity ->
+       must_be(atom, Module0),
+       Module = Module0
+    ;  Spec = Name/Arity ->
+       true
+    ;  throw(error(type_error(predicate_indicator, Spec), PI))
+    ),
+    must_be(atom, Name),
+    must_be(integer, Arity),
+    (  Arity < 0 ->
+       throw(error(domain_error(not_less_than_zero, Arity), PI))
+    ;  true
+    ).
+
+add_spy_point(spy(Module, Name, Arity)) :-
+    (  spy_point(Module0, Name, Arity),
+       Module0 == Module ->
+       true
+    ;  assertz(spy_point(Module, Name, Arity))
+    ).
+
+remove_spy_point(spy(Module, Name, Arity)) :-
+    (  var(Module) ->
+       retractall(spy_point(_, Name, Arity))
+    ;  retractall(spy_point(Module, Name, Arity))
+    ).
+
+leash_ports(Spec, Ports) :-
+    findall(Port, leashed_port(Port), Ports0),
+    (  var(Spec) ->
+       throw(error(instantiation_error, leash/1))
+    ;  Spec == all ->
+       Ports = [call, exit, redo, fail, exception]
+    ;  Spec == none ->
+       Ports = []
+    ;  Spec = [_|_] ->
+       maplist(port, Spec),
+       Ports = Spec
+    ;  Spec == [] ->
+       Ports = []
+    ;  Spec = +Port ->
+       port(Port),
+       (  member(Port, Ports0) ->
+          Ports = Ports0
+       ;  Ports = [Port | Ports0]
+       )
+    ;  Spec = -Port ->
+       port(Port),
+       findall(Port0, (member(Port0, Ports0), Port0 \== Port), Ports)
+    ;  port(Spec),
+       Ports = [Spec]
+    ).
+
+port(Port) :-
+    (  var(Port) ->
+       throw(error(instantiation_error, leash/1))
+    ;  member(Port, [call, exit, redo, fail, exception]) ->
+       true
+    ;  throw(error(domain_error(port, Port), leash/1))
+    ).
+
+assert_leashed_port(Port) :-
+    assertz(leashed_port(Port)).
+
+debug_mode(Mode) :-
+    (  bb_get('$debug_mode', Mode0) ->
+       Mode = Mode0
+    ;  Mode = off
+    ).
+
+set_debug_mode(Mode) :-
+    bb_put('$debug_mode', Mode),
+    update_debugger.
+
+% keeps the call policy of the machine in step with the mode of the
+% debugger and its spy points.
+update_debugger :-
+    debug_mode(Mode),
+    (  Mode == off ->
+       '$remove_debugger'
+    ;  findall(spy(Module, Name, Arity), spy_point(Module, Name, Arity), Specs),
+       '$install_debugger'(Specs),
+       (  Mode == creep ->
+          '$debugger_creep'(true)
+       ;  '$debugger_creep'(false)
+       )
+    ).
+
+debug_depth(Depth) :-
+    (  bb_get('$debug_depth', Depth0) ->
+       Depth = Depth0
+    ;  Depth = 0
+    ).
+
+% the box of a traced call of Goal, whose code is Code. B is the
+% choicepoint of retry_point/1, to which r backtracks to call Goal
+% again, and Bf is that of the fail port, to which f backtracks.
+
+'$trace_goal'(Goal, Code) :-
+    debug_depth(Depth0),
+    Depth is Depth0 + 1,
+    '$get_b_value'(B0),
+    retry_point(B),
+    (  '$get_b_value'(Bf),
+       port(call, Goal, Depth, B, Control0),
+       control(Control0, B, Bf),
+       bb_b_put('$debug_depth', Depth),
+       catch(debug_call(Goal, Code),
+             E,
+             exception_port(E, Goal, Depth, B, Bf)),
+       bb_b_put('$debug_depth', Depth0),
+       (  port(exit, Goal, Depth, B, Control),
+          control(Control, B, Bf)
+       ;  port(redo, Goal, Depth, B, Control),
+          control(Control, B, Bf),
+          fail
+       )
+    ;  port(fail, Goal, Depth, B, Control),
+       control(Control, B, B0),
+       '$set_cp'(B0),
+       fail
+    ).
+
+retry_point(B) :-
+    '$get_b_value'(B).
+retry_point(B) :-
+    bb_get('$debug_retry', true),
+    bb_put('$debug_retry', false),
+    retry_point(B).
+
+debug_call(_:Goal, Code) :-
+    '$debug_call'(Goal, Code).
+
+control(continue, _, _).
+control(retry, B, _) :-
+    bb_put('$debug_retry', true),
+    '$set_cp'(B),
+    fail.
+control(fail, _, Bf) :-
+    '$set_cp'(Bf),
+    fail.
+
+exception_port(E, Goal, Depth, B, Bf) :-
+    (  E = error('$interrupt_thrown', _) ->
+       throw(E)
+    ;  port(exception(E), Goal, Depth, B, Control),
+       control(Control, B, Bf),
+       throw(E)
+    ).
+
+port(Port, Goal, Depth, B, Control) :-
+    (  shows_port(Goal, B) ->
+       bb_put('$debug_skip', none),
+       write_port(Port, Goal, Depth),
+       (  port_name(Port, Name),
+          leashed_port(Name) ->
+          write(user_output, ' ? '),
+          flush_output(user_output),
+          read_command(Port, B, Control)
+       ;  nl(user_output),
+          Control = continue
+       )
+    ;  Control = continue
+    ).
+
+shows_port(Module:Goal, B) :-
+    (  debug_mode(creep) ->
+       true
+    ;  bb_get('$debug_skip', B) ->
+       true
+    ;  debug_mode(leap),
+       functor(Goal, Name, Arity),
+       spy_point(Module0, Name, Arity),
+       (  var(Module0) ->
+          true
+       ;  Module0 == Module
+       )
+    ).
+
+port_name(exception(_), exception) :- !.
+port_name(Port, Port).
+
+write_port(Port, Module:Goal, Depth) :-
+    port_label(Port, Label),
+    write(user_output, '   '),
+    write(user_output, Label),
+    write(user_output, ': ('),
+    write(user_output, Depth),
+    write(user_output, ') '),
+    (  Module == user ->
+       write_term(user_output, Goal, [quoted(true), max_depth(10)])
+    ;  write_term(user_output, Module:Goal, [quoted(true), max_depth(10)])
+    ),
+    (  Port = exception(E) ->
+       write(user_output, ' raises '),
+       write_term(user_output, E, [quoted(true), max_depth(10)])
+    ;  true
+    ).
+
+port_label(call, 'Call').
+port_label(exit, 'Exit').
+port_label(redo, 'Redo').
+port_label(fail, 'Fail').
+port_label(exception(_), 'Exception').
+
+read_command(Port, B, Control) :-
+    get_single_char(C),
+    (  member(C, [c, ' ', '\n']) ->
+       nl(user_output),
+       set_debug_mode(creep),
+       Control = continue
+    ;  C = s ->
+       nl(user_output),
+       (  member(Port, [call, redo]) ->
+          bb_put('$debug_skip', B),
+          set_debug_mode(leap)
+       ;  set_debug_mode(creep)
+       ),
+       Control = continue
+    ;  C = l ->
+       nl(user_output),
+       set_debug_mode(leap),
+       Control = continue
+    ;  C = r ->
+       nl(user_output),
+       set_debug_mode(creep),
+       Control = retry
+    ;  C = f ->
+       nl(user_output),
+       set_debug_mode(creep),
+       (  Port == fail ->
+          Control = continue
+       ;  Control = fail
+       )
+    ;  C = a ->
+       nl(user_output),
+       throw(error('$interrupt_thrown', repl))
+    ;  C = n ->
+       nl(user_output),
+       set_debug_mode(off),
+       Control = continue
+    ;  C = e ->
+       nl(user_output),
+       halt
+    ;  member(C, [h, ?]) ->
+       debug_help_message,
+       write(user_output, ' ? '),
+       read_command(Port, B, Control)
+    ;  read_command(Port, B, Control)
+    ).
+
+debug_help_message :-
+    nl(user_output), nl(user_output),
+    write(user_output, '"c", SPACE or RETURN: creep\n'),
+    write(user_output, '"s": skip to the exit or failure of the call\n'),
+    write(user_output, '"l": leap to the next spy point\n'),
+    write(user_output, '"r": retry the call\n'),
+    write(user_output, '"f": fail the call\n'),
+    write(user_output, '"a": abort the query\n'),
+    write(user_output, '"n": turn off the debugger and continue\n'),
+    write(user_output, '"e": exit Scryer Prolog\n'),
+    write(user_output, '"h": display this help message\n\n').
@@ -0,0 +1,104 @@
+%% Calling C functions in shared libraries. A library is loaded with
+%% use_foreign_library/1, after which foreign/3 declares the functions
+%% it defines as predicates of the calling module. For example,
+%%
+%%   :- use_foreign_library("libm.so.6").
+%%   :- foreign(pow, [double, double], double).
+%%
+%% defines pow/3, so that pow(2, 10, X) gives X = 1024.0. As
+%% directives, use_foreign_library/1 and foreign/3 are handled by the
+%% loader, which compiles the predicates foreign/3 defines. Called as
+%% a goal, foreign/3 asserts them instead, in the module being loaded
+%% or else in user.
+%%
+%% The argument types are int, long, double, string (a char*, passed
+%% as an atom or a string), pointer (a C pointer returned by another
+%% foreign function) and buffer(Size), a zeroed buffer of Size bytes
+%% whose contents are unified with the argument as a string once the
+%% function returns. The return types are the same, save for buffer,
+%% and void, in which case the predicate has no result argument.
+%% Variadic functions such as printf can't be declared. Foreign
+%% libraries are only supported on Unix.
+
+:- module(ffi, [ffi_call/3,
+                foreign/3,
+                use_foreign_library/1]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+:- dynamic(foreign_function/3).
+
+use_foreign_library(Library) :-
+    (  var(Library) ->
+       throw(error(instantiation_error, use_foreign_library/1))
+    ;  '$ffi_load_library'(Library) ->
+       true
+    ;  throw(error(existence_error(source_sink, Library), use_foreign_library/1))
+    ).
+
+arg_type(int).
+arg_type(long).
+arg_type(double).
+arg_type(string).
+arg_type(pointer).
+arg_type(buffer(Size)) :-
+    integer(Size),
+    Size > 0.
+
+return_type(int).
+return_type(long).
+return_type(double).
+return_type(string).
+return_type(pointer).
+return_type(void).
+
+must_be_ffi_type(Kind, Type) :-
+    (  var(Type) ->
+       throw(error(instantiation_error, foreign/3))
+    ;  call(Kind, Type) ->
+       true
+    ;  throw(error(domain_error(foreign_type, Type), foreign/3))
+    ).
+
+foreign_clause(Name, ArgTypes, RetType, (Head :- ffi:ffi_call(Name, Args, Result))) :-
+    must_be(atom, Name),
+    must_be(list, ArgTypes),
+    maplist(must_be_ffi_type(arg_type), ArgTypes),
+    must_be_ffi_type(return_type, RetType),
+    (  '$ffi_define'(Name, ArgTypes, RetType) ->
+       true
+    ;  throw(error(existence_error(foreign_function, Name), foreign/3))
+    ),
+    retractall(foreign_function(Name, _, _)),
+    assertz(foreign_function(Name, ArgTypes, RetType)),
+    length(ArgTypes, N),
+    length(Args, N),
+    (  RetType == void ->
+       HeadArgs = Args
+    ;  append(Args, [Result], HeadArgs)
+    ),
+    Head =.. [Name | HeadArgs].
+
+foreign(Name, ArgTypes, RetType) :-
+    foreign_clause(Name, ArgTypes, RetType, (Head :- Body)),
+    (  prolog_load_context(module, Module) ->
+       true
+    ;  Module = user
+    ),
+    retractall(Module:Head),
+    assertz(Module:(Head :- Body)).
+
+ffi_call(Name, Args, Result) :-
+    must_be(atom, Name),
+    must_be(list, Args),
+    (  foreign_function(Name, ArgTypes, _) ->
+       true
+    ;  throw(error(existence_error(foreign_function, Name), ffi_call/3))
+    ),
+    length(ArgTypes, N),
+    (  length(Args, N) ->
+       true
+    ;  throw(error(domain_error(foreign_arguments, Args), ffi_call/3))
+    ),
+    '$ffi_call'(Name, Args, Result).
@@ -69,6 +69,7 @@
 :- use_module(library(iso_ext)).
 :- use_module(library(time)).
 :- use_module(library(crypto)).
+:- use_module(library(threads)).
 
 % Module prefix workaround with meta_predicate
 http_listen(Port, Module:Handlers0) :-
@@ -87,9 +88,15 @@ http_listen_(Port, Handlers) :-
     format("Listening at port ~d\n", [Port]),
     accept_loop(Socket, Handlers).
 
-% Server loop
+% Server loop. Each connection is served by a thread of its own, so a
+% slow client or handler doesn't hold up the others.
 accept_loop(Socket, Handlers) :-
-    setup_call_cleanup(socket_server_accept(Socket, _Client, Stream, [type(binary)]),
+    socket_server_accept(Socket, _Client, Stream, [type(binary)]),
+    thread_create(serve_client(Stream, Handlers), _, [detached(true)]),
+    accept_loop(Socket, Handlers).
+
+serve_client(Stream, Handlers) :-
+    setup_call_cleanup(true,
         (
             read_header_lines(Stream, Lines),
             [Request|Headers] = Lines,
@@ -120,8 +127,7 @@ accept_loop(Socket, Handlers) :-
                     )
             ),
             ! % Remove
-        ), close(Stream)),
-    accept_loop(Socket, Handlers).
+        ), close(Stream)).
 
 match_handler(Handlers, Method, "/", Handler) :-
     member(H, Handlers),
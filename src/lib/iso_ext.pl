@@ -7,12 +7,19 @@
                     bb_get/2,
                     bb_put/2,
                     call_cleanup/2,
+                    catch_with_backtrace/3,
                     call_with_inference_limit/3,
+                    call_with_time_limit/2,
+                    call_with_time_limit/3,
                     forall/2,
+                    garbage_collect/0,
+                    garbage_collect_atoms/0,
                     partial_string/1,
                     partial_string/3,
                     partial_string_tail/2,
                     setup_call_cleanup/3,
+                    statistics/0,
+                    statistics/2,
                     call_nth/2,
                     variant/2,
                     copy_term_nat/2]).
@@ -32,6 +39,63 @@
 forall(Generate, Test) :-
     \+ (Generate, \+ Test).
 
+garbage_collect :-
+    '$garbage_collect'.
+
+%% atoms are released when the last reference to them is dropped.
+%% garbage_collect_atoms/0 sweeps the atom table of any entries left
+%% without references, as is done after each heap collection.
+
+garbage_collect_atoms :-
+    '$garbage_collect_atoms'.
+
+%% engine statistics. runtime and walltime are [Total, SinceLast]
+%% lists in milliseconds, cputime is in seconds and heapused,
+%% stackused and trailused are in bytes.
+
+statistics(Key, Value) :-
+    (  var(Key) ->
+       instantiation_error(statistics/2)
+    ;  statistics_key(Key) ->
+       '$statistics'(Key, Value)
+    ;  atom(Key) ->
+       domain_error(statistics_key, Key, statistics/2)
+    ;  type_error(atom, Key, statistics/2)
+    ).
+
+statistics_key(atoms).
+statistics_key(choicepoints).
+statistics_key(clauses).
+statistics_key(cputime).
+statistics_key(heapused).
+statistics_key(inferences).
+statistics_key(modules).
+statistics_key(runtime).
+statistics_key(stackused).
+statistics_key(trailused).
+statistics_key(walltime).
+
+statistics :-
+    '$statistics'(cputime, CPUTime),
+    '$statistics'(inferences, Inferences),
+    '$statistics'(heapused, Heap),
+    '$statistics'(stackused, Stack),
+    '$statistics'(trailused, Trail),
+    '$statistics'(choicepoints, ChoicePoints),
+    '$statistics'(atoms, Atoms),
+    '$statistics'(clauses, Clauses),
+    '$statistics'(modules, Modules),
+    Seconds is round(CPUTime * 1000) / 1000.0,
+    write('% '), write(Seconds), write(' seconds CPU time for '),
+    write(Inferences), write(' inferences'), nl,
+    write('% '), write(Heap), write(' bytes of heap, '),
+    write(Stack), write(' bytes of stack and '),
+    write(Trail), write(' bytes of trail in use'), nl,
+    write('% '), write(ChoicePoints), write(' choicepoints'), nl,
+    write('% '), write(Atoms), write(' atoms, '),
+    write(Clauses), write(' clauses and '),
+    write(Modules), write(' modules'), nl.
+
 %% (non-)backtrackable global variables.
 
 bb_put(Key, Value) :-
@@ -86,9 +150,10 @@ scc_helper(C, G, Bb) :-
 scc_helper(_, _, Bb) :-
     '$reset_block'(Bb),
     '$get_ball'(Ball),
+    '$save_backtrace'(Backtrace),
     '$erase_ball',
     '$call_with_default_policy'(run_cleaners_with_handling),
-    '$call_with_default_policy'(throw(Ball)).
+    '$rethrow'(Ball, Backtrace).
 scc_helper(_, _, _) :-
     '$get_cp'(Cp),
     '$call_with_default_policy'(run_cleaners_without_handling(Cp)),
@@ -127,8 +192,7 @@ end_block(B, _Bb, NBb, L) :-
 
 :- non_counted_backtracking handle_ile/3.
 handle_ile(B, inference_limit_exceeded(B), inference_limit_exceeded) :- !.
-handle_ile(B, E, _) :-
-    '$remove_call_policy_check'(B),
+handle_ile(_, E, _) :-
     '$call_with_default_policy'(throw(E)).
 
 :- meta_predicate call_with_inference_limit(0, ?, ?).
@@ -136,8 +200,7 @@ handle_ile(B, E, _) :-
 call_with_inference_limit(G, L, R) :-
     '$get_current_block'(Bb),
     '$get_b_value'(B),
-    '$call_with_default_policy'(call_with_inference_limit(G, L, R, Bb, B)),
-    '$remove_call_policy_check'(B).
+    '$call_with_default_policy'(call_with_inference_limit(G, L, R, Bb, B)).
 
 :- non_counted_backtracking call_with_inference_limit/5.
 call_with_inference_limit(G, L, R, Bb, B) :-
@@ -154,12 +217,78 @@ call_with_inference_limit(_, _, R, Bb, B) :-
     (  '$get_ball'(Ball),
        '$get_level'(Cp),
        '$set_cp_by_default'(Cp)
-    ;  '$remove_call_policy_check'(B),
-       '$fail'
+    ;  '$fail'
     ),
     '$erase_ball',
     '$call_with_default_policy'(handle_ile(B, Ball, R)).
 
+% call_with_time_limit
+
+% Goal is called as by once/1. once Time seconds have passed, the
+% exception is thrown at the next call or redo of a predicate, which
+% interrupts Goal unless it's busy in a long running builtin such as
+% sleep/1. inner limits are removed as the exception unwinds them.
+
+:- meta_predicate call_with_time_limit(?, 0).
+
+call_with_time_limit(Time, Goal) :-
+    call_with_time_limit(Time, Goal, time_limit_exceeded, call_with_time_limit/2).
+
+:- meta_predicate call_with_time_limit(?, 0, ?).
+
+call_with_time_limit(Time, Goal, Context) :-
+    call_with_time_limit(Time, Goal, time_limit_exceeded(Context), call_with_time_limit/3).
+
+call_with_time_limit(Time, Goal, Exception, PI) :-
+    builtins:must_be_number(Time, PI),
+    (  Time < 0 ->
+       domain_error(not_less_than_zero, Time, PI)
+    ;  true
+    ),
+    Micros is ceiling(Time * 1000000),
+    '$get_b_value'(B),
+    catch(setup_call_cleanup(install_time_limit(Micros, B),
+                             once(Goal),
+                             remove_time_limit(B)),
+          '$time_limit_exceeded'(B),
+          throw(Exception)).
+
+install_time_limit(Micros, B) :-
+    '$install_time_limit'(Micros, B).
+
+remove_time_limit(B) :-
+    '$remove_time_limit'(B).
+
+% catch_with_backtrace
+
+% as catch/3, except that an error term error(Formal, Context) is
+% caught as error(Formal, backtrace(Context, Frames)), where Frames
+% are the frames active where it was thrown, innermost first, as
+% frame(Module:Name/Arity, Clause, File:Line). Clause is the number
+% of the clause of the frame, and either it or File:Line is none if
+% it's unknown. balls that aren't caught are rethrown unchanged.
+
+:- meta_predicate catch_with_backtrace(0, ?, 0).
+
+catch_with_backtrace(G, C, R) :-
+    '$get_current_block'(Bb),
+    '$call_with_default_policy'(catch_with_backtrace(G, C, R, Bb)).
+
+:- non_counted_backtracking catch_with_backtrace/4.
+catch_with_backtrace(G, _, _, Bb) :-
+    '$install_new_block'(NBb),
+    call(G),
+    '$call_with_default_policy'(builtins:end_block(Bb, NBb)).
+catch_with_backtrace(_, C, R, Bb) :-
+    '$reset_block'(Bb),
+    '$get_ball'(Ball0),
+    (  Ball0 = error(Formal, Context) ->
+       '$get_backtrace'(Frames),
+       Ball = error(Formal, backtrace(Context, Frames))
+    ;  Ball = Ball0
+    ),
+    '$call_with_default_policy'(builtins:handle_ball(Ball, C, R)).
+
 variant(X, Y) :- '$variant'(X, Y).
 
 partial_string(String, L, L0) :-
@@ -0,0 +1,171 @@
+/*  A profiler, which counts the ports of the predicates called by a
+    goal, after the box model of library(debug), and measures the CPU
+    time spent in them.
+
+    ?- profile(Goal).
+
+    calls Goal once, as once/1 does, and prints a report of the
+    predicates it called, sorted by the CPU time spent in each of them
+    outside of the predicates it called in turn. Calls from one library
+    predicate to another aren't profiled. profile/2 takes a list of
+    options:
+
+    - sort_by(Field): sort the report by Field, one of calls, redos,
+      exits, fails, self and cumulative. The default is self.
+    - top(N): show the first N predicates of the report. The default
+      is 25.
+    - report(Boolean): print the report, or not. The default is true.
+
+    profile_data/1 yields the data of the latest profile as a list of
+
+    profile(Module:Name/Arity, Calls, Redos, Exits, Fails, Self, Cumulative)
+
+    where Self and Cumulative are seconds of CPU time, Cumulative
+    counting recursive calls of the predicate once.
+
+    A last call made by a predicate that has left no choicepoints runs
+    in the box of the call of that predicate, so that tail recursion
+    still runs in constant space. The last call exits or fails with
+    that box, and a redo of the box isn't counted for it.
+*/
+
+:- module(profile, [profile/1,
+                    profile/2,
+                    profile_data/1,
+                    show_profile/1]).
+
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+
+:- meta_predicate profile(0).
+:- meta_predicate profile(0, +).
+
+profile(Goal) :-
+    profile(Goal, []).
+
+profile(Goal, Options) :-
+    must_be(list, Options),
+    maplist(must_be_profile_option, Options),
+    setup_call_cleanup(start_profiler,
+                       (  call(Goal) ->
+                          Succeeded = true
+                       ;  Succeeded = false
+                       ),
+                       stop_profiler),
+    (  option(report(false), Options, true) ->
+       true
+    ;  show_profile(Options)
+    ),
+    Succeeded == true.
+
+start_profiler :-
+    '$install_profiler'.
+
+stop_profiler :-
+    '$remove_profiler'(Data),
+    bb_put('$profile_data', Data).
+
+profile_data(Data) :-
+    (  bb_get('$profile_data', Data0) ->
+       Data = Data0
+    ;  Data = []
+    ).
+
+show_profile(Options) :-
+    must_be(list, Options),
+    maplist(must_be_profile_option, Options),
+    option(sort_by(Field), Options, self),
+    option(top(N), Options, 25),
+    profile_data(Data),
+    field_keys(Data, Field, Pairs),
+    keysort(Pairs, SortedPairs),
+    reverse(SortedPairs, DescendingPairs),
+    pairs_values(DescendingPairs, SortedData),
+    length(SortedData, Count),
+    Shown is min(N, Count),
+    length(ShownData, Shown),
+    append(ShownData, _, SortedData),
+    format("~w~t~40|~t~w~8+~t~w~8+~t~w~8+~t~w~8+~t~w~10+~t~w~12+~n",
+           ['Predicate', 'Calls', 'Redos', 'Exits', 'Fails', 'Self', 'Cumulative']),
+    format("~`-t~86|~n", []),
+    maplist(show_profile_line, ShownData).
+
+show_profile_line(profile(PI, Calls, Redos, Exits, Fails, Self, Cumulative)) :-
+    format("~q~t~40|~t~d~8+~t~d~8+~t~d~8+~t~d~8+~t~3f~10+~t~3f~12+~n",
+           [PI, Calls, Redos, Exits, Fails, Self, Cumulative]).
+
+field_keys([], _, []).
+field_keys([Profile | Profiles], Field, [Key-Profile | Pairs]) :-
+    field_key(Field, Profile, Key),
+    field_keys(Profiles, Field, Pairs).
+
+field_key(calls, profile(_, Calls, _, _, _, _, _), Calls).
+field_key(redos, profile(_, _, Redos, _, _, _, _), Redos).
+field_key(exits, profile(_, _, _, Exits, _, _, _), Exits).
+field_key(fails, profile(_, _, _, _, Fails, _, _), Fails).
+field_key(self, profile(_, _, _, _, _, Self, _), Self).
+field_key(cumulative, profile(_, _, _, _, _, _, Cumulative), Cumulative).
+
+option(Option, Options, Default) :-
+    (  member(Option0, Options),
+       functor(Option0, Name, 1),
+       functor(Option, Name, 1) ->
+       Option = Option0
+    ;  arg(1, Option, Default)
+    ).
+
+must_be_profile_option(Option) :-
+    (  var(Option) ->
+       instantiation_error(profile/2)
+    ;  Option = sort_by(Field) ->
+       must_be(atom, Field),
+       (  field_key(Field, profile(_, _, _, _, _, _, _), _) ->
+          true
+       ;  domain_error(profile_field, Field, profile/2)
+       )
+    ;  Option = top(N) ->
+       must_be(integer, N),
+       (  N >= 0 ->
+          true
+       ;  domain_error(not_less_than_zero, N, profile/2)
+       )
+    ;  Option = report(Boolean) ->
+       must_be(boolean, Boolean)
+    ;  domain_error(profile_option, Option, profile/2)
+    ).
+
+% the box of a profiled call of Goal, whose code is Code. if Goal
+% exits without leaving choicepoints, the box is left for good,
+% without a redo port.
+
+'$profile_goal'(Goal, Code) :-
+    '$get_b_value'(B0),
+    '$profile_port'(call, Goal),
+    (  '$get_b_value'(B1),
+       catch(profile_call(Goal, Code),
+             E,
+             exception_port(E, Goal)),
+       '$get_b_value'(B),
+       (  B == B1 ->
+          '$set_cp'(B0),
+          '$profile_port'(exit, Goal)
+       ;  (  '$profile_port'(exit, Goal)
+          ;  '$profile_port'(redo, Goal),
+             fail
+          )
+       )
+    ;  '$profile_port'(fail, Goal),
+       fail
+    ).
+
+exception_port(E, Goal) :-
+    '$profile_port'(exception, Goal),
+    throw(E).
+
+profile_call(_:Goal, Code) :-
+    '$get_b_value'(B),
+    '$profile_body'(B),
+    '$debug_call'(Goal, Code).
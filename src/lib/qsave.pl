@@ -0,0 +1,80 @@
+%% Saved states. qsave_program(File) saves the state of the machine
+%% to File: the code of every module, the clauses asserted so far, the
+%% operators and flags, the values of global variables set by
+%% bb_put/2, and the foreign libraries and functions of
+%% library(ffi). Started as
+%%
+%%   scryer-prolog -x File
+%%
+%% Scryer Prolog restores that state in place of loading its
+%% libraries, and goes on with the remaining arguments as usual, so
+%% that an application needn't be consulted again on each run. A state
+%% can only be restored by the build of Scryer Prolog that saved it.
+%%
+%% Streams and threads aren't saved, and neither are the values of
+%% global variables set by bb_b_put/2. Saving a global variable whose
+%% value holds a stream, a socket or a C pointer is a
+%% representation_error(saved_state). Failing to write File for lack
+%% of permission is a permission_error(output, source_sink, File), and
+%% any other I/O error a system_error(io_error(File, Message)), where
+%% the atom Message describes it.
+%%
+%% qsave_program/2 recognises the option stand_alone(Bool). If Bool is
+%% true, File is a copy of the running executable that carries the
+%% state, and runs main/0 of user when started, with argv/1 holding
+%% all of its command line arguments. It halts once main/0 does, with
+%% exit code 1 if main/0 fails or throws an exception. Other options
+%% are ignored.
+
+:- module(qsave, [qsave_program/1,
+                  qsave_program/2]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+qsave_program(File) :-
+    qsave_program(File, []).
+
+qsave_program(File, Options) :-
+    must_be(list, Options),
+    (  var(File) ->
+       throw(error(instantiation_error, qsave_program/2))
+    ;  true
+    ),
+    (  member(stand_alone(StandAlone), Options) ->
+       must_be(boolean, StandAlone)
+    ;  StandAlone = false
+    ),
+    (  StandAlone == true,
+       \+ current_predicate(main/0) ->
+       throw(error(existence_error(procedure, main/0), qsave_program/2))
+    ;  true
+    ),
+    % errors in opening File for writing are those of open/3.
+    open(File, write, Stream),
+    close(Stream),
+    % the command line arguments are those of the process that
+    % restores the state.
+    findall(Argv, '$toplevel':'$argv'(Argv), Argvs),
+    retractall('$toplevel':'$argv'(_)),
+    (  StandAlone == true ->
+       assertz('$toplevel':stand_alone)
+    ;  true
+    ),
+    (  '$qsave_program'(File, StandAlone, IOError) ->
+       Saved = true
+    ;  Saved = false
+    ),
+    retractall('$toplevel':stand_alone),
+    maplist(assert_argv, Argvs),
+    (  Saved == false ->
+       throw(error(representation_error(saved_state), qsave_program/2))
+    ;  IOError == [] ->
+       true
+    ;  IOError == permission_denied ->
+       throw(error(permission_error(output, source_sink, File), qsave_program/2))
+    ;  throw(error(system_error(io_error(File, IOError)), qsave_program/2))
+    ).
+
+assert_argv(Argv) :-
+    assertz('$toplevel':'$argv'(Argv)).
@@ -56,6 +56,7 @@ socket_server_accept(ServerSocket, Client, Stream, Options) :-
     builtins:parse_stream_options(Options,
                                   [Alias, EOFAction, Reposition, Type],
                                   socket_server_accept/4),
+    '$thread_wait_readable'(ServerSocket),
     '$socket_server_accept'(ServerSocket, Client, Stream, Alias, EOFAction, Reposition, Type).
 
 
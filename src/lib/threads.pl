@@ -0,0 +1,123 @@
+%% Prolog threads. Threads share the program and the dynamic
+%% database, and exchange terms through their message queues.
+%%
+%% Threads are scheduled cooperatively on a single OS thread, and are
+%% preempted only between predicate calls. A thread blocked on a
+%% message, a mutex or a join is parked until another thread wakes it.
+%% So is a thread accepting a connection on a server socket, or
+%% reading from a socket stream through get_char/2 and friends,
+%% read_term/3 or read_line_to_chars/3, until input arrives. Other
+%% reads (from user_input, say, or by read/2) block every thread until
+%% they complete, as does a read_term/3 whose term arrives in pieces.
+%% Waiting on an event no running thread can bring about raises
+%% system_error(deadlock).
+%%
+%% thread_create/3 takes the options alias(Alias) and detached(Bool).
+%% A detached thread can't be joined, and is forgotten once it
+%% finishes.
+
+:- module(threads, [mutex_lock/1,
+                    mutex_unlock/1,
+                    thread_create/3,
+                    thread_get_message/1,
+                    thread_join/2,
+                    thread_self/1,
+                    thread_send_message/2,
+                    with_mutex/2]).
+
+:- use_module(library(error), [must_be/2]).
+:- use_module(library(iso_ext), [setup_call_cleanup/3]).
+:- use_module(library(lists), [member/2]).
+
+:- meta_predicate thread_create(0, ?, +).
+
+:- meta_predicate with_mutex(+, 0).
+
+'$thread_start'(Goal) :-
+    catch(( call(Goal) ->
+            Status = true
+          ; Status = false
+          ),
+          E,
+          Status = exception(E)),
+    '$thread_finish'(Status).
+
+thread_create(Goal, Id, Options) :-
+    must_be(list, Options),
+    (  member(alias(Alias), Options) ->
+       must_be(atom, Alias)
+    ;  true
+    ),
+    (  member(detached(Detached), Options) ->
+       must_be(boolean, Detached)
+    ;  Detached = false
+    ),
+    (  '$thread_create'(Goal, Alias, Detached, Id) ->
+       true
+    ;  throw(error(permission_error(create, thread, Alias), thread_create/3))
+    ).
+
+thread_join(Id, Status) :-
+    must_be_thread_id(Id, thread_join/2),
+    (  '$thread_join'(Id, Status0) ->
+       (  Status0 == '$running' ->
+          (  '$thread_wait_join'(Id) ->
+             thread_join(Id, Status)
+          ;  throw(error(system_error(deadlock), thread_join/2))
+          )
+       ;  Status = Status0
+       )
+    ;  throw(error(existence_error(thread, Id), thread_join/2))
+    ).
+
+thread_self(Id) :-
+    '$thread_self'(Id).
+
+thread_send_message(Id, Message) :-
+    must_be_thread_id(Id, thread_send_message/2),
+    (  '$thread_send_message'(Id, Message) ->
+       true
+    ;  throw(error(existence_error(thread, Id), thread_send_message/2))
+    ).
+
+thread_get_message(Message) :-
+    thread_get_message(0, Message).
+
+%% scan the queue from the I-th message, waiting for more messages to
+%% arrive once the queue is exhausted.
+thread_get_message(I, Message) :-
+    (  '$thread_peek_message'(I, Message0) ->
+       (  Message0 = Message ->
+          '$thread_remove_message'(I)
+       ;  I1 is I + 1,
+          thread_get_message(I1, Message)
+       )
+    ;  '$thread_wait_message'(I) ->
+       thread_get_message(I, Message)
+    ;  throw(error(system_error(deadlock), thread_get_message/1))
+    ).
+
+must_be_thread_id(Id, PI) :-
+    (  var(Id) ->
+       throw(error(instantiation_error, PI))
+    ;  true
+    ).
+
+mutex_lock(Mutex) :-
+    must_be(atom, Mutex),
+    (  '$mutex_lock'(Mutex) ->
+       true
+    ;  throw(error(system_error(deadlock), mutex_lock/1))
+    ).
+
+mutex_unlock(Mutex) :-
+    must_be(atom, Mutex),
+    (  '$mutex_unlock'(Mutex) ->
+       true
+    ;  throw(error(permission_error(unlock, mutex, Mutex), mutex_unlock/1))
+    ).
+
+with_mutex(Mutex, Goal) :-
+    setup_call_cleanup(mutex_lock(Mutex),
+                       once(Goal),
+                       mutex_unlock(Mutex)).
@@ -38,7 +38,8 @@
 
    sleep(S) sleeps for S seconds (a floating point number).
 
-   time(Goal) reports the execution time of Goal.
+   time(Goal) reports the execution time of Goal, and the number of
+   inferences it took.
 
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
@@ -80,50 +81,51 @@ sleep(T) :-
     ).
 
 
-% '$cpu_now' can be replaced by statistics/2 once that is implemented.
-
 :- meta_predicate time(0).
 
 time(Goal) :-
-        '$cpu_now'(T0),
+        statistics(cputime, T0),
+        statistics(inferences, I0),
         setup_call_cleanup(true,
                            (   Goal,
-                               report_time(T0)
+                               report_time(T0, I0)
                            ),
-                           report_time(T0)).
+                           report_time(T0, I0)).
 
-report_time(T0) :-
-        '$cpu_now'(T),
+report_time(T0, I0) :-
+        statistics(cputime, T),
+        statistics(inferences, I),
         Time is T - T0,
+        Inferences is I - I0,
         (   bb_get('$first_answer', true) ->
-            format("   % CPU time: ~3f seconds~n", [Time])
-        ;   format("% CPU time: ~3f seconds~n   ", [Time])
+            format("   % CPU time: ~3f seconds, ~d inferences~n", [Time, Inferences])
+        ;   format("% CPU time: ~3f seconds, ~d inferences~n   ", [Time, Inferences])
         ).
 
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
 ?- time((true;false)).
-   % CPU time: 0.000 seconds
+   % CPU time: 0.001 seconds, 1010 inferences
    true
-;  % CPU time: 0.001 seconds
+;  % CPU time: 0.007 seconds, 4746 inferences
    false.
 
 :- time(use_module(library(clpz))).
-   % CPU time: 0.000 seconds
-   % CPU time: 0.001 seconds
+   % CPU time: 3.530 seconds, 2554128 inferences
+   % CPU time: 3.534 seconds, 2557512 inferences
    true.
 
 :- time(use_module(library(lists))).
-   % CPU time: 0.000 seconds
-   % CPU time: 0.001 seconds
+   % CPU time: 0.001 seconds, 1054 inferences
+   % CPU time: 0.005 seconds, 4432 inferences
    true.
 
 ?- time(member(X, [a,b,c])).
-   % CPU time: 0.000 seconds
+   % CPU time: 0.001 seconds, 1153 inferences
    X = a
-;  % CPU time: 0.002 seconds
+;  % CPU time: 0.009 seconds, 8103 inferences
    X = b
-;  % CPU time: 0.004 seconds
+;  % CPU time: 0.017 seconds, 15481 inferences
    X = c
-;  % CPU time: 0.007 seconds
+;  % CPU time: 0.025 seconds, 22862 inferences
    false.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
@@ -1,9 +1,13 @@
 
-:- module(loader, [consult/1,
+:- module(loader, [clause_property/2,
+                   consult/1,
+                   ensure_loaded/1,
                    expand_goal/3,
                    expand_term/2,
                    file_load/2,
                    load/1,
+                   make/0,
+                   nth_clause/3,
                    predicate_property/2,
                    prolog_load_context/2,
                    strip_module/3,
@@ -13,12 +17,32 @@
 
 
 :- use_module(library(error)).
+:- use_module(library(iso_ext), [setup_call_cleanup/3]).
 :- use_module(library(lists)).
 :- use_module(library(pairs)).
 
 
+%% '$source_file'(File, Time) holds for every source file loaded by
+%% use_module/1,2, consult/1, ensure_loaded/1 or include/1, Time being
+%% its modification time when it was last loaded. make/0 reloads the
+%% files whose modification time has since changed.
+:- dynamic('$source_file'/2).
+%% '$source_file_import'(File, Module, Exports): Module imported
+%% Exports from File, an empty list meaning all of its exports.
+:- dynamic('$source_file_import'/3).
+%% '$included_file'(File, Source): File was included by Source, one
+%% clause for each file including it.
+:- dynamic('$included_file'/2).
+%% '$source_file_module'(File, Module): loading File declared Module.
+:- dynamic('$source_file_module'/2).
+
+
 '$print_message_and_fail'(Error) :-
-    (  (  Error = error(existence_error(procedure, Expansion), Expansion)
+    (  Error = '$time_limit_exceeded'(_) ->
+       % a time limit that expired during an expansion belongs to the
+       % call_with_time_limit/2 around it, not to the expansion.
+       throw(Error)
+    ;  (  Error = error(existence_error(procedure, Expansion), Expansion)
        ;  Error = error(evaluation_error((_:_)/_),Expansion)
        )  ->
        (  (  Expansion = goal_expansion/2
@@ -132,6 +156,18 @@ load(Stream) :-
 load(_).
 
 
+%% like load/1, but errors are thrown to the caller rather than
+%% printed.
+consult_stream(Stream) :-
+    create_load_context(Stream, Evacuable),
+    catch((loader:load_loop(Stream, Evacuable),
+           loader:run_initialization_goals),
+          E,
+          builtins:(loader:unload_evacuable(Evacuable),
+                    builtins:throw(E))),
+    '$pop_load_context'.
+
+
 print_comma_separated_list([VN=_]) :-
     write(VN),
     !.
@@ -166,19 +202,82 @@ warn_about_singletons([Singleton|Singletons], LinesRead) :-
 
 
 load_loop(Stream, Evacuable) :-
+    load_terms(Stream, Evacuable, []),
+    '$conclude_load'(Evacuable).
+
+%% Conditions is the stack of the conditional compilation directives
+%% enclosing the term read next, innermost first. The terms of a
+%% branch are compiled only if each of its conditions is true.
+load_terms(Stream, Evacuable, Conditions) :-
     (  '$devour_whitespace'(Stream) ->
        stream_property(Stream, position(position_and_lines_read(_, LinesRead))),
+       '$set_clause_location',
        read_term(Stream, Term, [singletons(Singletons)])
     ;  Term = end_of_file
     ),
     (  Term == end_of_file ->
        close(Stream),
-       '$conclude_load'(Evacuable)
+       (  Conditions == [] ->
+          true
+       ;  throw(error(syntax_error(missing_endif), load/1))
+       )
     ;  var(Term) ->
        instantiation_error(load/1)
+    ;  conditional_directive(Term, Conditions, Conditions1) ->
+       load_terms(Stream, Evacuable, Conditions1)
+    ;  Conditions = [Condition | _],
+       Condition \== true ->
+       load_terms(Stream, Evacuable, Conditions)
     ;  warn_about_singletons(Singletons, LinesRead),
        compile_term(Term, Evacuable),
-       load_loop(Stream, Evacuable)
+       load_terms(Stream, Evacuable, Conditions)
+    ).
+
+
+%% a condition is true while its branch is compiled, false until
+%% one of its branches is, done once one has been and skip if it is
+%% nested in a branch that isn't compiled.
+conditional_directive((:- Directive), Conditions0, Conditions) :-
+    nonvar(Directive),
+    conditional_directive_(Directive, Conditions0, Conditions).
+
+conditional_directive_(if(Goal), Conditions, [Condition | Conditions]) :-
+    (  Conditions = [Condition0 | _],
+       Condition0 \== true ->
+       Condition = skip
+    ;  condition_holds(Goal, Condition)
+    ).
+conditional_directive_(elif(Goal), Conditions0, [Condition | Conditions]) :-
+    (  Conditions0 = [Condition0 | Conditions] ->
+       (  Condition0 == false ->
+          condition_holds(Goal, Condition)
+       ;  Condition0 == true ->
+          Condition = done
+       ;  Condition = Condition0
+       )
+    ;  throw(error(syntax_error(missing_if), load/1))
+    ).
+conditional_directive_(else, Conditions0, [Condition | Conditions]) :-
+    (  Conditions0 = [Condition0 | Conditions] ->
+       (  Condition0 == false ->
+          Condition = true
+       ;  Condition0 == true ->
+          Condition = done
+       ;  Condition = Condition0
+       )
+    ;  throw(error(syntax_error(missing_if), load/1))
+    ).
+conditional_directive_(endif, Conditions0, Conditions) :-
+    (  Conditions0 = [_ | Conditions] ->
+       true
+    ;  throw(error(syntax_error(missing_if), load/1))
+    ).
+
+condition_holds(Goal, Condition) :-
+    prolog_load_context(module, Module),
+    (  call(Module:Goal) ->
+       Condition = true
+    ;  Condition = false
     ).
 
 
@@ -275,6 +374,11 @@ compile_dispatch_or_clause_on_list([Term | Terms], Evacuable) :-
 compile_dispatch_or_clause(Term, Evacuable) :-
     (  var(Term) ->
        instantiation_error(load/1)
+    ;  Term = (:- Declaration),
+       nonvar(Declaration),
+       Declaration = include(File) ->
+       % included clauses continue the predicates preceding them.
+       include(File, Evacuable)
     ;  compile_dispatch(Term, Evacuable) ->
        '$flush_term_queue'(Evacuable)
     ;  compile_clause(Term, Evacuable)
@@ -363,11 +467,18 @@ compile_declaration(discontiguous(Name/Arity), Evacuable) :-
     must_be(integer, Arity),
     prolog_load_context(module, Module),
     '$add_discontiguous_predicate'(Module, Name, Arity, Evacuable).
+compile_declaration(ensure_loaded(File), Evacuable) :-
+    ensure_loaded(File, Evacuable).
 compile_declaration(initialization(Goal), Evacuable) :-
     prolog_load_context(module, Module),
     assertz(Module:'$initialization_goals'(Goal)).
 compile_declaration(set_prolog_flag(Flag, Value), _) :-
     set_prolog_flag(Flag, Value).
+compile_declaration(use_foreign_library(Library), _) :-
+    ffi:use_foreign_library(Library).
+compile_declaration(foreign(Name, ArgTypes, RetType), Evacuable) :-
+    ffi:foreign_clause(Name, ArgTypes, RetType, Clause),
+    compile_clause(Clause, Evacuable).
 compile_declaration(non_counted_backtracking(Name/Arity), Evacuable) :-
     must_be(atom, Name),
     must_be(integer, Arity),
@@ -493,6 +604,174 @@ open_file(Path, Stream) :-
             )
     ).
 
+%% the terms of File are compiled as if they were written in place of
+%% the include directive.
+include(File, Evacuable) :-
+    (  var(File) ->
+       instantiation_error(load/1)
+    ;  path_atom(File, FilePath) ->
+       load_context_path(FilePath, Path),
+       open_file(Path, Stream),
+       stream_property(Stream, file_name(PathFileName)),
+       % the include context is popped however the load ends.
+       setup_call_cleanup(loader:push_include_context(Stream, PathFileName),
+                          once(loader:load_terms(Stream, Evacuable, [])),
+                          loader:pop_include_context),
+       record_included_file(PathFileName)
+    ;  type_error(atom, File, load/1)
+    ).
+
+push_include_context(Stream, PathFileName) :-
+    '$push_include_context'(Stream, PathFileName).
+
+pop_include_context :-
+    '$pop_load_context'.
+
+
+ensure_loaded(File) :-
+    '$push_load_state_payload'(Evacuable),
+    ensure_loaded(File, Evacuable).
+
+%% like use_module/1, but a file that is already loaded isn't loaded
+%% again, only its module is imported. Every loaded file has an in
+%% situ module named by its absolute path.
+ensure_loaded(File, Evacuable) :-
+    (  var(File) ->
+       instantiation_error(load/1)
+    ;  File = library(_) ->
+       use_module(File, [], Evacuable)
+    ;  path_atom(File, FilePath) ->
+       load_context_path(FilePath, Path),
+       open_file(Path, Stream),
+       stream_property(Stream, file_name(PathFileName)),
+       absolute_path(PathFileName, AbsolutePath),
+       (  '$module_exists'(AbsolutePath) ->
+          close(Stream),
+          import_loaded_module(AbsolutePath, Evacuable),
+          record_source_file_import(AbsolutePath, [])
+       ;  file_load(Stream, PathFileName, Subevacuable),
+          '$use_module'(Evacuable, Subevacuable, []),
+          record_source_file(PathFileName, Subevacuable, [])
+       )
+    ;  type_error(atom, File, load/1)
+    ).
+
+%% the canonical path of a file, as its load context has it. Paths of
+%% files that no longer exist are only made absolute.
+absolute_path(Path, AbsolutePath) :-
+    atom_chars(Path, PathCs),
+    (  '$path_canonical'(PathCs, Cs) ->
+       atom_chars(AbsolutePath, Cs)
+    ;  sub_atom(Path, 0, 1, _, '/') ->
+       AbsolutePath = Path
+    ;  '$working_directory'(Cs, Cs),
+       atom_chars(Dir, Cs),
+       atom_concat(Dir, '/', DirSlashed),
+       atom_concat(DirSlashed, Path, AbsolutePath)
+    ).
+
+import_loaded_module(File, Evacuable) :-
+    (  '$source_file_module'(File, Module),
+       '$load_compiled_library'(Module, [], Evacuable) ->
+       true
+    ;  true
+    ).
+
+
+source_file_time(File, Time) :-
+    atom_chars(File, FileChars),
+    '$file_time'(FileChars, modification, Time).
+
+record_source_file(PathFileName, Subevacuable, Exports) :-
+    absolute_path(PathFileName, File),
+    (  record_source_file_time(File) ->
+       record_source_file_module(File, Subevacuable),
+       record_source_file_import(File, Exports)
+    ;  true
+    ).
+
+record_source_file_time(File) :-
+    source_file_time(File, Time),
+    retractall(loader:'$source_file'(File, _)),
+    assertz(loader:'$source_file'(File, Time)).
+
+record_source_file_module(File, Subevacuable) :-
+    retractall(loader:'$source_file_module'(File, _)),
+    (  '$load_state_payload_module'(Subevacuable, Module) ->
+       assertz(loader:'$source_file_module'(File, Module))
+    ;  true
+    ).
+
+record_source_file_import(File, Exports) :-
+    (  prolog_load_context(module, Module) ->
+       true
+    ;  Module = user
+    ),
+    retractall(loader:'$source_file_import'(File, Module, _)),
+    assertz(loader:'$source_file_import'(File, Module, Exports)).
+
+record_included_file(PathFileName) :-
+    absolute_path(PathFileName, File),
+    (  prolog_load_context(source, Source0),
+       record_source_file_time(File) ->
+       absolute_path(Source0, Source),
+       retractall(loader:'$included_file'(File, Source)),
+       assertz(loader:'$included_file'(File, Source))
+    ;  true
+    ).
+
+
+%% make.
+%
+% Reload every source file modified since it was last loaded. The
+% clauses of a reloaded file, including its contributions to multifile
+% predicates, are replaced by those it holds now, and the modules that
+% imported it import it again. A modified included file reloads the
+% files including it. Errors are printed as they are by consult/1, and
+% the files left are reloaded all the same.
+make :-
+    findall(Source, loader:modified_source_file(Source), Sources0),
+    sort(Sources0, Sources),
+    maplist(loader:reload_source_file, Sources).
+
+modified_source_file(Source) :-
+    '$source_file'(File, Time),
+    source_file_time(File, CurrentTime),
+    CurrentTime \== Time,
+    (  '$included_file'(File, _) ->
+       '$included_file'(File, Source)
+    ;  Source = File
+    ).
+
+reload_source_file(File) :-
+    findall(Module-Exports, loader:'$source_file_import'(File, Module, Exports), Imports),
+    retractall(loader:'$included_file'(_, File)),
+    (  catch(loader:reload_source_file(File, Imports),
+             E,
+             loader:'$print_message_and_fail'(E)) ->
+       true
+    ;  true
+    ).
+
+reload_source_file(File, Imports) :-
+    open(File, read, Stream),
+    file_load(Stream, File, Subevacuable),
+    (  record_source_file_time(File) ->
+       record_source_file_module(File, Subevacuable)
+    ;  true
+    ),
+    maplist(loader:reimport_source_file(Subevacuable), Imports).
+
+reimport_source_file(Subevacuable, Module-Exports) :-
+    (  (  Module == user
+       ;  '$module_exists'(Module)
+       ) ->
+       '$push_module_load_state_payload'(Evacuable, Module),
+       '$use_module'(Evacuable, Subevacuable, Exports)
+    ;  true
+    ).
+
+
 use_module(Module, Exports, Evacuable) :-
     (  var(Module) ->
        instantiation_error(load/1)
@@ -513,11 +792,32 @@ use_module(Module, Exports, Evacuable) :-
           open_file(Path, Stream),
           stream_property(Stream, file_name(PathFileName)),
           file_load(Stream, PathFileName, Subevacuable),
-          '$use_module'(Evacuable, Subevacuable, Exports)
+          '$use_module'(Evacuable, Subevacuable, Exports),
+          record_source_file(PathFileName, Subevacuable, Exports)
        ;  type_error(atom, Library, load/1)
        )
     ).
 
+%% the machine calls '$autoload'(Module, Goal, Library) in place of
+%% Goal when its predicate is undefined in Module, Library exports it
+%% and the autoload flag is true. Only that predicate is imported. Its
+%% meta-arguments weren't qualified when Goal was compiled, so they
+%% are qualified by Module here.
+'$autoload'(Module, Goal, Library) :-
+    functor(Goal, Name, Arity),
+    '$push_module_load_state_payload'(Evacuable, Module),
+    use_module(library(Library), [Name/Arity], Evacuable),
+    (  predicate_property(Module:Goal, number_of_clauses(_)) ->
+       true
+    ;  throw(error(existence_error(procedure, Name/Arity), Name/Arity))
+    ),
+    (  predicate_property(Module:Goal, meta_predicate(MetaSpecs)),
+       expand_module_names(Goal, MetaSpecs, Module, ExpandedGoal0, []) ->
+       ExpandedGoal = ExpandedGoal0
+    ;  ExpandedGoal = Goal
+    ),
+    Module:ExpandedGoal.
+
 
 
 check_predicate_property(meta_predicate, Module, Name, Arity, MetaPredicateTerm) :-
@@ -530,6 +830,17 @@ check_predicate_property(multifile, Module, Name, Arity, multifile) :-
     '$cpp_multifile_property'(Module, Name, Arity).
 check_predicate_property(discontiguous, Module, Name, Arity, discontiguous) :-
     '$cpp_discontiguous_property'(Module, Name, Arity).
+check_predicate_property(number_of_clauses, Module, Name, Arity, number_of_clauses(N)) :-
+    '$cpp_predicate_clauses'(Module, Name, Arity, Clauses),
+    length(Clauses, N).
+check_predicate_property(file, Module, Name, Arity, file(File)) :-
+    '$cpp_predicate_clauses'(Module, Name, Arity, Clauses),
+    memberchk(_-location(File0, _, _), Clauses),
+    File = File0.
+check_predicate_property(line_count, Module, Name, Arity, line_count(Line)) :-
+    '$cpp_predicate_clauses'(Module, Name, Arity, Clauses),
+    memberchk(_-location(_, Line0, _), Clauses),
+    Line = Line0.
 
 
 
@@ -571,6 +882,53 @@ predicate_property(Callable, Property) :-
     ).
 
 
+% the clauses of a predicate are referred to by
+% '$clause_ref'(Module, Name, Arity, Start), where Start is the start
+% of the code of the clause.
+
+nth_clause(Callable, N, Ref) :-
+    (  var(Callable) ->
+       instantiation_error(nth_clause/3)
+    ;  functor(Callable, (:), 2),
+       arg(1, Callable, Module),
+       arg(2, Callable, Callable0),
+       atom(Module),
+       nonvar(Callable0) ->
+       functor(Callable0, Name, Arity)
+    ;  load_context(Module),
+       functor(Callable, Name, Arity)
+    ),
+    (  atom(Name),
+       Name \== [] ->
+       true
+    ;  type_error(callable, Callable, nth_clause/3)
+    ),
+    '$cpp_predicate_clauses'(Module, Name, Arity, Clauses),
+    (  var(N) ->
+       nth0(N0, Clauses, Start-_),
+       N is N0 + 1
+    ;  must_be(integer, N),
+       N > 0,
+       N0 is N - 1,
+       nth0(N0, Clauses, Start-_)
+    ),
+    Ref = '$clause_ref'(Module, Name, Arity, Start).
+
+clause_property(Ref, Property) :-
+    (  var(Ref) ->
+       instantiation_error(clause_property/2)
+    ;  Ref = '$clause_ref'(Module, Name, Arity, Start) ->
+       '$cpp_predicate_clauses'(Module, Name, Arity, Clauses),
+       memberchk(Start-location(File, Line, Column), Clauses),
+       clause_location_property(Property, File, Line, Column)
+    ;  domain_error(clause_reference, Ref, clause_property/2)
+    ).
+
+clause_location_property(file(File), File, _, _).
+clause_location_property(line_count(Line), _, Line, _).
+clause_location_property(line_position(Column), _, _, Column).
+
+
 strip_module(M0, G0, M1, G1) :-
     (  nonvar(G0),
        G0 = (MG1:G2) ->
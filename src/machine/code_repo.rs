@@ -1,16 +1,342 @@
+use prolog_parser::ast::*;
+use prolog_parser::tabled_rc::*;
+
 use crate::clause_types::*;
+use crate::forms::*;
+use crate::indexing::*;
 use crate::instructions::*;
 use crate::machine::machine_indices::*;
+use crate::machine::CompilationTarget;
+
+use indexmap::{IndexMap, IndexSet};
+
+use std::collections::{BTreeMap, VecDeque};
 
 #[derive(Debug)]
 pub(crate) struct CodeRepo {
     pub(super) code: Code,
+    // the argument indices of the SwitchOnTerm at each location, by
+    // argument. None marks an argument some clause doesn't bind.
+    pub(super) arg_indices: IndexMap<usize, IndexMap<usize, Option<ArgIndex>>>,
+    pub(super) predicate_extents: PredicateExtents,
+    pub(super) clause_locations: ClauseLocations,
+}
+
+// the predicate, and the module it was compiled into, of each extent
+// of code, by the start of the extent with its end.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PredicateExtents(pub(super) BTreeMap<usize, (usize, PredicateKey, ClauseName)>);
+
+impl PredicateExtents {
+    #[inline]
+    fn insert(&mut self, start: usize, end: usize, key: PredicateKey, module_name: ClauseName) {
+        if start < end {
+            self.0.insert(start, (end, key, module_name));
+        }
+    }
+
+    // the predicate whose code holds p, with the module it was
+    // compiled into.
+    pub(crate) fn predicate_at(&self, p: usize) -> Option<(&PredicateKey, &ClauseName)> {
+        match self.0.range(..=p).next_back() {
+            Some((_, (end, key, module_name))) if p < *end => Some((key, module_name)),
+            _ => None,
+        }
+    }
+
+    // the extent holding p, with its predicate and module.
+    pub(crate) fn extent_at(&self, p: usize) -> Option<(usize, usize, &PredicateKey, &ClauseName)> {
+        match self.0.range(..=p).next_back() {
+            Some((&start, (end, key, module_name))) if p < *end => {
+                Some((start, *end, key, module_name))
+            }
+            _ => None,
+        }
+    }
+}
+
+// the file, line and column of a clause compiled from a source file.
+pub(crate) type ClauseLocation = (ClauseName, usize, usize);
+
+// the extent of the code of each compiled clause by its start, with
+// the location of the clauses compiled from source files.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ClauseLocations(pub(super) BTreeMap<usize, (usize, Option<ClauseLocation>)>);
+
+impl ClauseLocations {
+    #[inline]
+    pub(super) fn insert(&mut self, start: usize, end: usize, location: Option<ClauseLocation>) {
+        if start < end {
+            self.0.insert(start, (end, location));
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, start: usize) -> Option<&ClauseLocation> {
+        self.0
+            .get(&start)
+            .and_then(|(_, location)| location.as_ref())
+    }
+
+    // the start of the clause whose code holds p, with its location.
+    #[inline]
+    pub(crate) fn clause_at(&self, p: usize) -> Option<(usize, Option<&ClauseLocation>)> {
+        match self.0.range(..=p).next_back() {
+            Some((&start, (end, location))) if p < *end => Some((start, location.as_ref())),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, usize, Option<&ClauseLocation>)> {
+        self.0
+            .iter()
+            .map(|(&start, (end, location))| (start, *end, location.as_ref()))
+    }
 }
 
 impl CodeRepo {
     #[inline]
     pub(super) fn new() -> Self {
-        CodeRepo { code: Code::new() }
+        CodeRepo {
+            code: Code::new(),
+            arg_indices: IndexMap::new(),
+            predicate_extents: PredicateExtents::default(),
+            clause_locations: ClauseLocations::default(),
+        }
+    }
+
+    #[inline]
+    pub(super) fn add_predicate_extent(
+        &mut self,
+        start: usize,
+        key: PredicateKey,
+        module_name: ClauseName,
+    ) {
+        let end = self.code.len();
+        self.predicate_extents.insert(start, end, key, module_name);
+    }
+
+    #[inline]
+    pub(super) fn predicate_at(&self, p: usize) -> Option<(&PredicateKey, &ClauseName)> {
+        self.predicate_extents.predicate_at(p)
+    }
+
+    // the starts of the clauses of a predicate, in order, or None if
+    // the predicate has no code. The clauses of extensible predicates
+    // are kept by their skeletons, and those of the others follow the
+    // code index of the predicate.
+    pub(crate) fn predicate_clauses(
+        &self,
+        indices: &IndexStore,
+        module_name: &ClauseName,
+        key: &PredicateKey,
+    ) -> Option<Vec<usize>> {
+        let compilation_target = match module_name.as_str() {
+            "user" => CompilationTarget::User,
+            _ => CompilationTarget::Module(module_name.clone()),
+        };
+
+        if let Some(skeleton) = indices.get_predicate_skeleton(&compilation_target, key) {
+            return Some(
+                skeleton
+                    .clauses
+                    .iter()
+                    .map(|clause_index_info| clause_index_info.clause_start)
+                    .collect(),
+            );
+        }
+
+        let code_index = match module_name.as_str() {
+            "user" => indices.code_dir.get(key),
+            _ => indices
+                .modules
+                .get(module_name)
+                .and_then(|module| module.code_dir.get(key)),
+        };
+
+        let p = code_index?.local()?;
+        let (start, end, ..) = self.predicate_extents.extent_at(p)?;
+
+        Some(
+            self.clause_locations
+                .0
+                .range(start..end)
+                .map(|(&clause_start, _)| clause_start)
+                .collect(),
+        )
+    }
+
+    // the number of clauses of every predicate in every module.
+    pub(crate) fn number_of_clauses(&self, indices: &IndexStore) -> usize {
+        let predicates: IndexSet<(&ClauseName, &PredicateKey)> = self
+            .predicate_extents
+            .0
+            .values()
+            .map(|(_, key, module_name)| (module_name, key))
+            .collect();
+
+        predicates
+            .into_iter()
+            .filter_map(|(module_name, key)| self.predicate_clauses(indices, module_name, key))
+            .map(|clause_starts| clause_starts.len())
+            .sum()
+    }
+
+    // the first instruction of the clause whose code spans start to
+    // end, past the choice and indexing instructions preceding it.
+    pub(crate) fn clause_entry(&self, start: usize, end: usize) -> usize {
+        (start..end)
+            .find(|&p| !matches!(&self.code[p], Line::Choice(_) | Line::IndexingCode(_)))
+            .unwrap_or(start)
+    }
+
+    // the locations of the instructions calling the body goals of the
+    // clause whose code spans start to end.
+    pub(crate) fn clause_goals(&self, start: usize, end: usize) -> Vec<usize> {
+        (start..end)
+            .filter(|&p| {
+                matches!(
+                    &self.code[p],
+                    Line::Control(ControlInstruction::CallClause(..))
+                        | Line::Control(ControlInstruction::JmpBy(..))
+                )
+            })
+            .collect()
+    }
+
+    // the location p jumps to if it holds a RevJmpBy, as the lines
+    // following an argument index do, and p otherwise.
+    #[inline]
+    pub(super) fn jump_target(&self, p: usize) -> usize {
+        match &self.code[p] {
+            &Line::Control(ControlInstruction::RevJmpBy(o)) => p - o,
+            _ => p,
+        }
+    }
+
+    fn new_arg_index(&mut self, index_loc: usize, arg: usize) -> Option<ArgIndex> {
+        let clause_locs = indexed_clause_locs(&self.code, index_loc);
+        let keys = clause_locs
+            .iter()
+            .map(|&clause_loc| clause_arg_key(&self.code, clause_loc, arg))
+            .collect::<Option<Vec<_>>>()?;
+
+        let loc = self.code.len();
+        let mut clauses = VecDeque::new();
+
+        self.code.push(Line::IndexingCode(vec![]));
+
+        for (key, clause_loc) in keys.into_iter().zip(clause_locs) {
+            let jmp_loc = self.code.len();
+
+            self.code
+                .push(Line::Control(ControlInstruction::RevJmpBy(jmp_loc - clause_loc)));
+
+            clauses.push_back((key, jmp_loc - loc));
+        }
+
+        Some(ArgIndex {
+            loc,
+            clauses,
+            is_current: false,
+        })
+    }
+
+    // builds the index on argument arg of the subsequence indexed at
+    // index_loc, or rebuilds it if clauses were added to it.
+    pub(super) fn build_arg_index(
+        &mut self,
+        index_loc: usize,
+        arg: usize,
+        atom_tbl: TabledData<Atom>,
+    ) {
+        let is_dynamic = match to_indexing_line(&self.code[index_loc]).map(|lines| &lines[0]) {
+            Some(IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, v, ..))) => {
+                matches!(v, IndexingCodePtr::DynamicExternal(_))
+            }
+            _ => return,
+        };
+
+        let is_new = match self.arg_indices.get(&index_loc) {
+            Some(arg_indices) => !arg_indices.contains_key(&arg),
+            None => true,
+        };
+
+        if is_new {
+            let arg_index = self.new_arg_index(index_loc, arg);
+
+            self.arg_indices
+                .entry(index_loc)
+                .or_default()
+                .insert(arg, arg_index);
+        }
+
+        let arg_index = match self
+            .arg_indices
+            .get_mut(&index_loc)
+            .and_then(|arg_indices| arg_indices.get_mut(&arg))
+        {
+            Some(Some(arg_index)) if !arg_index.is_current => arg_index,
+            _ => return,
+        };
+
+        let indexing_code = if is_dynamic {
+            arg_indexing_code::<DynamicCodeIndices>(arg_index, arg, atom_tbl)
+        } else {
+            arg_indexing_code::<StaticCodeIndices>(arg_index, arg, atom_tbl)
+        };
+
+        self.code[arg_index.loc] = Line::IndexingCode(indexing_code);
+        arg_index.is_current = true;
+    }
+
+    // adds the clause at clause_loc, newly asserted into the
+    // subsequence indexed at index_loc, to its argument indices. They're
+    // rebuilt the next time they're used.
+    pub(super) fn add_to_arg_indices(
+        &mut self,
+        index_loc: usize,
+        clause_loc: usize,
+        append_or_prepend: AppendOrPrepend,
+    ) {
+        let arg_indices = match self.arg_indices.get_mut(&index_loc) {
+            Some(arg_indices) => arg_indices,
+            None => return,
+        };
+
+        for (&arg, arg_index_opt) in arg_indices.iter_mut() {
+            let arg_index = match arg_index_opt {
+                Some(arg_index) => arg_index,
+                None => continue,
+            };
+
+            let key = match clause_arg_key(&self.code, clause_loc, arg) {
+                Some(key) => key,
+                None => {
+                    *arg_index_opt = None;
+                    continue;
+                }
+            };
+
+            let jmp_loc = self.code.len();
+
+            self.code
+                .push(Line::Control(ControlInstruction::RevJmpBy(jmp_loc - clause_loc)));
+
+            if append_or_prepend.is_append() {
+                arg_index.clauses.push_back((key, jmp_loc - arg_index.loc));
+            } else {
+                arg_index.clauses.push_front((key, jmp_loc - arg_index.loc));
+            }
+
+            arg_index.is_current = false;
+        }
+    }
+
+    #[inline]
+    pub(super) fn remove_arg_indices(&mut self, index_loc: usize) {
+        self.arg_indices.remove(&index_loc);
     }
 
     #[inline]
@@ -156,7 +482,7 @@ impl CodeRepo {
 
         loop {
             match &indexed_choice_instrs.get(ii) {
-                Some(&offset) => match &self.code[p + offset - 1] {
+                Some(&offset) => match &self.code[self.jump_target(p + offset) - 1] {
                     &Line::Choice(ChoiceInstruction::DynamicInternalElse(
                         birth,
                         death,
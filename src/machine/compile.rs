@@ -1328,11 +1328,15 @@ fn print_overwrite_warning(
 impl<'a> LoadState<'a> {
     pub(super) fn listing_src_file_name(&self) -> Option<ClauseName> {
         if let Some(load_context) = self.wam.load_contexts.last() {
-            if !load_context.path.is_file() {
+            // the clauses of included files belong to the file
+            // including them.
+            let path = load_context.source.as_ref().unwrap_or(&load_context.path);
+
+            if !path.is_file() {
                 return None;
             }
 
-            if let Some(path_str) = load_context.path.to_str() {
+            if let Some(path_str) = path.to_str() {
                 if !path_str.is_empty() {
                     return Some(clause_name!(
                         path_str.to_string(),
@@ -1398,6 +1402,30 @@ impl<'a> LoadState<'a> {
         let queue = preprocessor.parse_queue(self)?;
         let mut code = cg.compile_predicate(&clauses)?;
 
+        let locations: Vec<_> = predicates.locations.drain(0..).collect();
+
+        // the code of the last clause ends where the appendix of the
+        // predicate begins.
+        let mut clause_ends: Vec<_> = cg
+            .skeleton
+            .clauses
+            .iter()
+            .skip(1)
+            .map(|clause_index_info| code_len + clause_index_info.clause_start)
+            .collect();
+
+        clause_ends.push(code_len + code.len());
+
+        for (idx, (clause_index_info, end)) in
+            cg.skeleton.clauses.iter().zip(clause_ends).enumerate()
+        {
+            self.wam.code_repo.clause_locations.insert(
+                code_len + clause_index_info.clause_start,
+                end,
+                locations.get(idx).cloned().flatten(),
+            );
+        }
+
         compile_appendix(
             &mut code,
             queue,
@@ -1479,12 +1507,18 @@ impl<'a> LoadState<'a> {
         set_code_index(
             &mut self.retraction_info,
             &predicates.compilation_target,
-            key,
+            key.clone(),
             &code_index,
             index_ptr,
         );
 
         self.wam.code_repo.code.extend(code.into_iter());
+        self.wam.code_repo.add_predicate_extent(
+            code_len,
+            key,
+            predicates.compilation_target.module_name(),
+        );
+
         Ok(code_index)
     }
 
@@ -1606,6 +1640,7 @@ impl<'a> LoadState<'a> {
         compilation_target: CompilationTarget,
         non_counted_bt: bool,
         append_or_prepend: AppendOrPrepend,
+        location: Option<ClauseLocation>,
     ) -> Result<CodeIndex, SessionError> {
         let settings = match self
             .wam
@@ -1637,7 +1672,9 @@ impl<'a> LoadState<'a> {
                 };
 
                 let mut predicate_queue = predicate_queue![clause];
+
                 predicate_queue.compilation_target = compilation_target;
+                predicate_queue.locations.push(location);
 
                 return self.compile(key, &mut predicate_queue, settings);
             }
@@ -1682,6 +1719,30 @@ impl<'a> LoadState<'a> {
                     self.wam.machine_st.global_clock,
                 );
 
+                self.wam.code_repo.clause_locations.insert(
+                    code_len,
+                    self.wam.code_repo.code.len(),
+                    location,
+                );
+
+                self.wam.code_repo.add_predicate_extent(
+                    code_len,
+                    key.clone(),
+                    compilation_target.module_name(),
+                );
+
+                // the new clause follows its choice instruction if it
+                // joined an indexed subsequence.
+                let clause_index_info = skeleton.clauses.back().unwrap();
+
+                if let Some(index_loc) = clause_index_info.opt_arg_index_key.switch_on_term_loc() {
+                    self.wam.code_repo.add_to_arg_indices(
+                        index_loc,
+                        clause_index_info.clause_start + 1,
+                        AppendOrPrepend::Append,
+                    );
+                }
+
                 self.push_back_to_local_predicate_skeleton(&compilation_target, &key, code_len);
 
                 let code_index =
@@ -1722,6 +1783,22 @@ impl<'a> LoadState<'a> {
                     self.wam.machine_st.global_clock,
                 );
 
+                self.wam.code_repo.add_predicate_extent(
+                    code_len,
+                    key.clone(),
+                    compilation_target.module_name(),
+                );
+
+                let clause_index_info = &skeleton.clauses[0];
+
+                if let Some(index_loc) = clause_index_info.opt_arg_index_key.switch_on_term_loc() {
+                    self.wam.code_repo.add_to_arg_indices(
+                        index_loc,
+                        clause_index_info.clause_start + 1,
+                        AppendOrPrepend::Prepend,
+                    );
+                }
+
                 self.push_front_to_local_predicate_skeleton(&compilation_target, &key, code_len);
 
                 let code_index =
@@ -1796,6 +1873,18 @@ impl<'a> LoadState<'a> {
             }
         };
 
+        // retraction may merge the subsequences around the clause.
+        for clause_index_info in skeleton
+            .clauses
+            .iter()
+            .skip(target_pos.saturating_sub(1))
+            .take(3)
+        {
+            if let Some(index_loc) = clause_index_info.opt_arg_index_key.switch_on_term_loc() {
+                self.wam.code_repo.remove_arg_indices(index_loc);
+            }
+        }
+
         let code = &mut self.wam.code_repo.code;
         let lower_bound = lower_bound_of_target_clause(skeleton, target_pos);
         let lower_bound_is_unindexed = !skeleton.clauses[lower_bound].opt_arg_index_key.is_some();
@@ -2109,6 +2198,7 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
                 clause_clause_compilation_target.clone(),
                 false, // non_counted_bt is false.
                 append_or_prepend,
+                None,
             )?;
 
             num_clause_predicates += 1;
@@ -2212,6 +2302,8 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
         let non_counted_bt = self.non_counted_bt_preds.contains(&key);
 
         if do_incremental_compile {
+            let mut locations = self.predicates.locations.drain(0..);
+
             for term in self.predicates.predicates.drain(0..) {
                 self.load_state.incremental_compile_clause(
                     key.clone(),
@@ -2219,6 +2311,7 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
                     self.predicates.compilation_target.clone(),
                     non_counted_bt,
                     AppendOrPrepend::Append,
+                    locations.next().flatten(),
                 )?;
             }
         } else {
@@ -268,7 +268,7 @@ impl<T: CopierTarget> CopyTermState<T> {
                     Addr::PStrLocation(addr, n) => {
                         self.copy_partial_string(addr, n);
                     }
-                    Addr::Stream(h) => {
+                    Addr::Pointer(h) | Addr::Stream(h) | Addr::TcpListener(h) => {
                         *self.value_at_scan() = self.target[h].context_free_clone();
                     }
                     _ => {
@@ -0,0 +1,512 @@
+use prolog_parser::{clause_name, temp_v};
+
+use crate::heap_iter::*;
+use crate::machine::machine_indices::*;
+use crate::machine::*;
+
+pub use crate::rug::{Integer, Rational};
+
+use indexmap::IndexMap;
+use ordered_float::OrderedFloat;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/* The API for embedding Scryer in Rust programs. Queries are run by
+ * the drivers at the end of toplevel.pl, which pass each answer to
+ * '$yield_answer'/2. That suspends the machine until the next answer
+ * is requested, at which point the query is resumed by backtracking
+ * into it. */
+
+// an owned copy of a term read from the heap.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    Atom(String),
+    Integer(Integer),
+    Rational(Rational),
+    Float(f64),
+    String(String),
+    List(Vec<Term>),
+    Compound(String, Vec<Term>),
+    Var(String),
+}
+
+pub type Bindings = BTreeMap<String, Term>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrologError {
+    Syntax(Term),    // the query couldn't be read.
+    Exception(Term), // the query threw a ball it didn't catch.
+}
+
+#[derive(Debug)]
+pub(crate) enum QueryAnswer {
+    True(Bindings),
+    Exception(Term),
+    SyntaxError(Term),
+    False,
+}
+
+fn atom_needs_quotes(atom: &str) -> bool {
+    let mut chars = atom.chars();
+
+    match chars.next() {
+        Some(c) if c.is_lowercase() => !chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => !["[]", "{}", "!", ";", ","].contains(&atom),
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::Atom(atom) if atom_needs_quotes(atom) => {
+                write!(f, "'{}'", atom.replace('\\', "\\\\").replace('\'', "\\'"))
+            }
+            Term::Atom(atom) => write!(f, "{}", atom),
+            Term::Integer(n) => write!(f, "{}", n),
+            Term::Rational(r) => write!(f, "{}", r),
+            Term::Float(fl) => write!(f, "{:?}", fl),
+            Term::String(s) => write!(f, "{:?}", s),
+            Term::List(terms) => {
+                write!(f, "[")?;
+
+                for (idx, term) in terms.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", term)?;
+                }
+
+                write!(f, "]")
+            }
+            Term::Compound(name, args) => {
+                write!(f, "{}(", Term::Atom(name.clone()))?;
+
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", arg)?;
+                }
+
+                write!(f, ")")
+            }
+            Term::Var(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for PrologError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrologError::Syntax(ball) => write!(f, "syntax error: {}", ball),
+            PrologError::Exception(ball) => write!(f, "uncaught exception: {}", ball),
+        }
+    }
+}
+
+impl Error for PrologError {}
+
+// collapse the lists held in partial strings into strings.
+fn list_or_string(terms: Vec<Term>, is_string: bool) -> Term {
+    if is_string {
+        Term::String(
+            terms
+                .into_iter()
+                .map(|term| match term {
+                    Term::Atom(atom) => atom,
+                    _ => unreachable!(),
+                })
+                .collect(),
+        )
+    } else {
+        Term::List(terms)
+    }
+}
+
+pub(crate) struct TermReader<'a> {
+    iter: HCPreOrderIterator<'a>,
+    var_names: &'a IndexMap<Addr, String>,
+}
+
+impl<'a> TermReader<'a> {
+    pub(crate) fn read(
+        machine_st: &'a MachineState,
+        var_names: &'a IndexMap<Addr, String>,
+        addr: Addr,
+    ) -> Term {
+        let mut reader = TermReader {
+            iter: machine_st.pre_order_iter(addr),
+            var_names,
+        };
+
+        reader.next_term()
+    }
+
+    fn next_term(&mut self) -> Term {
+        let addr = self.iter.next().unwrap();
+        self.term_from(addr)
+    }
+
+    fn term_from(&mut self, addr: Addr) -> Term {
+        let machine_st = self.iter.machine_st;
+
+        match addr {
+            Addr::Lis(_) | Addr::PStrLocation(..) => {
+                let mut is_string = matches!(addr, Addr::PStrLocation(..));
+                let mut terms = vec![self.next_term()];
+
+                loop {
+                    match self.iter.next().unwrap() {
+                        Addr::Lis(_) => {
+                            is_string = false;
+                            terms.push(self.next_term());
+                        }
+                        Addr::PStrLocation(..) => {
+                            terms.push(self.next_term());
+                        }
+                        Addr::EmptyList => {
+                            return list_or_string(terms, is_string);
+                        }
+                        tail => {
+                            let tail = self.term_from(tail);
+
+                            return terms.into_iter().rev().fold(tail, |tail, head| {
+                                Term::Compound(".".to_string(), vec![head, tail])
+                            });
+                        }
+                    }
+                }
+            }
+            Addr::Str(h) => match &machine_st.heap[h] {
+                &HeapCellValue::NamedStr(arity, ref name, _) => {
+                    let name = name.as_str().to_string();
+                    let args = (0..arity).map(|_| self.next_term()).collect();
+
+                    Term::Compound(name, args)
+                }
+                _ => unreachable!(),
+            },
+            Addr::Con(h) => match &machine_st.heap[h] {
+                HeapCellValue::Atom(ref name, _) => Term::Atom(name.as_str().to_string()),
+                HeapCellValue::Integer(ref n) => Term::Integer((**n).clone()),
+                HeapCellValue::Rational(ref r) => Term::Rational((**r).clone()),
+                HeapCellValue::DBRef(_) => {
+                    Term::Compound("$db_ref".to_string(), vec![Term::Integer(Integer::from(h))])
+                }
+                _ => unreachable!(),
+            },
+            Addr::Char(c) => Term::Atom(c.to_string()),
+            Addr::EmptyList => Term::List(vec![]),
+            Addr::Fixnum(n) => Term::Integer(Integer::from(n)),
+            Addr::Usize(n) => Term::Integer(Integer::from(n)),
+            Addr::Float(fl) => Term::Float(fl.into_inner()),
+            Addr::Pointer(h) => match &machine_st.heap[h] {
+                &HeapCellValue::Pointer(ptr) => Term::Compound(
+                    "$pointer".to_string(),
+                    vec![Term::Integer(Integer::from(ptr as usize))],
+                ),
+                _ => unreachable!(),
+            },
+            Addr::Stream(h) => {
+                Term::Compound("$stream".to_string(), vec![Term::Integer(Integer::from(h))])
+            }
+            Addr::TcpListener(h) => Term::Compound(
+                "$tcp_listener".to_string(),
+                vec![Term::Integer(Integer::from(h))],
+            ),
+            Addr::AttrVar(h) | Addr::HeapCell(h) => match self.var_names.get(&addr) {
+                Some(name) => Term::Var(name.clone()),
+                None => Term::Var(format!("_{}", h)),
+            },
+            Addr::StackCell(fr, sc) => match self.var_names.get(&addr) {
+                Some(name) => Term::Var(name.clone()),
+                None => Term::Var(format!("_S{}_{}", fr, sc)),
+            },
+            Addr::CutPoint(b) => Term::Integer(Integer::from(b)),
+            Addr::LoadStatePayload(_) => Term::Atom("$load_state_payload".to_string()),
+        }
+    }
+}
+
+// writes owned terms to the heap. Variables of the same name are
+// shared between the terms written by a single writer, except for _.
+pub(crate) struct TermWriter<'a> {
+    machine_st: &'a mut MachineState,
+    vars: IndexMap<String, Addr>,
+}
+
+impl<'a> TermWriter<'a> {
+    pub(crate) fn new(machine_st: &'a mut MachineState) -> Self {
+        TermWriter {
+            machine_st,
+            vars: IndexMap::new(),
+        }
+    }
+
+    fn fresh_var(&mut self) -> Addr {
+        let h = self.machine_st.heap.h();
+
+        self.machine_st
+            .heap
+            .push(HeapCellValue::Addr(Addr::HeapCell(h)));
+
+        Addr::HeapCell(h)
+    }
+
+    pub(crate) fn write(&mut self, term: &Term) -> Addr {
+        match term {
+            Term::Atom(atom) => {
+                let mut chars = atom.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Addr::Char(c),
+                    _ => {
+                        let atom = clause_name!(atom.clone(), self.machine_st.atom_tbl);
+                        Addr::Con(self.machine_st.heap.push(HeapCellValue::Atom(atom, None)))
+                    }
+                }
+            }
+            Term::Integer(n) => match n.to_isize() {
+                Some(n) => Addr::Fixnum(n),
+                None => Addr::Con(
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Integer(Rc::new(n.clone()))),
+                ),
+            },
+            Term::Rational(r) => Addr::Con(
+                self.machine_st
+                    .heap
+                    .push(HeapCellValue::Rational(Rc::new(r.clone()))),
+            ),
+            Term::Float(fl) => Addr::Float(OrderedFloat(*fl)),
+            Term::String(s) => self.machine_st.heap.put_complete_string(s),
+            Term::List(terms) => {
+                let addrs: Vec<_> = terms.iter().map(|term| self.write(term)).collect();
+
+                Addr::HeapCell(
+                    self.machine_st
+                        .heap
+                        .to_list(addrs.into_iter().map(HeapCellValue::Addr)),
+                )
+            }
+            Term::Compound(name, args) if args.is_empty() => {
+                self.write(&Term::Atom(name.clone()))
+            }
+            Term::Compound(name, args) => {
+                let addrs: Vec<_> = args.iter().map(|arg| self.write(arg)).collect();
+                let name = clause_name!(name.clone(), self.machine_st.atom_tbl);
+                let h = self.machine_st.heap.h();
+
+                self.machine_st
+                    .heap
+                    .push(HeapCellValue::NamedStr(addrs.len(), name, None));
+
+                for addr in addrs {
+                    self.machine_st.heap.push(HeapCellValue::Addr(addr));
+                }
+
+                Addr::Str(h)
+            }
+            Term::Var(name) if name == "_" => self.fresh_var(),
+            Term::Var(name) => match self.vars.get(name) {
+                Some(&addr) => addr,
+                None => {
+                    let addr = self.fresh_var();
+                    self.vars.insert(name.clone(), addr);
+                    addr
+                }
+            },
+        }
+    }
+}
+
+/* An iterator over the answers to a query. Dropping it before the
+ * answers are exhausted discards the rest of them. */
+pub struct QueryIter<'a> {
+    machine: &'a mut Machine,
+    state: QueryState,
+}
+
+enum QueryState {
+    Pending(Stream),
+    Running(usize), // the choice point of the driver.
+    Done,
+}
+
+impl<'a> Iterator for QueryIter<'a> {
+    type Item = Result<Bindings, PrologError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let answer = match mem::replace(&mut self.state, QueryState::Done) {
+            QueryState::Pending(stream) => self.machine.start_query("$run_query", stream),
+            QueryState::Running(_) => self.machine.resume_query(),
+            QueryState::Done => return None,
+        };
+
+        match answer {
+            (QueryAnswer::True(bindings), b) => {
+                self.state = QueryState::Running(b);
+                Some(Ok(bindings))
+            }
+            (QueryAnswer::Exception(ball), b) => {
+                self.machine.stop_query(b);
+                Some(Err(PrologError::Exception(ball)))
+            }
+            (QueryAnswer::SyntaxError(ball), b) => {
+                self.machine.stop_query(b);
+                Some(Err(PrologError::Syntax(ball)))
+            }
+            (QueryAnswer::False, _) => None,
+        }
+    }
+}
+
+impl<'a> Drop for QueryIter<'a> {
+    fn drop(&mut self) {
+        if let QueryState::Running(b) = self.state {
+            self.machine.stop_query(b);
+        }
+    }
+}
+
+impl Machine {
+    /// Compiles the clauses and directives of `src` into the `user` module.
+    pub fn consult_str(&mut self, src: &str) -> Result<(), PrologError> {
+        match self.start_query("$consult_stream", Stream::from(src.to_string())) {
+            (QueryAnswer::True(_), b) => {
+                self.stop_query(b);
+                Ok(())
+            }
+            (QueryAnswer::Exception(ball), b) | (QueryAnswer::SyntaxError(ball), b) => {
+                self.stop_query(b);
+                Err(PrologError::Exception(ball))
+            }
+            (QueryAnswer::False, _) => Ok(()),
+        }
+    }
+
+    /// Runs `query` in the `user` module, lazily producing its answers
+    /// as bindings of the variables named in it. The final period of
+    /// the query is optional.
+    pub fn query(&mut self, query: &str) -> QueryIter<'_> {
+        let mut query = query.trim_end().to_string();
+
+        if !query.ends_with('.') {
+            query.push_str(" .");
+        }
+
+        QueryIter {
+            machine: self,
+            state: QueryState::Pending(Stream::from(query)),
+        }
+    }
+
+    fn start_query(&mut self, driver: &'static str, stream: Stream) -> (QueryAnswer, usize) {
+        self.machine_st[temp_v!(1)] =
+            Addr::Stream(self.machine_st.heap.push(HeapCellValue::Stream(stream)));
+
+        self.run_module_predicate(clause_name!("$toplevel"), (clause_name!(driver), 1));
+        self.query_answer.take().unwrap_or((QueryAnswer::False, 0))
+    }
+
+    fn resume_query(&mut self) -> (QueryAnswer, usize) {
+        self.machine_st.backtrack();
+        self.run_query();
+
+        self.query_answer.take().unwrap_or((QueryAnswer::False, 0))
+    }
+
+    // discard the remaining answers by backtracking into the
+    // driver's final clause.
+    fn stop_query(&mut self, b: usize) {
+        self.machine_st.b = b;
+        self.resume_query();
+    }
+
+    pub(crate) fn yield_answer(&mut self) {
+        let answer = self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]));
+
+        let b = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(2)]))
+        {
+            Addr::Usize(b) => b,
+            _ => 0,
+        };
+
+        let answer = match answer {
+            Addr::Str(h) => match &self.machine_st.heap[h] {
+                HeapCellValue::NamedStr(1, ref name, _) if name.as_str() == "true" => {
+                    QueryAnswer::True(self.read_bindings(Addr::HeapCell(h + 1)))
+                }
+                HeapCellValue::NamedStr(1, ref name, _) if name.as_str() == "exception" => {
+                    let var_names = IndexMap::new();
+                    let ball =
+                        TermReader::read(&self.machine_st, &var_names, Addr::HeapCell(h + 1));
+
+                    QueryAnswer::Exception(ball)
+                }
+                HeapCellValue::NamedStr(1, ref name, _) if name.as_str() == "syntax_error" => {
+                    let var_names = IndexMap::new();
+                    let ball =
+                        TermReader::read(&self.machine_st, &var_names, Addr::HeapCell(h + 1));
+
+                    QueryAnswer::SyntaxError(ball)
+                }
+                _ => unreachable!(),
+            },
+            _ => QueryAnswer::False,
+        };
+
+        self.query_answer = Some((answer, b));
+        self.machine_st.p = CodePtr::Local(LocalCodePtr::Halt);
+    }
+
+    fn read_bindings(&self, var_list: Addr) -> Bindings {
+        let machine_st = &self.machine_st;
+
+        let mut pairs = vec![];
+        let mut var_list = machine_st.store(machine_st.deref(var_list));
+
+        while let Addr::Lis(l) = var_list {
+            if let Addr::Str(s) = machine_st.store(machine_st.deref(Addr::HeapCell(l))) {
+                let name = atom_from!(
+                    machine_st,
+                    machine_st.store(machine_st.deref(Addr::HeapCell(s + 1)))
+                );
+
+                pairs.push((name.as_str().to_string(), Addr::HeapCell(s + 2)));
+            }
+
+            var_list = machine_st.store(machine_st.deref(Addr::HeapCell(l + 1)));
+        }
+
+        // unbound variables are named after the query variable the
+        // others were bound to, if there is one.
+        let mut var_names = IndexMap::new();
+
+        for &prefer_unbound in &[true, false] {
+            for (name, addr) in &pairs {
+                let var = machine_st.store(*addr);
+                let addr = machine_st.store(machine_st.deref(var));
+
+                if addr.is_ref() && (addr == var || !prefer_unbound) {
+                    var_names.entry(addr).or_insert_with(|| name.clone());
+                }
+            }
+        }
+
+        pairs
+            .into_iter()
+            .map(|(name, addr)| (name, TermReader::read(machine_st, &var_names, addr)))
+            .collect()
+    }
+}
@@ -0,0 +1,490 @@
+use prolog_parser::ast::*;
+use prolog_parser::{clause_name, temp_v};
+
+use crate::machine::machine_errors::*;
+use crate::machine::machine_indices::*;
+use crate::machine::machine_state::*;
+
+use indexmap::IndexMap;
+use libc::{c_char, c_int, c_long, c_void};
+use libffi::low::ffi_sarg;
+use libffi::middle::{Arg, Cif, CodePtr, Type};
+use ordered_float::OrderedFloat;
+
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+
+/* C functions are called through libffi, with a call interface built
+ * from the argument and return types given to foreign/3. Libraries
+ * are opened with dlopen, so foreign functions are only available on
+ * Unix. */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FFIType {
+    Int,
+    Long,
+    Double,
+    String,
+    Pointer,
+    Buffer(usize), // a zeroed buffer of the given size, read back as a string.
+    Void,
+}
+
+impl FFIType {
+    fn ffi_type(self) -> Type {
+        match self {
+            FFIType::Int => Type::c_int(),
+            FFIType::Long => Type::c_long(),
+            FFIType::Double => Type::f64(),
+            FFIType::String | FFIType::Pointer | FFIType::Buffer(_) => Type::pointer(),
+            FFIType::Void => Type::void(),
+        }
+    }
+}
+
+// an argument converted to its C type, kept alive for the length of the call.
+enum FFIValue {
+    Int(c_int),
+    Long(c_long),
+    Double(f64),
+    Pointer(*mut c_void),
+}
+
+impl FFIValue {
+    fn arg(&self) -> Arg {
+        match self {
+            FFIValue::Int(n) => Arg::new(n),
+            FFIValue::Long(n) => Arg::new(n),
+            FFIValue::Double(f) => Arg::new(f),
+            FFIValue::Pointer(ptr) => Arg::new(ptr),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ForeignFunction {
+    ptr: *mut c_void,
+    cif: Cif,
+    args: Vec<FFIType>,
+    ret: FFIType,
+}
+
+#[derive(Debug)]
+pub(crate) struct ForeignLibraries {
+    libraries: IndexMap<String, *mut c_void>,
+    functions: IndexMap<ClauseName, ForeignFunction>,
+}
+
+impl ForeignLibraries {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        ForeignLibraries {
+            libraries: IndexMap::new(),
+            functions: IndexMap::new(),
+        }
+    }
+
+    // look the symbol up in the loaded libraries, then in those of the
+    // running executable.
+    #[cfg(unix)]
+    fn symbol(&self, name: &CStr) -> Option<*mut c_void> {
+        self.libraries
+            .values()
+            .chain(std::iter::once(&libc::RTLD_DEFAULT))
+            .map(|&handle| unsafe { libc::dlsym(handle, name.as_ptr()) })
+            .find(|ptr| !ptr.is_null())
+    }
+
+    #[cfg(not(unix))]
+    fn symbol(&self, _name: &CStr) -> Option<*mut c_void> {
+        None
+    }
+
+    // opens the library at path unless it's already open, returning
+    // false if it can't be opened.
+    #[cfg(unix)]
+    pub(super) fn open(&mut self, path: String) -> bool {
+        if self.libraries.contains_key(&path) {
+            return true;
+        }
+
+        let handle = match CString::new(path.as_str()) {
+            Ok(c_path) => unsafe {
+                libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL)
+            },
+            Err(_) => std::ptr::null_mut(),
+        };
+
+        if handle.is_null() {
+            false
+        } else {
+            self.libraries.insert(path, handle);
+            true
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn open(&mut self, _path: String) -> bool {
+        false
+    }
+
+    // binds name to the symbol of that name, returning false if no
+    // loaded library defines it.
+    pub(super) fn define(&mut self, name: ClauseName, args: Vec<FFIType>, ret: FFIType) -> bool {
+        let ptr = match CString::new(name.as_str()) {
+            Ok(c_name) => self.symbol(&c_name),
+            Err(_) => None,
+        };
+
+        match ptr {
+            Some(ptr) => {
+                let cif = Cif::new(args.iter().map(|ty| ty.ffi_type()), ret.ffi_type());
+
+                self.functions.insert(
+                    name,
+                    ForeignFunction {
+                        ptr,
+                        cif,
+                        args,
+                        ret,
+                    },
+                );
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub(super) fn paths(&self) -> impl Iterator<Item = &String> {
+        self.libraries.keys()
+    }
+
+    #[inline]
+    pub(super) fn definitions(&self) -> impl Iterator<Item = (&ClauseName, &[FFIType], FFIType)> {
+        self.functions
+            .iter()
+            .map(|(name, function)| (name, function.args.as_slice(), function.ret))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ForeignLibraries {
+    fn drop(&mut self) {
+        for (_, handle) in self.libraries.drain(..) {
+            unsafe {
+                libc::dlclose(handle);
+            }
+        }
+    }
+}
+
+impl MachineState {
+    fn ffi_type(&self, addr: Addr) -> Option<FFIType> {
+        match self.store(self.deref(addr)) {
+            Addr::Con(h) if self.heap.atom_at(h) => match &self.heap[h] {
+                HeapCellValue::Atom(ref name, _) => match name.as_str() {
+                    "int" => Some(FFIType::Int),
+                    "long" => Some(FFIType::Long),
+                    "double" => Some(FFIType::Double),
+                    "string" => Some(FFIType::String),
+                    "pointer" => Some(FFIType::Pointer),
+                    "void" => Some(FFIType::Void),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Addr::Str(h) => match &self.heap[h] {
+                HeapCellValue::NamedStr(1, ref name, _) if name.as_str() == "buffer" => {
+                    match self.store(self.deref(Addr::HeapCell(h + 1))) {
+                        Addr::Fixnum(n) if n > 0 => Some(FFIType::Buffer(n as usize)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn ffi_error(&self, valid_type: ValidType, culprit: Addr) -> MachineStub {
+        let stub = MachineError::functor_stub(clause_name!("ffi_call"), 3);
+
+        if culprit.is_ref() {
+            self.error_form(MachineError::instantiation_error(), stub)
+        } else {
+            self.error_form(
+                MachineError::type_error(self.heap.h(), valid_type, culprit),
+                stub,
+            )
+        }
+    }
+
+    fn ffi_int(&self, addr: Addr) -> Result<isize, MachineStub> {
+        match addr {
+            Addr::Fixnum(n) => Ok(n),
+            Addr::Con(h) => match &self.heap[h] {
+                HeapCellValue::Integer(ref n) => match n.to_isize() {
+                    Some(n) => Ok(n),
+                    None => {
+                        Err(self.representation_error(RepFlag::Term, clause_name!("ffi_call"), 3))
+                    }
+                },
+                _ => Err(self.ffi_error(ValidType::Integer, addr)),
+            },
+            _ => Err(self.ffi_error(ValidType::Integer, addr)),
+        }
+    }
+
+    fn ffi_double(&self, addr: Addr) -> Result<f64, MachineStub> {
+        match addr {
+            Addr::Float(OrderedFloat(f)) => Ok(f),
+            Addr::Fixnum(n) => Ok(n as f64),
+            Addr::Con(h) => match &self.heap[h] {
+                HeapCellValue::Integer(ref n) => Ok(n.to_f64()),
+                _ => Err(self.ffi_error(ValidType::Number, addr)),
+            },
+            _ => Err(self.ffi_error(ValidType::Number, addr)),
+        }
+    }
+
+    // atoms and strings are both passed as char*.
+    fn ffi_string(&self, addr: Addr) -> Result<CString, MachineStub> {
+        let string = match addr {
+            Addr::Con(h) if self.heap.atom_at(h) => match &self.heap[h] {
+                HeapCellValue::Atom(ref name, _) => name.as_str().to_string(),
+                _ => unreachable!(),
+            },
+            Addr::Char(c) => c.to_string(),
+            Addr::EmptyList | Addr::Lis(_) | Addr::PStrLocation(..) => {
+                let mut iter = self.heap_pstr_iter(addr);
+                let string = iter.to_string();
+
+                if iter.focus() != Addr::EmptyList {
+                    return Err(self.ffi_error(ValidType::Text, addr));
+                }
+
+                string
+            }
+            _ => return Err(self.ffi_error(ValidType::Text, addr)),
+        };
+
+        CString::new(string).map_err(|_| self.ffi_error(ValidType::Text, addr))
+    }
+
+    fn ffi_pointer(&self, addr: Addr) -> Result<*mut c_void, MachineStub> {
+        match addr {
+            Addr::Pointer(h) => Ok(self.heap.pointer(h)),
+            _ => Err(self.ffi_error(ValidType::Pointer, addr)),
+        }
+    }
+
+    pub(super) fn load_foreign_library(&mut self, libraries: &mut ForeignLibraries) {
+        let path = match self.store(self.deref(self[temp_v!(1)])) {
+            addr @ Addr::Con(_) | addr @ Addr::Char(_) => {
+                atom_from!(self, addr).as_str().to_string()
+            }
+            addr => self.heap_pstr_iter(addr).to_string(),
+        };
+
+        if !libraries.open(path) {
+            self.fail = true;
+        }
+    }
+
+    pub(super) fn define_foreign_function(
+        &mut self,
+        libraries: &mut ForeignLibraries,
+    ) -> CallResult {
+        let name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+        let stub = MachineError::functor_stub(clause_name!("foreign"), 3);
+        let addrs = match self.try_from_list(temp_v!(2), stub) {
+            Ok(addrs) => addrs,
+            Err(_) => {
+                self.fail = true;
+                return Ok(());
+            }
+        };
+
+        let mut args = Vec::with_capacity(addrs.len());
+
+        for addr in addrs {
+            match self.ffi_type(addr) {
+                // void is only a return type.
+                Some(FFIType::Void) => {
+                    let stub = MachineError::functor_stub(clause_name!("foreign"), 3);
+                    let err = MachineError::domain_error(
+                        DomainErrorType::ForeignType,
+                        self.store(self.deref(addr)),
+                    );
+
+                    return Err(self.error_form(err, stub));
+                }
+                Some(ty) => args.push(ty),
+                None => {
+                    self.fail = true;
+                    return Ok(());
+                }
+            }
+        }
+
+        let ret = match self.ffi_type(self[temp_v!(3)]) {
+            Some(ret) => ret,
+            None => {
+                self.fail = true;
+                return Ok(());
+            }
+        };
+
+        if !libraries.define(name, args, ret) {
+            self.fail = true;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn call_foreign_function(&mut self, libraries: &ForeignLibraries) -> CallResult {
+        let name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+        let function = match libraries.functions.get(&name) {
+            Some(function) => function,
+            None => {
+                self.fail = true;
+                return Ok(());
+            }
+        };
+
+        let stub = MachineError::functor_stub(clause_name!("ffi_call"), 3);
+        let args = self.try_from_list(temp_v!(2), stub)?;
+
+        if args.len() != function.args.len() {
+            self.fail = true;
+            return Ok(());
+        }
+
+        let mut values = Vec::with_capacity(args.len());
+
+        // the strings and buffers must outlive the call.
+        let mut strings = vec![];
+        let mut buffers = vec![];
+
+        for (&ty, addr) in function.args.iter().zip(args) {
+            let addr = self.store(self.deref(addr));
+
+            let value = match ty {
+                FFIType::Int => {
+                    let n = self.ffi_int(addr)?;
+
+                    match c_int::try_from(n) {
+                        Ok(n) => FFIValue::Int(n),
+                        Err(_) => {
+                            return Err(self.representation_error(
+                                RepFlag::Term,
+                                clause_name!("ffi_call"),
+                                3,
+                            ))
+                        }
+                    }
+                }
+                FFIType::Long => {
+                    let n = self.ffi_int(addr)?;
+
+                    match c_long::try_from(n) {
+                        Ok(n) => FFIValue::Long(n),
+                        Err(_) => {
+                            return Err(self.representation_error(
+                                RepFlag::Term,
+                                clause_name!("ffi_call"),
+                                3,
+                            ))
+                        }
+                    }
+                }
+                FFIType::Double => FFIValue::Double(self.ffi_double(addr)?),
+                FFIType::String => {
+                    let string = self.ffi_string(addr)?;
+                    let ptr = string.as_ptr() as *mut c_void;
+
+                    strings.push(string);
+                    FFIValue::Pointer(ptr)
+                }
+                FFIType::Pointer => FFIValue::Pointer(self.ffi_pointer(addr)?),
+                FFIType::Buffer(size) => {
+                    let mut buffer = vec![0u8; size];
+                    let ptr = buffer.as_mut_ptr() as *mut c_void;
+
+                    buffers.push((addr, buffer));
+                    FFIValue::Pointer(ptr)
+                }
+                FFIType::Void => {
+                    unreachable!("void argument types are rejected by define_foreign_function")
+                }
+            };
+
+            values.push(value);
+        }
+
+        let args: Vec<_> = values.iter().map(FFIValue::arg).collect();
+        let code = CodePtr::from_ptr(function.ptr);
+
+        let result = unsafe {
+            match function.ret {
+                FFIType::Int => {
+                    // libffi widens integral return values to a full
+                    // ffi_arg, so the int is read as one and narrowed.
+                    let n: ffi_sarg = function.cif.call(code, &args);
+                    Some(Addr::Fixnum(n as c_int as isize))
+                }
+                FFIType::Long => {
+                    let n: c_long = function.cif.call(code, &args);
+                    Some(Addr::Fixnum(n as isize))
+                }
+                FFIType::Double => {
+                    let f: f64 = function.cif.call(code, &args);
+                    Some(Addr::Float(OrderedFloat(f)))
+                }
+                FFIType::Pointer => {
+                    let ptr: *mut c_void = function.cif.call(code, &args);
+                    Some(self.heap.to_unifiable(HeapCellValue::Pointer(ptr)))
+                }
+                FFIType::String => {
+                    let ptr: *const c_char = function.cif.call(code, &args);
+
+                    if ptr.is_null() {
+                        // a NULL char* fails the call.
+                        self.fail = true;
+                        return Ok(());
+                    }
+
+                    let string = CStr::from_ptr(ptr);
+                    Some(self.heap.put_complete_string(&string.to_string_lossy()))
+                }
+                FFIType::Void | FFIType::Buffer(_) => {
+                    function.cif.call::<()>(code, &args);
+                    None
+                }
+            }
+        };
+
+        for (addr, buffer) in buffers {
+            let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            let contents = self
+                .heap
+                .put_complete_string(&String::from_utf8_lossy(&buffer[..len]));
+
+            self.unify(contents, addr);
+
+            if self.fail {
+                return Ok(());
+            }
+        }
+
+        if let Some(result) = result {
+            self.unify(result, self[temp_v!(3)]);
+        }
+
+        Ok(())
+    }
+}
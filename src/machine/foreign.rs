@@ -0,0 +1,216 @@
+use prolog_parser::temp_v;
+
+use crate::machine::embedding::{Integer, PrologError, Term, TermReader, TermWriter};
+use crate::machine::machine_indices::*;
+use crate::machine::*;
+
+use indexmap::IndexMap;
+
+use std::fmt;
+
+/* Predicates implemented in Rust by an embedding application. Each
+ * is compiled to a stub clause that passes its head to
+ * '$call_foreign'/2 in builtins.pl, which calls the Rust function
+ * through '$foreign_call'/5 and retries it on backtracking for as long
+ * as it asks to be redone. */
+
+pub enum ForeignResult {
+    Fail,
+    Succeed,
+    // succeed, leaving a choice point that calls the predicate again
+    // with the given redo state on backtracking.
+    Redo(usize),
+    Throw(Term),
+}
+
+pub type ForeignFn = Box<dyn Fn(&mut ForeignContext) -> ForeignResult>;
+
+pub(crate) struct ForeignPredicate(ForeignFn);
+
+impl fmt::Debug for ForeignPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ForeignPredicate")
+    }
+}
+
+pub(crate) type ForeignPredicates = IndexMap<(String, String, usize), ForeignPredicate>;
+
+pub struct ForeignContext<'a> {
+    machine_st: &'a mut MachineState,
+    args: usize,     // the heap location of the first argument.
+    solution: usize, // the heap location of the first argument to bind.
+    arity: usize,
+    redo: Option<usize>,
+}
+
+impl<'a> ForeignContext<'a> {
+    #[inline]
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The state passed to `ForeignResult::Redo` by the previous call,
+    /// or `None` on the first call.
+    #[inline]
+    pub fn redo_state(&self) -> Option<usize> {
+        self.redo
+    }
+
+    /// Reads the argument at `idx`, counting from 0.
+    pub fn arg(&self, idx: usize) -> Term {
+        assert!(idx < self.arity);
+
+        let var_names = IndexMap::new();
+        TermReader::read(self.machine_st, &var_names, Addr::HeapCell(self.args + idx))
+    }
+
+    /// Unifies the argument at `idx` with `term` once the predicate
+    /// succeeds. If the unification fails, so does the call, whatever
+    /// the predicate returns.
+    pub fn unify(&mut self, idx: usize, term: &Term) -> bool {
+        assert!(idx < self.arity);
+
+        if self.machine_st.fail {
+            return false;
+        }
+
+        let addr = TermWriter::new(self.machine_st).write(term);
+
+        self.machine_st
+            .unify(addr, Addr::HeapCell(self.solution + idx));
+
+        !self.machine_st.fail
+    }
+}
+
+impl Machine {
+    /// Defines `module:name/arity` as a call to `f`. Registering a
+    /// predicate again replaces its function.
+    pub fn register_foreign(
+        &mut self,
+        module: &str,
+        name: &str,
+        arity: usize,
+        f: ForeignFn,
+    ) -> Result<(), PrologError> {
+        let key = (module.to_string(), name.to_string(), arity);
+
+        if let Some(foreign_pred) = self.foreign_predicates.get_mut(&key) {
+            foreign_pred.0 = f;
+            return Ok(());
+        }
+
+        // the predicate is only registered once its stub compiles.
+        let id = self.foreign_predicates.len();
+
+        let head = if arity == 0 {
+            Term::Atom(name.to_string())
+        } else {
+            let args = (0..arity).map(|i| Term::Var(format!("A{}", i))).collect();
+            Term::Compound(name.to_string(), args)
+        };
+
+        let stub = Term::Compound(
+            ":-".to_string(),
+            vec![
+                Term::Compound(
+                    ":".to_string(),
+                    vec![Term::Atom(module.to_string()), head.clone()],
+                ),
+                Term::Compound(
+                    ":".to_string(),
+                    vec![
+                        Term::Atom("builtins".to_string()),
+                        Term::Compound(
+                            "$call_foreign".to_string(),
+                            vec![Term::Integer(Integer::from(id)), head],
+                        ),
+                    ],
+                ),
+            ],
+        );
+
+        self.consult_str(&format!("{}.", stub))?;
+        self.foreign_predicates.insert(key, ForeignPredicate(f));
+
+        Ok(())
+    }
+
+    pub(crate) fn foreign_call(&mut self) {
+        let id = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            Addr::Fixnum(id) => id as usize,
+            _ => unreachable!(),
+        };
+
+        let (args, arity) = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(2)]))
+        {
+            Addr::Str(h) => match &self.machine_st.heap[h] {
+                &HeapCellValue::NamedStr(arity, ..) => (h + 1, arity),
+                _ => unreachable!(),
+            },
+            _ => (0, 0),
+        };
+
+        let solution = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(3)]))
+        {
+            Addr::Str(h) => h + 1,
+            _ => 0,
+        };
+
+        let redo = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(4)]))
+        {
+            Addr::Str(h) => match self
+                .machine_st
+                .store(self.machine_st.deref(Addr::HeapCell(h + 1)))
+            {
+                Addr::Fixnum(state) => Some(state as usize),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let result = match self.foreign_predicates.get_index(id) {
+            Some((_, foreign_pred)) => {
+                let mut ctx = ForeignContext {
+                    machine_st: &mut self.machine_st,
+                    args,
+                    solution,
+                    arity,
+                    redo,
+                };
+
+                (foreign_pred.0)(&mut ctx)
+            }
+            None => unreachable!(),
+        };
+
+        if self.machine_st.fail {
+            return;
+        }
+
+        let control = match result {
+            ForeignResult::Fail => {
+                self.machine_st.fail = true;
+                return;
+            }
+            ForeignResult::Succeed => Term::Atom("det".to_string()),
+            ForeignResult::Redo(state) => Term::Compound(
+                "redo".to_string(),
+                vec![Term::Integer(Integer::from(state))],
+            ),
+            ForeignResult::Throw(ball) => Term::Compound("throw".to_string(), vec![ball]),
+        };
+
+        let control = TermWriter::new(&mut self.machine_st).write(&control);
+        self.machine_st.unify(control, self.machine_st[temp_v!(5)]);
+    }
+}
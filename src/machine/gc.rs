@@ -0,0 +1,403 @@
+use crate::machine::heap::*;
+use crate::machine::machine_indices::*;
+use crate::machine::machine_state::*;
+
+use indexmap::IndexSet;
+
+// the heap size, in cells, at which the first collection is triggered.
+pub(crate) const INITIAL_GC_THRESHOLD: usize = 1 << 20;
+
+/* A sliding mark-and-compact collector for the WAM heap. Live cells
+ * keep their relative order, which is what lets the heap boundaries
+ * saved in choice points (and hb) remain meaningful after a
+ * collection: everything allocated before a choice point stays
+ * below its (relocated) boundary. */
+
+struct Marker<'a> {
+    heap: &'a Heap,
+    marked: Vec<bool>,
+    worklist: Vec<usize>,
+}
+
+impl<'a> Marker<'a> {
+    fn new(heap: &'a Heap) -> Self {
+        Marker {
+            heap,
+            marked: vec![false; heap.h()],
+            worklist: vec![],
+        }
+    }
+
+    #[inline]
+    fn mark_cell(&mut self, h: usize) {
+        if h < self.marked.len() && !self.marked[h] {
+            self.marked[h] = true;
+            self.worklist.push(h);
+        }
+    }
+
+    fn mark_addr(&mut self, addr: Addr) {
+        match addr {
+            Addr::AttrVar(h) | Addr::Lis(h) => {
+                self.mark_cell(h);
+                self.mark_cell(h + 1);
+            }
+            Addr::Con(h)
+            | Addr::HeapCell(h)
+            | Addr::LoadStatePayload(h)
+            | Addr::PStrLocation(h, _)
+            | Addr::Str(h)
+            | Addr::Pointer(h)
+            | Addr::Stream(h)
+            | Addr::TcpListener(h) => {
+                self.mark_cell(h);
+            }
+            _ => {}
+        }
+    }
+
+    fn mark_trail_ref(&mut self, trail_ref: TrailRef) {
+        match trail_ref {
+            TrailRef::Ref(Ref::HeapCell(h))
+            | TrailRef::AttrVarHeapLink(h)
+            | TrailRef::BlackboardEntry(h) => {
+                self.mark_cell(h);
+            }
+            TrailRef::Ref(Ref::AttrVar(h)) => {
+                self.mark_addr(Addr::AttrVar(h));
+            }
+            TrailRef::Ref(Ref::StackCell(..)) => {}
+            TrailRef::AttrVarListLink(h, l) => {
+                self.mark_cell(h);
+                self.mark_addr(Addr::Lis(l));
+            }
+            TrailRef::BlackboardOffset(key_h, value_h) => {
+                self.mark_cell(key_h);
+                self.mark_cell(value_h);
+            }
+        }
+    }
+
+    fn trace(&mut self) {
+        while let Some(h) = self.worklist.pop() {
+            match self.heap[h] {
+                HeapCellValue::Addr(addr) => {
+                    self.mark_addr(addr);
+                }
+                HeapCellValue::NamedStr(arity, ..) => {
+                    for i in 1..arity + 1 {
+                        self.mark_cell(h + i);
+                    }
+                }
+                HeapCellValue::PartialString(_, true) => {
+                    self.mark_cell(h + 1);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// maps old heap locations to their post-compaction locations.
+struct Forwarder {
+    fwd: Vec<usize>,
+    old_h: usize,
+}
+
+impl Forwarder {
+    fn new(marked: &[bool]) -> Self {
+        let mut fwd = Vec::with_capacity(marked.len() + 1);
+        let mut live = 0;
+
+        for &is_marked in marked {
+            fwd.push(live);
+
+            if is_marked {
+                live += 1;
+            }
+        }
+
+        fwd.push(live);
+
+        Forwarder {
+            fwd,
+            old_h: marked.len(),
+        }
+    }
+
+    #[inline]
+    fn live(&self) -> usize {
+        self.fwd[self.old_h]
+    }
+
+    #[inline]
+    fn forward(&self, h: usize) -> usize {
+        if h <= self.old_h {
+            self.fwd[h]
+        } else {
+            h - (self.old_h - self.live())
+        }
+    }
+
+    fn forward_addr(&self, addr: &mut Addr) {
+        match addr {
+            Addr::AttrVar(ref mut h)
+            | Addr::Con(ref mut h)
+            | Addr::HeapCell(ref mut h)
+            | Addr::LoadStatePayload(ref mut h)
+            | Addr::Lis(ref mut h)
+            | Addr::PStrLocation(ref mut h, _)
+            | Addr::Str(ref mut h)
+            | Addr::Pointer(ref mut h)
+            | Addr::Stream(ref mut h)
+            | Addr::TcpListener(ref mut h) => {
+                *h = self.forward(*h);
+            }
+            _ => {}
+        }
+    }
+
+    fn forward_trail_ref(&self, trail_ref: &mut TrailRef) {
+        match trail_ref {
+            TrailRef::Ref(Ref::HeapCell(ref mut h))
+            | TrailRef::Ref(Ref::AttrVar(ref mut h))
+            | TrailRef::AttrVarHeapLink(ref mut h)
+            | TrailRef::BlackboardEntry(ref mut h) => {
+                *h = self.forward(*h);
+            }
+            TrailRef::Ref(Ref::StackCell(..)) => {}
+            TrailRef::AttrVarListLink(ref mut h, ref mut l) => {
+                *h = self.forward(*h);
+                *l = self.forward(*l);
+            }
+            TrailRef::BlackboardOffset(ref mut key_h, ref mut value_h) => {
+                *key_h = self.forward(*key_h);
+                *value_h = self.forward(*value_h);
+            }
+        }
+    }
+}
+
+impl MachineState {
+    // the environments reachable from the current environment and
+    // from the environments saved in live choice points.
+    fn live_frames(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut and_frames = IndexSet::new();
+        let mut or_frames = vec![];
+        let mut es = vec![self.e];
+
+        let mut b = self.b;
+
+        while b > 0 {
+            let or_frame = self.stack.index_or_frame(b);
+
+            or_frames.push(b);
+            es.push(or_frame.prelude.e);
+
+            b = or_frame.prelude.b;
+        }
+
+        for mut e in es {
+            while e > 0 && and_frames.insert(e) {
+                e = self.stack.index_and_frame(e).prelude.e;
+            }
+        }
+
+        (and_frames.into_iter().collect(), or_frames)
+    }
+
+    fn for_each_root<F: FnMut(&mut Addr)>(
+        &mut self,
+        and_frames: &[usize],
+        or_frames: &[usize],
+        global_variables: &mut GlobalVarDir,
+        cut_policy: &mut Box<dyn CutPolicy>,
+        mut f: F,
+    ) {
+        for addr in self.registers.iter_mut() {
+            f(addr);
+        }
+
+        for &e in and_frames {
+            let and_frame = self.stack.index_and_frame_mut(e);
+
+            for i in 1..and_frame.prelude.univ_prelude.num_cells + 1 {
+                f(&mut and_frame[i]);
+            }
+        }
+
+        for &b in or_frames {
+            let or_frame = self.stack.index_or_frame_mut(b);
+
+            for i in 0..or_frame.prelude.univ_prelude.num_cells {
+                f(&mut or_frame[i]);
+            }
+        }
+
+        for (_, ref mut loc) in global_variables.values_mut() {
+            if let Some(ref mut addr) = loc {
+                f(addr);
+            }
+        }
+
+        if let Ok(cut_policy) = cut_policy.downcast_mut::<SCCCutPolicy>() {
+            for addr in cut_policy.cont_pt_addrs_mut() {
+                f(addr);
+            }
+        }
+
+        for (_, ref mut addr) in self.attr_var_init.bindings.iter_mut() {
+            f(addr);
+        }
+    }
+
+    /* Drop the trail entries of heap cells younger than the choice
+     * point they were recorded under. hb isn't lowered on cut, so
+     * these pile up in deterministic code and would otherwise keep
+     * their cells alive indefinitely. */
+    fn tidy_trail(&mut self, or_frames: &[usize]) {
+        let mut keep = vec![true; self.trail.len()];
+        let mut k = 0;
+
+        for i in (0..self.trail.len()).rev() {
+            while k < or_frames.len() && self.stack.index_or_frame(or_frames[k]).prelude.tr > i {
+                k += 1;
+            }
+
+            let boundary = if k < or_frames.len() {
+                self.stack.index_or_frame(or_frames[k]).prelude.h
+            } else {
+                0
+            };
+
+            keep[i] = match self.trail[i] {
+                TrailRef::Ref(Ref::HeapCell(h))
+                | TrailRef::Ref(Ref::AttrVar(h))
+                | TrailRef::AttrVarHeapLink(h)
+                | TrailRef::AttrVarListLink(h, _) => h < boundary,
+                TrailRef::Ref(Ref::StackCell(..))
+                | TrailRef::BlackboardEntry(_)
+                | TrailRef::BlackboardOffset(..) => true,
+            };
+        }
+
+        let mut tr_fwd = Vec::with_capacity(keep.len() + 1);
+        let mut kept = 0;
+
+        for &keep in &keep {
+            tr_fwd.push(kept);
+
+            if keep {
+                kept += 1;
+            }
+        }
+
+        tr_fwd.push(kept);
+
+        let mut keep = keep.into_iter();
+        self.trail.retain(|_| keep.next().unwrap());
+
+        for &b in or_frames {
+            let or_frame = self.stack.index_or_frame_mut(b);
+            or_frame.prelude.tr = tr_fwd[or_frame.prelude.tr];
+        }
+
+        self.tr = tr_fwd[self.tr];
+    }
+
+    pub(super) fn collect_garbage(
+        &mut self,
+        global_variables: &mut GlobalVarDir,
+        cut_policy: &mut Box<dyn CutPolicy>,
+    ) {
+        let (and_frames, or_frames) = self.live_frames();
+        let mut roots = vec![];
+
+        self.tidy_trail(&or_frames);
+
+        self.for_each_root(
+            &and_frames,
+            &or_frames,
+            global_variables,
+            cut_policy,
+            |addr| roots.push(*addr),
+        );
+
+        let marked = {
+            let mut marker = Marker::new(&self.heap);
+
+            for addr in roots {
+                marker.mark_addr(addr);
+            }
+
+            for &trail_ref in &self.trail {
+                marker.mark_trail_ref(trail_ref);
+            }
+
+            for &h in &self.attr_var_init.attr_var_queue {
+                marker.mark_addr(Addr::AttrVar(h));
+            }
+
+            for &(h, _) in &self.attr_var_init.bindings {
+                marker.mark_addr(Addr::AttrVar(h));
+            }
+
+            match self.s {
+                HeapPtr::HeapCell(h) | HeapPtr::PStrChar(h, _) | HeapPtr::PStrLocation(h, _) => {
+                    marker.mark_cell(h);
+                }
+            }
+
+            marker.trace();
+            marker.marked
+        };
+
+        let fwd = Forwarder::new(&marked);
+
+        for cell in self.heap.iter_mut_from(0) {
+            if let HeapCellValue::Addr(ref mut addr) = cell {
+                fwd.forward_addr(addr);
+            }
+        }
+
+        self.for_each_root(
+            &and_frames,
+            &or_frames,
+            global_variables,
+            cut_policy,
+            |addr| fwd.forward_addr(addr),
+        );
+
+        for trail_ref in self.trail.iter_mut() {
+            fwd.forward_trail_ref(trail_ref);
+        }
+
+        for h in self.attr_var_init.attr_var_queue.iter_mut() {
+            *h = fwd.forward(*h);
+        }
+
+        for (h, _) in self.attr_var_init.bindings.iter_mut() {
+            *h = fwd.forward(*h);
+        }
+
+        for &b in &or_frames {
+            let or_frame = self.stack.index_or_frame_mut(b);
+            or_frame.prelude.h = fwd.forward(or_frame.prelude.h);
+        }
+
+        self.hb = fwd.forward(self.hb);
+
+        self.s = match self.s {
+            HeapPtr::HeapCell(h) => HeapPtr::HeapCell(fwd.forward(h)),
+            HeapPtr::PStrChar(h, n) => HeapPtr::PStrChar(fwd.forward(h), n),
+            HeapPtr::PStrLocation(h, n) => HeapPtr::PStrLocation(fwd.forward(h), n),
+        };
+
+        self.heap.compact(&marked);
+
+        // the atoms of the dropped cells were released by compact.
+        self.atom_tbl.sweep();
+
+        self.gc_threshold = std::cmp::max(INITIAL_GC_THRESHOLD, 2 * fwd.live());
+    }
+}
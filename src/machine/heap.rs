@@ -6,6 +6,8 @@ use crate::machine::machine_indices::*;
 use crate::machine::partial_string::*;
 use crate::machine::raw_block::*;
 
+use libc::c_void;
+
 use std::convert::TryFrom;
 use std::mem;
 use std::ops::{Index, IndexMut};
@@ -157,6 +159,7 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
             }
             &HeapCellValue::PartialString(..) => HeapCellValue::Addr(Addr::PStrLocation(h, 0)),
             &HeapCellValue::Rational(ref r) => HeapCellValue::Rational(r.clone()),
+            &HeapCellValue::Pointer(_) => HeapCellValue::Addr(Addr::Pointer(h)),
             &HeapCellValue::Stream(_) => HeapCellValue::Addr(Addr::Stream(h)),
             &HeapCellValue::TcpListener(_) => HeapCellValue::Addr(Addr::TcpListener(h)),
         }
@@ -261,6 +264,7 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
 
                 Addr::Con(h)
             }
+            val @ HeapCellValue::Pointer(..) => Addr::Pointer(self.push(val)),
             val @ HeapCellValue::Stream(..) => Addr::Stream(self.push(val)),
             val @ HeapCellValue::TcpListener(..) => Addr::TcpListener(self.push(val)),
         }
@@ -332,6 +336,41 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
         self.buf.top = new_top as *const _;
     }
 
+    /* Slide the cells flagged in marked to the bottom of the heap,
+     * preserving their relative order, and drop the rest. */
+    pub(crate) fn compact(&mut self, marked: &[bool]) {
+        debug_assert_eq!(marked.len(), self.h());
+
+        let size = mem::size_of::<HeapCellValue>();
+        let mut dest = self.buf.base as usize;
+
+        unsafe {
+            for (h, is_marked) in marked.iter().enumerate() {
+                let src = self.buf.base as usize + h * size;
+
+                if src >= self.buf.top as usize {
+                    break;
+                }
+
+                if *is_marked {
+                    if dest != src {
+                        ptr::copy_nonoverlapping(
+                            src as *const HeapCellValue,
+                            dest as *mut HeapCellValue,
+                            1,
+                        );
+                    }
+
+                    dest += size;
+                } else {
+                    ptr::drop_in_place(src as *mut HeapCellValue);
+                }
+            }
+        }
+
+        self.buf.top = dest as *const _;
+    }
+
     #[inline]
     pub(crate) fn h(&self) -> usize {
         (self.buf.top as usize - self.buf.base as usize) / mem::size_of::<HeapCellValue>()
@@ -428,10 +467,22 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
         }
     }
 
+    #[inline]
+    pub(crate) fn pointer(&self, h: usize) -> *mut c_void {
+        match self[h] {
+            HeapCellValue::Pointer(ptr) => ptr,
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub(crate) fn index_addr<'a>(&'a self, addr: &Addr) -> RefOrOwned<'a, HeapCellValue> {
         match addr {
-            &Addr::Con(h) | &Addr::Str(h) | &Addr::Stream(h) | &Addr::TcpListener(h) => {
+            &Addr::Con(h)
+            | &Addr::Str(h)
+            | &Addr::Pointer(h)
+            | &Addr::Stream(h)
+            | &Addr::TcpListener(h) => {
                 RefOrOwned::Borrowed(&self[h])
             }
             addr => RefOrOwned::Owned(HeapCellValue::Addr(*addr)),
@@ -0,0 +1,1528 @@
+use prolog_parser::ast::*;
+use prolog_parser::tabled_rc::*;
+use prolog_parser::{clause_name, temp_v};
+
+use crate::forms::*;
+use crate::indexing::IndexingCodePtr;
+use crate::instructions::*;
+use crate::machine::ffi::*;
+use crate::machine::machine_indices::*;
+use crate::machine::machine_state::*;
+use crate::machine::partial_string::PartialString;
+use crate::machine::*;
+use crate::rug::{Integer, Rational};
+
+use indexmap::IndexMap;
+use ordered_float::OrderedFloat;
+use slice_deque::SliceDeque;
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::fs;
+use std::hash::Hash;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/* A boot image is the state Machine::new leaves behind once it has
+ * compiled the bootstrapping libraries and loaded the toplevel: the
+ * code area, the code, op and meta-predicate directories of user and
+ * of every module, and the predicate skeletons. It's written the
+ * first time the scryer-prolog executable of a build starts up, to
+ * the cache directory of the user, and read in place of compiling the
+ * libraries from then on, by embedders too. Images are keyed by
+ * IMAGE_KEY, a hash of the crate sources computed by build.rs, so
+ * every build reads only its own image, and writing it removes those
+ * of other builds. Setting SCRYER_PROLOG_NO_BOOT_IMAGE in the
+ * environment turns boot images off, so that the libraries are
+ * compiled at every startup.
+ *
+ * CodeIndex and SharedOpDesc values are shared between the
+ * directories and the code that refers to them, so each is written
+ * once, with later occurrences written as references to it. Atoms are
+ * all read into the atom table of the machine, whether or not they
+ * were ClauseName::BuiltIn. The argument indices of the code
+ * repository aren't written, since they're rebuilt on demand.
+ *
+ * qsave_program/2 writes the same image of a machine that has loaded
+ * an application, as a saved state. Beyond what a boot image holds,
+ * which now includes the clauses asserted since, it holds the values
+ * of global variables set by bb_put/2, the occurs_check flag, and
+ * the foreign libraries and functions of library(ffi), which are
+ * loaded again when the state is restored. Streams, threads,
+ * backtrackable global variables and the foreign predicates of the C
+ * interface aren't saved.
+ *
+ * A stand-alone executable is a copy of the running executable with a
+ * saved state appended to it, followed by the length of the state as
+ * eight little-endian bytes and EXE_MAGIC. */
+
+const IMAGE_MAGIC: &[u8] = b"scryer-image";
+const EXE_MAGIC: &[u8] = b"scryer-standalone";
+
+const EXE_TRAILER_LEN: usize = 8 + EXE_MAGIC.len();
+
+// the largest arity tried in finding the arity of a SystemClauseType.
+const MAX_SYSTEM_ARITY: usize = 16;
+
+pub(super) struct ImageWriter {
+    bytes: Vec<u8>,
+    code_indices: IndexMap<*const Cell<IndexPtr>, usize>,
+    op_descs: IndexMap<*const Cell<(usize, Specifier)>, usize>,
+    complete: bool, // false if some of the state couldn't be written.
+}
+
+pub(super) struct ImageReader<'a> {
+    bytes: &'a [u8],
+    atom_tbl: TabledData<Atom>,
+    code_indices: Vec<CodeIndex>,
+    op_descs: Vec<SharedOpDesc>,
+}
+
+impl ImageWriter {
+    fn new() -> Self {
+        ImageWriter {
+            bytes: vec![],
+            code_indices: IndexMap::new(),
+            op_descs: IndexMap::new(),
+            complete: true,
+        }
+    }
+
+    #[inline]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        bytes.len().write(self);
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+impl<'a> ImageReader<'a> {
+    fn new(bytes: &'a [u8], atom_tbl: TabledData<Atom>) -> Self {
+        ImageReader {
+            bytes,
+            atom_tbl,
+            code_indices: vec![],
+            op_descs: vec![],
+        }
+    }
+
+    #[inline]
+    fn read_byte(&mut self) -> Option<u8> {
+        let (&byte, bytes) = self.bytes.split_first()?;
+        self.bytes = bytes;
+        Some(byte)
+    }
+
+    fn read_bytes(&mut self) -> Option<&'a [u8]> {
+        let len = usize::read(self)?;
+
+        if len > self.bytes.len() {
+            return None;
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Some(bytes)
+    }
+}
+
+pub(super) trait Image: Sized {
+    fn write(&self, w: &mut ImageWriter);
+    fn read(r: &mut ImageReader) -> Option<Self>;
+}
+
+macro_rules! read_field {
+    ($r:ident, $field:ident) => {
+        Image::read($r)?
+    };
+}
+
+// implements Image for an enum of unit and tuple variants, each of
+// which is written as its tag followed by its fields.
+macro_rules! image_enum {
+    ($ty:ident { $($tag:literal => $variant:ident $(($($field:ident),+))?),+ $(,)? }) => {
+        impl Image for $ty {
+            fn write(&self, w: &mut ImageWriter) {
+                match self {
+                    $($ty::$variant $(($($field),+))? => {
+                        w.write_byte($tag);
+                        $($($field.write(w);)+)?
+                    })+
+                }
+            }
+
+            fn read(r: &mut ImageReader) -> Option<Self> {
+                match r.read_byte()? {
+                    $($tag => Some($ty::$variant $(($(read_field!(r, $field)),+))?),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl Image for usize {
+    // LEB128, as most of the numbers of an image are small.
+    fn write(&self, w: &mut ImageWriter) {
+        let mut n = *self;
+
+        while n >= 0x80 {
+            w.write_byte((n as u8) | 0x80);
+            n >>= 7;
+        }
+
+        w.write_byte(n as u8);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let mut n: usize = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = r.read_byte()?;
+
+            if shift >= usize::BITS {
+                return None;
+            }
+
+            n |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Some(n);
+            }
+        }
+    }
+}
+
+impl Image for isize {
+    fn write(&self, w: &mut ImageWriter) {
+        (((*self << 1) ^ (*self >> (isize::BITS - 1))) as usize).write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let n = usize::read(r)?;
+        Some(((n >> 1) as isize) ^ -((n & 1) as isize))
+    }
+}
+
+impl Image for u32 {
+    fn write(&self, w: &mut ImageWriter) {
+        (*self as usize).write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        usize::read(r).and_then(|n| u32::try_from(n).ok())
+    }
+}
+
+impl Image for bool {
+    fn write(&self, w: &mut ImageWriter) {
+        w.write_byte(*self as u8);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        match r.read_byte()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Image for char {
+    fn write(&self, w: &mut ImageWriter) {
+        (*self as u32).write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        u32::read(r).and_then(std::char::from_u32)
+    }
+}
+
+impl Image for OrderedFloat<f64> {
+    fn write(&self, w: &mut ImageWriter) {
+        w.bytes.extend_from_slice(&self.0.to_bits().to_le_bytes());
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        if r.bytes.len() < 8 {
+            return None;
+        }
+
+        let (bytes, rest) = r.bytes.split_at(8);
+        r.bytes = rest;
+
+        let mut bits = [0u8; 8];
+        bits.copy_from_slice(bytes);
+
+        Some(OrderedFloat(f64::from_bits(u64::from_le_bytes(bits))))
+    }
+}
+
+impl Image for String {
+    fn write(&self, w: &mut ImageWriter) {
+        w.write_bytes(self.as_bytes());
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let bytes = r.read_bytes()?;
+        std::str::from_utf8(bytes).ok().map(String::from)
+    }
+}
+
+impl Image for Rc<String> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.as_ref().write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        String::read(r).map(Rc::new)
+    }
+}
+
+impl Image for Rc<Integer> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.to_string().write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        String::read(r)?.parse::<Integer>().ok().map(Rc::new)
+    }
+}
+
+impl Image for Rc<Rational> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.numer().to_string().write(w);
+        self.denom().to_string().write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let numer = String::read(r)?.parse::<Integer>().ok()?;
+        let denom = String::read(r)?.parse::<Integer>().ok()?;
+
+        if denom == 0 {
+            return None;
+        }
+
+        Some(Rc::new(Rational::from(numer) / Rational::from(denom)))
+    }
+}
+
+impl Image for PathBuf {
+    fn write(&self, w: &mut ImageWriter) {
+        match self.to_str() {
+            Some(path) => path.to_string().write(w),
+            None => w.complete = false,
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        String::read(r).map(PathBuf::from)
+    }
+}
+
+impl<T: Image> Image for Option<T> {
+    fn write(&self, w: &mut ImageWriter) {
+        match self {
+            Some(t) => {
+                w.write_byte(1);
+                t.write(w);
+            }
+            None => {
+                w.write_byte(0);
+            }
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        match r.read_byte()? {
+            0 => Some(None),
+            1 => T::read(r).map(Some),
+            _ => None,
+        }
+    }
+}
+
+impl<A: Image, B: Image> Image for (A, B) {
+    fn write(&self, w: &mut ImageWriter) {
+        self.0.write(w);
+        self.1.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let a = A::read(r)?;
+        let b = B::read(r)?;
+
+        Some((a, b))
+    }
+}
+
+impl<A: Image, B: Image, C: Image> Image for (A, B, C) {
+    fn write(&self, w: &mut ImageWriter) {
+        self.0.write(w);
+        self.1.write(w);
+        self.2.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let a = A::read(r)?;
+        let b = B::read(r)?;
+        let c = C::read(r)?;
+
+        Some((a, b, c))
+    }
+}
+
+impl<T: Image> Image for Vec<T> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.len().write(w);
+
+        for t in self {
+            t.write(w);
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let len = usize::read(r)?;
+        let mut ts = Vec::with_capacity(len.min(r.bytes.len()));
+
+        for _ in 0..len {
+            ts.push(T::read(r)?);
+        }
+
+        Some(ts)
+    }
+}
+
+impl<T: Image> Image for SliceDeque<T> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.len().write(w);
+
+        for t in self.iter() {
+            t.write(w);
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let len = usize::read(r)?;
+        let mut ts = SliceDeque::new();
+
+        for _ in 0..len {
+            ts.push_back(T::read(r)?);
+        }
+
+        Some(ts)
+    }
+}
+
+impl<K: Image + Hash + Eq, V: Image> Image for IndexMap<K, V> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.len().write(w);
+
+        for (k, v) in self {
+            k.write(w);
+            v.write(w);
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let len = usize::read(r)?;
+        let mut map = IndexMap::with_capacity(len.min(r.bytes.len()));
+
+        for _ in 0..len {
+            let k = K::read(r)?;
+            let v = V::read(r)?;
+
+            map.insert(k, v);
+        }
+
+        Some(map)
+    }
+}
+
+impl<K: Image + Ord, V: Image> Image for BTreeMap<K, V> {
+    fn write(&self, w: &mut ImageWriter) {
+        self.len().write(w);
+
+        for (k, v) in self {
+            k.write(w);
+            v.write(w);
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let len = usize::read(r)?;
+        let mut map = BTreeMap::new();
+
+        for _ in 0..len {
+            let k = K::read(r)?;
+            let v = V::read(r)?;
+
+            map.insert(k, v);
+        }
+
+        Some(map)
+    }
+}
+
+impl Image for ClauseName {
+    fn write(&self, w: &mut ImageWriter) {
+        self.as_str().to_string().write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let name = String::read(r)?;
+        Some(clause_name!(name, r.atom_tbl))
+    }
+}
+
+impl Image for CodeIndex {
+    // the first occurrence of a CodeIndex is written as the next
+    // unused reference, followed by its IndexPtr.
+    fn write(&self, w: &mut ImageWriter) {
+        let ptr = Rc::as_ptr(&self.0);
+        let next = w.code_indices.len();
+
+        match w.code_indices.get(&ptr) {
+            Some(&idx) => {
+                idx.write(w);
+            }
+            None => {
+                w.code_indices.insert(ptr, next);
+                next.write(w);
+                self.get().write(w);
+            }
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let idx = usize::read(r)?;
+
+        if idx < r.code_indices.len() {
+            Some(r.code_indices[idx].clone())
+        } else if idx == r.code_indices.len() {
+            let code_index = CodeIndex::new(IndexPtr::read(r)?);
+            r.code_indices.push(code_index.clone());
+            Some(code_index)
+        } else {
+            None
+        }
+    }
+}
+
+impl Image for SharedOpDesc {
+    // written like CodeIndex.
+    fn write(&self, w: &mut ImageWriter) {
+        let ptr = &**self as *const Cell<(usize, Specifier)>;
+        let next = w.op_descs.len();
+
+        match w.op_descs.get(&ptr) {
+            Some(&idx) => {
+                idx.write(w);
+            }
+            None => {
+                w.op_descs.insert(ptr, next);
+                next.write(w);
+                self.get().write(w);
+            }
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let idx = usize::read(r)?;
+
+        if idx < r.op_descs.len() {
+            Some(r.op_descs[idx].clone())
+        } else if idx == r.op_descs.len() {
+            let (prec, spec) = <(usize, Specifier)>::read(r)?;
+            let op_desc = SharedOpDesc::new(prec, spec);
+
+            r.op_descs.push(op_desc.clone());
+            Some(op_desc)
+        } else {
+            None
+        }
+    }
+}
+
+impl Image for OpDirValue {
+    fn write(&self, w: &mut ImageWriter) {
+        self.0.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        SharedOpDesc::read(r).map(OpDirValue)
+    }
+}
+
+impl Image for SystemClauseType {
+    // written by name and arity, save for the types with fields.
+    fn write(&self, w: &mut ImageWriter) {
+        match *self {
+            SystemClauseType::DynamicModuleResolution(narity) => {
+                w.write_byte(0);
+                narity.write(w);
+            }
+            SystemClauseType::SetCutPoint(r) => {
+                w.write_byte(1);
+                r.write(w);
+            }
+            SystemClauseType::SetCutPointByDefault(r) => {
+                w.write_byte(2);
+                r.write(w);
+            }
+            _ => {
+                let name = self.name();
+                let arity = (0..=MAX_SYSTEM_ARITY)
+                    .find(|&arity| SystemClauseType::from(name.as_str(), arity) == Some(*self));
+
+                match arity {
+                    Some(arity) => {
+                        w.write_byte(3);
+                        name.write(w);
+                        arity.write(w);
+                    }
+                    None => {
+                        w.complete = false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        match r.read_byte()? {
+            0 => Some(SystemClauseType::DynamicModuleResolution(usize::read(r)?)),
+            1 => Some(SystemClauseType::SetCutPoint(RegType::read(r)?)),
+            2 => Some(SystemClauseType::SetCutPointByDefault(RegType::read(r)?)),
+            3 => {
+                let name = String::read(r)?;
+                let arity = usize::read(r)?;
+
+                SystemClauseType::from(&name, arity)
+            }
+            _ => None,
+        }
+    }
+}
+
+image_enum!(RegType {
+    0 => Perm(r),
+    1 => Temp(r),
+});
+
+image_enum!(Fixity {
+    0 => In,
+    1 => Post,
+    2 => Pre,
+});
+
+image_enum!(DoubleQuotes {
+    0 => Atom,
+    1 => Chars,
+    2 => Codes,
+});
+
+image_enum!(Constant {
+    0 => Atom(name, spec),
+    1 => Char(c),
+    2 => EmptyList,
+    3 => Fixnum(n),
+    4 => Integer(n),
+    5 => Rational(n),
+    6 => Float(f),
+    7 => String(s),
+    8 => Usize(n),
+});
+
+image_enum!(Number {
+    0 => Float(f),
+    1 => Integer(n),
+    2 => Rational(n),
+    3 => Fixnum(n),
+});
+
+image_enum!(IndexPtr {
+    0 => DynamicUndefined,
+    1 => DynamicIndex(p),
+    2 => Index(p),
+    3 => Undefined,
+});
+
+image_enum!(Level {
+    0 => Deep,
+    1 => Root,
+    2 => Shallow,
+});
+
+image_enum!(ArithmeticTerm {
+    0 => Reg(r),
+    1 => Interm(i),
+    2 => Number(n),
+});
+
+image_enum!(CompareNumberQT {
+    0 => GreaterThan,
+    1 => LessThan,
+    2 => GreaterThanOrEqual,
+    3 => LessThanOrEqual,
+    4 => NotEqual,
+    5 => Equal,
+});
+
+image_enum!(CompareTermQT {
+    0 => LessThan,
+    1 => LessThanOrEqual,
+    2 => GreaterThanOrEqual,
+    3 => GreaterThan,
+});
+
+image_enum!(InlinedClauseType {
+    0 => CompareNumber(qt, at_1, at_2),
+    1 => IsAtom(r),
+    2 => IsAtomic(r),
+    3 => IsCompound(r),
+    4 => IsInteger(r),
+    5 => IsNumber(r),
+    6 => IsRational(r),
+    7 => IsFloat(r),
+    8 => IsNonVar(r),
+    9 => IsVar(r),
+});
+
+image_enum!(BuiltInClauseType {
+    0 => AcyclicTerm,
+    1 => Arg,
+    2 => Compare,
+    3 => CompareTerm(qt),
+    4 => CopyTerm,
+    5 => Eq,
+    6 => Functor,
+    7 => Ground,
+    8 => Is(r, at),
+    9 => KeySort,
+    10 => NotEq,
+    11 => Read,
+    12 => Sort,
+});
+
+image_enum!(ClauseType {
+    0 => BuiltIn(ct),
+    1 => CallN,
+    2 => Inlined(ct),
+    3 => Named(name, arity, idx),
+    4 => Op(name, spec, idx),
+    5 => System(ct),
+});
+
+image_enum!(ArithmeticInstruction {
+    0 => Add(at_1, at_2, t),
+    1 => Sub(at_1, at_2, t),
+    2 => Mul(at_1, at_2, t),
+    3 => Pow(at_1, at_2, t),
+    4 => IntPow(at_1, at_2, t),
+    5 => IDiv(at_1, at_2, t),
+    6 => Max(at_1, at_2, t),
+    7 => Min(at_1, at_2, t),
+    8 => IntFloorDiv(at_1, at_2, t),
+    9 => RDiv(at_1, at_2, t),
+    10 => Div(at_1, at_2, t),
+    11 => Shl(at_1, at_2, t),
+    12 => Shr(at_1, at_2, t),
+    13 => Xor(at_1, at_2, t),
+    14 => And(at_1, at_2, t),
+    15 => Or(at_1, at_2, t),
+    16 => Mod(at_1, at_2, t),
+    17 => Rem(at_1, at_2, t),
+    18 => Gcd(at_1, at_2, t),
+    19 => Sign(at, t),
+    20 => Cos(at, t),
+    21 => Sin(at, t),
+    22 => Tan(at, t),
+    23 => Log(at, t),
+    24 => Exp(at, t),
+    25 => ACos(at, t),
+    26 => ASin(at, t),
+    27 => ATan(at, t),
+    28 => ATan2(at_1, at_2, t),
+    29 => Sqrt(at, t),
+    30 => Abs(at, t),
+    31 => Float(at, t),
+    32 => Truncate(at, t),
+    33 => Round(at, t),
+    34 => Ceiling(at, t),
+    35 => Floor(at, t),
+    36 => Neg(at, t),
+    37 => Plus(at, t),
+    38 => BitwiseComplement(at, t),
+});
+
+image_enum!(Death {
+    0 => Finite(d),
+    1 => Infinity,
+});
+
+image_enum!(NextOrFail {
+    0 => Next(i),
+    1 => Fail(i),
+});
+
+image_enum!(ChoiceInstruction {
+    0 => DynamicElse(birth, death, next_or_fail),
+    1 => DynamicInternalElse(birth, death, next_or_fail),
+    2 => DefaultRetryMeElse(o),
+    3 => DefaultTrustMe(o),
+    4 => RetryMeElse(o),
+    5 => TrustMe(o),
+    6 => TryMeElse(o),
+});
+
+image_enum!(ControlInstruction {
+    0 => Allocate(num_frames),
+    1 => CallClause(ct, arity, perm_vars, last_call, use_default_cp),
+    2 => Deallocate,
+    3 => JmpBy(arity, offset, perm_vars, last_call),
+    4 => RevJmpBy(offset),
+    5 => Proceed,
+});
+
+image_enum!(CutInstruction {
+    0 => Cut(r),
+    1 => GetLevel(r),
+    2 => GetLevelAndUnify(r),
+    3 => NeckCut,
+});
+
+image_enum!(FactInstruction {
+    0 => GetConstant(lvl, c, r),
+    1 => GetList(lvl, r),
+    2 => GetPartialString(lvl, s, r, has_tail),
+    3 => GetStructure(ct, arity, r),
+    4 => GetValue(r, arg),
+    5 => GetVariable(r, arg),
+    6 => UnifyConstant(c),
+    7 => UnifyLocalValue(r),
+    8 => UnifyVariable(r),
+    9 => UnifyValue(r),
+    10 => UnifyVoid(n),
+});
+
+image_enum!(QueryInstruction {
+    0 => GetVariable(r, arg),
+    1 => PutConstant(lvl, c, r),
+    2 => PutList(lvl, r),
+    3 => PutPartialString(lvl, s, r, has_tail),
+    4 => PutStructure(ct, arity, r),
+    5 => PutUnsafeValue(perm, arg),
+    6 => PutValue(r, arg),
+    7 => PutVariable(r, arg),
+    8 => SetConstant(c),
+    9 => SetLocalValue(r),
+    10 => SetVariable(r),
+    11 => SetValue(r),
+    12 => SetVoid(n),
+});
+
+image_enum!(IndexingCodePtr {
+    0 => External(o),
+    1 => DynamicExternal(o),
+    2 => Fail,
+    3 => Internal(o),
+});
+
+image_enum!(IndexingInstruction {
+    0 => SwitchOnTerm(arg, v, c, l, s),
+    1 => SwitchOnConstant(constants),
+    2 => SwitchOnStructure(structures),
+    3 => SwitchOnSubterm(arg, v, c, l, s),
+});
+
+image_enum!(IndexedChoiceInstruction {
+    0 => Retry(o),
+    1 => Trust(o),
+    2 => Try(o),
+});
+
+image_enum!(IndexingLine {
+    0 => Indexing(instr),
+    1 => IndexedChoice(instrs),
+    2 => DynamicIndexedChoice(instrs),
+});
+
+image_enum!(Line {
+    0 => Arithmetic(instr),
+    1 => Choice(instr),
+    2 => Control(instr),
+    3 => Cut(instr),
+    4 => Fact(instr),
+    5 => IndexingCode(lines),
+    6 => IndexedChoice(instr),
+    7 => DynamicIndexedChoice(o),
+    8 => Query(instr),
+});
+
+image_enum!(MetaSpec {
+    0 => Minus,
+    1 => Plus,
+    2 => Either,
+    3 => RequiresExpansionWithArgument(n),
+});
+
+image_enum!(CompilationTarget {
+    0 => Module(name),
+    1 => User,
+});
+
+image_enum!(ListingSource {
+    0 => DynamicallyGenerated,
+    1 => File(name, path),
+    2 => User,
+});
+
+image_enum!(ModuleExport {
+    0 => OpDecl(op_decl),
+    1 => PredicateKey(key),
+});
+
+image_enum!(OptArgIndexKey {
+    0 => Constant(arg, loc, c, alts),
+    1 => List(arg, loc),
+    2 => None,
+    3 => Structure(arg, loc, name, arity),
+});
+
+impl Image for OpDecl {
+    fn write(&self, w: &mut ImageWriter) {
+        self.prec.write(w);
+        self.spec.write(w);
+        self.name.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(OpDecl {
+            prec: Image::read(r)?,
+            spec: Image::read(r)?,
+            name: Image::read(r)?,
+        })
+    }
+}
+
+impl Image for ModuleDecl {
+    fn write(&self, w: &mut ImageWriter) {
+        self.name.write(w);
+        self.exports.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(ModuleDecl {
+            name: Image::read(r)?,
+            exports: Image::read(r)?,
+        })
+    }
+}
+
+impl Image for ClauseIndexInfo {
+    fn write(&self, w: &mut ImageWriter) {
+        self.clause_start.write(w);
+        self.opt_arg_index_key.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(ClauseIndexInfo {
+            clause_start: Image::read(r)?,
+            opt_arg_index_key: Image::read(r)?,
+        })
+    }
+}
+
+impl Image for LocalPredicateSkeleton {
+    fn write(&self, w: &mut ImageWriter) {
+        self.is_discontiguous.write(w);
+        self.is_dynamic.write(w);
+        self.is_multifile.write(w);
+        self.clause_clause_locs.write(w);
+        self.clause_assert_margin.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(LocalPredicateSkeleton {
+            is_discontiguous: Image::read(r)?,
+            is_dynamic: Image::read(r)?,
+            is_multifile: Image::read(r)?,
+            clause_clause_locs: Image::read(r)?,
+            clause_assert_margin: Image::read(r)?,
+        })
+    }
+}
+
+impl Image for PredicateSkeleton {
+    fn write(&self, w: &mut ImageWriter) {
+        self.core.write(w);
+        self.clauses.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(PredicateSkeleton {
+            core: Image::read(r)?,
+            clauses: Image::read(r)?,
+        })
+    }
+}
+
+image_enum!(Addr {
+    0 => AttrVar(h),
+    1 => Char(c),
+    2 => Con(h),
+    3 => CutPoint(b),
+    4 => EmptyList,
+    5 => Fixnum(n),
+    6 => Float(f),
+    7 => Lis(l),
+    8 => LoadStatePayload(h),
+    9 => HeapCell(h),
+    10 => PStrLocation(h, n),
+    11 => Pointer(h),
+    12 => StackCell(fr, sc),
+    13 => Str(s),
+    14 => Stream(h),
+    15 => TcpListener(h),
+    16 => Usize(n),
+});
+
+image_enum!(FFIType {
+    0 => Int,
+    1 => Double,
+    2 => String,
+    3 => Pointer,
+    4 => Buffer(size),
+    5 => Void,
+    6 => Long,
+});
+
+impl Image for HeapCellValue {
+    fn write(&self, w: &mut ImageWriter) {
+        match *self {
+            HeapCellValue::Addr(ref addr) => {
+                w.write_byte(0);
+                addr.write(w);
+            }
+            HeapCellValue::Atom(ref name, ref spec) => {
+                w.write_byte(1);
+                name.write(w);
+                spec.write(w);
+            }
+            HeapCellValue::Integer(ref n) => {
+                w.write_byte(2);
+                n.write(w);
+            }
+            HeapCellValue::NamedStr(arity, ref name, ref spec) => {
+                w.write_byte(3);
+                arity.write(w);
+                name.write(w);
+                spec.write(w);
+            }
+            HeapCellValue::Rational(ref r) => {
+                w.write_byte(4);
+                r.write(w);
+            }
+            HeapCellValue::PartialString(ref pstr, from_constant) => {
+                w.write_byte(5);
+                pstr.as_str_from(0).to_string().write(w);
+                from_constant.write(w);
+            }
+            // database references, streams, sockets and C pointers
+            // don't outlive the process that made them.
+            HeapCellValue::DBRef(_)
+            | HeapCellValue::LoadStatePayload(_)
+            | HeapCellValue::Pointer(_)
+            | HeapCellValue::Stream(_)
+            | HeapCellValue::TcpListener(_) => {
+                w.complete = false;
+            }
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        match r.read_byte()? {
+            0 => Some(HeapCellValue::Addr(Image::read(r)?)),
+            1 => Some(HeapCellValue::Atom(Image::read(r)?, Image::read(r)?)),
+            2 => Some(HeapCellValue::Integer(Image::read(r)?)),
+            3 => Some(HeapCellValue::NamedStr(
+                Image::read(r)?,
+                Image::read(r)?,
+                Image::read(r)?,
+            )),
+            4 => Some(HeapCellValue::Rational(Image::read(r)?)),
+            5 => {
+                let string = String::read(r)?;
+
+                match PartialString::new(&string)? {
+                    (pstr, "") => Some(HeapCellValue::PartialString(pstr, bool::read(r)?)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Image for Ball {
+    fn write(&self, w: &mut ImageWriter) {
+        self.boundary.write(w);
+        self.stub.h().write(w);
+
+        for heap_value in self.stub.iter_from(0) {
+            heap_value.write(w);
+        }
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        let mut ball = Ball::new();
+
+        ball.boundary = usize::read(r)?;
+
+        for _ in 0..usize::read(r)? {
+            ball.stub.push(HeapCellValue::read(r)?);
+        }
+
+        Some(ball)
+    }
+}
+
+impl Image for Module {
+    fn write(&self, w: &mut ImageWriter) {
+        self.module_decl.write(w);
+        self.code_dir.write(w);
+        self.op_dir.write(w);
+        self.meta_predicates.write(w);
+        self.extensible_predicates.write(w);
+        self.local_extensible_predicates.write(w);
+        self.is_impromptu_module.write(w);
+        self.listing_src.write(w);
+    }
+
+    fn read(r: &mut ImageReader) -> Option<Self> {
+        Some(Module {
+            module_decl: Image::read(r)?,
+            code_dir: Image::read(r)?,
+            op_dir: Image::read(r)?,
+            meta_predicates: Image::read(r)?,
+            extensible_predicates: Image::read(r)?,
+            local_extensible_predicates: Image::read(r)?,
+            is_impromptu_module: Image::read(r)?,
+            listing_src: Image::read(r)?,
+        })
+    }
+}
+
+const NO_BOOT_IMAGE_VAR: &str = "SCRYER_PROLOG_NO_BOOT_IMAGE";
+
+#[inline]
+fn image_path() -> Option<PathBuf> {
+    if std::env::var_os(NO_BOOT_IMAGE_VAR).is_some() {
+        return None;
+    }
+
+    let mut path = dirs_next::cache_dir()?;
+
+    path.push("scryer-prolog");
+    path.push(format!("boot-{}.img", IMAGE_KEY));
+
+    Some(path)
+}
+
+// bytes are written to a file of their own and renamed into place at
+// path, so that a machine reading path never sees them partly written.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.to_path_buf();
+    tmp_path.set_extension(format!("{}.tmp", std::process::id()));
+
+    let result = fs::write(&tmp_path, bytes).and_then(|_| fs::rename(&tmp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+// stand-alone executables need the execute permission on Unix.
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// the length of the saved state ahead of the trailer of a stand-alone
+// executable, or None if trailer isn't one.
+fn embedded_state_len(trailer: &[u8]) -> Option<usize> {
+    if trailer.len() != EXE_TRAILER_LEN || &trailer[8..] != EXE_MAGIC {
+        return None;
+    }
+
+    let mut len_bytes = [0u8; 8];
+    len_bytes.copy_from_slice(&trailer[..8]);
+
+    usize::try_from(u64::from_le_bytes(len_bytes)).ok()
+}
+
+// the saved state appended to the running executable, if it's a
+// stand-alone executable.
+fn embedded_state() -> Option<Vec<u8>> {
+    let mut file = fs::File::open(std::env::current_exe().ok()?).ok()?;
+    let trailer_loc = file
+        .metadata()
+        .ok()?
+        .len()
+        .checked_sub(EXE_TRAILER_LEN as u64)?;
+
+    let mut trailer = [0u8; EXE_TRAILER_LEN];
+
+    file.seek(SeekFrom::Start(trailer_loc)).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+
+    let len = embedded_state_len(&trailer)?;
+    let state_loc = trailer_loc.checked_sub(len as u64)?;
+    let mut bytes = vec![0u8; len];
+
+    file.seek(SeekFrom::Start(state_loc)).ok()?;
+    file.read_exact(&mut bytes).ok()?;
+
+    Some(bytes)
+}
+
+// the running executable with state appended to it. If it's a
+// stand-alone executable, the state it carries is replaced.
+fn stand_alone_executable(state: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut exe = fs::read(std::env::current_exe()?)?;
+
+    if let Some(trailer_loc) = exe.len().checked_sub(EXE_TRAILER_LEN) {
+        if let Some(len) = embedded_state_len(&exe[trailer_loc..]) {
+            exe.truncate(trailer_loc.saturating_sub(len));
+        }
+    }
+
+    let len = state.len() as u64;
+
+    exe.extend(state);
+    exe.extend_from_slice(&len.to_le_bytes());
+    exe.extend_from_slice(EXE_MAGIC);
+
+    Ok(exe)
+}
+
+impl Machine {
+    // 0 if the occurs_check flag is false, 1 if it's true and 2 if
+    // it's error.
+    fn occurs_check(&self) -> u8 {
+        let unify_fn = self.machine_st.unify_fn as *const ();
+
+        if unify_fn == MachineState::unify_with_occurs_check as *const () {
+            1
+        } else if unify_fn == MachineState::unify_with_occurs_check_with_error as *const () {
+            2
+        } else {
+            0
+        }
+    }
+
+    fn set_occurs_check(&mut self, occurs_check: u8) {
+        match occurs_check {
+            1 => {
+                self.machine_st.unify_fn = MachineState::unify_with_occurs_check;
+                self.machine_st.bind_fn = MachineState::bind_with_occurs_check_wrapper;
+            }
+            2 => {
+                self.machine_st.unify_fn = MachineState::unify_with_occurs_check_with_error;
+                self.machine_st.bind_fn = MachineState::bind_with_occurs_check_with_error_wrapper;
+            }
+            _ => {
+                self.machine_st.unify_fn = MachineState::unify;
+                self.machine_st.bind_fn = MachineState::bind;
+            }
+        }
+    }
+
+    // the image of the machine, or None if some of its state can't be
+    // written to one.
+    fn write_image(&self) -> Option<Vec<u8>> {
+        let mut w = ImageWriter::new();
+
+        w.bytes.extend_from_slice(IMAGE_MAGIC);
+        IMAGE_KEY.to_string().write(&mut w);
+
+        self.code_repo.code.write(&mut w);
+        self.code_repo.predicate_extents.0.write(&mut w);
+        self.code_repo.clause_locations.0.write(&mut w);
+
+        self.indices.code_dir.write(&mut w);
+        self.indices.extensible_predicates.write(&mut w);
+        self.indices.local_extensible_predicates.write(&mut w);
+        self.indices.meta_predicates.write(&mut w);
+        self.indices.modules.write(&mut w);
+        self.indices.op_dir.write(&mut w);
+
+        // only the values set by bb_put/2 are written, since those
+        // set by bb_b_put/2 are undone on backtracking.
+        let global_variables: Vec<_> = self
+            .indices
+            .global_variables
+            .iter()
+            .filter(|(_, (ball, _))| !ball.stub.is_empty())
+            .collect();
+
+        global_variables.len().write(&mut w);
+
+        for (name, (ball, _)) in global_variables {
+            name.write(&mut w);
+            ball.write(&mut w);
+        }
+
+        let foreign_libraries = &self.indices.foreign_libraries;
+
+        foreign_libraries
+            .paths()
+            .cloned()
+            .collect::<Vec<_>>()
+            .write(&mut w);
+
+        foreign_libraries.definitions().count().write(&mut w);
+
+        for (name, args, ret) in foreign_libraries.definitions() {
+            name.write(&mut w);
+            args.to_vec().write(&mut w);
+            ret.write(&mut w);
+        }
+
+        self.machine_st.flags.double_quotes.write(&mut w);
+        w.write_byte(self.occurs_check());
+        self.machine_st.global_clock.write(&mut w);
+        self.machine_st.attr_var_init.verify_attrs_loc.write(&mut w);
+
+        if w.complete {
+            Some(w.bytes)
+        } else {
+            None
+        }
+    }
+
+    // reads an image into the machine, which is left untouched if the
+    // image is malformed, was written by another build or names a
+    // foreign library that can't be loaded.
+    fn read_image(&mut self, bytes: &[u8]) -> Option<()> {
+        let bytes = bytes.strip_prefix(IMAGE_MAGIC)?;
+        let mut r = ImageReader::new(bytes, self.machine_st.atom_tbl.clone());
+
+        if String::read(&mut r)? != IMAGE_KEY {
+            return None;
+        }
+
+        let code = Code::read(&mut r)?;
+        let predicate_extents = PredicateExtents(BTreeMap::read(&mut r)?);
+        let clause_locations = ClauseLocations(BTreeMap::read(&mut r)?);
+
+        let code_dir = CodeDir::read(&mut r)?;
+        let extensible_predicates = ExtensiblePredicates::read(&mut r)?;
+        let local_extensible_predicates = LocalExtensiblePredicates::read(&mut r)?;
+        let meta_predicates = MetaPredicateDir::read(&mut r)?;
+        let modules = ModuleDir::read(&mut r)?;
+        let op_dir = OpDir::read(&mut r)?;
+
+        let mut global_variables = GlobalVarDir::new();
+
+        for _ in 0..usize::read(&mut r)? {
+            let name = ClauseName::read(&mut r)?;
+            global_variables.insert(name, (Ball::read(&mut r)?, None));
+        }
+
+        let library_paths = Vec::<String>::read(&mut r)?;
+        let mut definitions = vec![];
+
+        for _ in 0..usize::read(&mut r)? {
+            let name = ClauseName::read(&mut r)?;
+            let args = Vec::<FFIType>::read(&mut r)?;
+
+            definitions.push((name, args, FFIType::read(&mut r)?));
+        }
+
+        let double_quotes = DoubleQuotes::read(&mut r)?;
+        let occurs_check = r.read_byte()?;
+        let global_clock = usize::read(&mut r)?;
+        let verify_attrs_loc = usize::read(&mut r)?;
+
+        if !r.bytes.is_empty() {
+            return None;
+        }
+
+        let mut foreign_libraries = ForeignLibraries::new();
+
+        for path in library_paths {
+            if !foreign_libraries.open(path) {
+                return None;
+            }
+        }
+
+        for (name, args, ret) in definitions {
+            if !foreign_libraries.define(name, args, ret) {
+                return None;
+            }
+        }
+
+        self.code_repo.code = code;
+        self.code_repo.predicate_extents = predicate_extents;
+        self.code_repo.clause_locations = clause_locations;
+
+        self.indices.code_dir = code_dir;
+        self.indices.extensible_predicates = extensible_predicates;
+        self.indices.local_extensible_predicates = local_extensible_predicates;
+        self.indices.meta_predicates = meta_predicates;
+        self.indices.modules = modules;
+        self.indices.op_dir = op_dir;
+        self.indices.global_variables = global_variables;
+        self.indices.foreign_libraries = foreign_libraries;
+
+        self.machine_st.flags.double_quotes = double_quotes;
+        self.set_occurs_check(occurs_check);
+        self.machine_st.global_clock = global_clock;
+        self.machine_st.attr_var_init.verify_attrs_loc = verify_attrs_loc;
+
+        Some(())
+    }
+
+    pub(super) fn load_image(&mut self) -> bool {
+        match image_path().and_then(|path| fs::read(path).ok()) {
+            Some(bytes) => self.read_image(&bytes).is_some(),
+            None => false,
+        }
+    }
+
+    // the images of other builds, and the temporary files left by
+    // interrupted writes of this build's image, are removed along with
+    // writing it. Failing to save it isn't an error.
+    pub(super) fn save_image(&self) {
+        let (path, bytes) = match (image_path(), self.write_image()) {
+            (Some(path), Some(bytes)) => (path, bytes),
+            _ => return,
+        };
+
+        let dir = match path.parent() {
+            Some(dir) if fs::create_dir_all(dir).is_ok() => dir,
+            _ => return,
+        };
+
+        if write_atomically(&path, &bytes).is_err() {
+            return;
+        }
+
+        let tmp_prefix = format!("boot-{}.", IMAGE_KEY);
+
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.filter_map(Result::ok).map(|e| e.path()) {
+                let is_stale_file = entry
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .map(|name| {
+                        let is_tmp_file = name.starts_with(&tmp_prefix) && name.ends_with(".tmp");
+                        let is_other_image =
+                            name.starts_with("boot-") && name.ends_with(".img") && entry != path;
+
+                        is_tmp_file || is_other_image
+                    })
+                    .unwrap_or(false);
+
+                if is_stale_file {
+                    let _ = fs::remove_file(entry);
+                }
+            }
+        }
+    }
+
+    // reads the saved state at path into the machine.
+    pub(super) fn load_state(&mut self, path: &Path) -> io::Result<()> {
+        let bytes = fs::read(path)?;
+
+        self.read_image(&bytes).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a saved state of this build of scryer-prolog, \
+                 or it names a foreign library that can't be loaded",
+            )
+        })
+    }
+
+    // reads the saved state appended to the running executable into
+    // the machine, returning false if it isn't a stand-alone
+    // executable.
+    pub(super) fn load_embedded_state(&mut self) -> bool {
+        match embedded_state() {
+            Some(bytes) => self.read_image(&bytes).is_some(),
+            None => false,
+        }
+    }
+
+    // '$qsave_program'(File, StandAlone, IOError) saves the state of
+    // the machine to File, as a stand-alone executable if StandAlone
+    // is true, failing if it holds terms that can't be saved. IOError
+    // is [] if File was written, permission_denied if it couldn't be
+    // for lack of permission, and the message of the error otherwise.
+    pub(super) fn qsave_program(&mut self) {
+        let path = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            addr @ Addr::Con(_) | addr @ Addr::Char(_) => {
+                atom_from!(self.machine_st, addr).as_str().to_string()
+            }
+            addr => self.machine_st.heap_pstr_iter(addr).to_string(),
+        };
+
+        let stand_alone = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(2)]))
+        );
+
+        let path = Path::new(&path);
+
+        let saved = match self.write_image() {
+            Some(bytes) if stand_alone.as_str() == "true" => stand_alone_executable(bytes)
+                .and_then(|exe| write_atomically(path, &exe))
+                .and_then(|_| make_executable(path)),
+            Some(bytes) => write_atomically(path, &bytes),
+            None => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        let io_error = match saved {
+            Ok(()) => Addr::EmptyList,
+            Err(err) => {
+                let name = if err.kind() == io::ErrorKind::PermissionDenied {
+                    clause_name!("permission_denied")
+                } else {
+                    clause_name!(err.to_string(), self.machine_st.atom_tbl)
+                };
+
+                self.machine_st
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(name, None))
+            }
+        };
+
+        self.machine_st.unify(io_error, self.machine_st[temp_v!(3)]);
+    }
+}
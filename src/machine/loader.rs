@@ -136,6 +136,12 @@ impl RetractionInfo {
 
 impl<'a> Drop for LoadState<'a> {
     fn drop(&mut self) {
+        if !self.retraction_info.records.is_empty() {
+            // the argument indices may include clauses about to be
+            // removed.
+            self.wam.code_repo.arg_indices.clear();
+        }
+
         while let Some(record) = self.retraction_info.records.pop() {
             match record {
                 RetractionRecord::AddedMetaPredicate(target_module_name, key) => {
@@ -657,13 +663,17 @@ impl CompilationTarget {
 
 pub(crate) struct PredicateQueue {
     pub(super) predicates: Vec<Term>,
+    // the source file, line and column each clause begins at, if it
+    // was read from one.
+    pub(super) locations: Vec<Option<ClauseLocation>>,
     pub(super) compilation_target: CompilationTarget,
 }
 
 impl PredicateQueue {
     #[inline]
-    pub(super) fn push(&mut self, clause: Term) {
+    pub(super) fn push(&mut self, clause: Term, location: Option<ClauseLocation>) {
         self.predicates.push(clause);
+        self.locations.push(location);
     }
 
     #[inline]
@@ -680,6 +690,7 @@ impl PredicateQueue {
     pub(super) fn take(&mut self) -> Self {
         Self {
             predicates: mem::replace(&mut self.predicates, vec![]),
+            locations: mem::take(&mut self.locations),
             compilation_target: self.compilation_target.take(),
         }
     }
@@ -694,6 +705,7 @@ macro_rules! predicate_queue {
     [$($v:expr),*] => (
         PredicateQueue {
             predicates: vec![$($v,)*],
+            locations: vec![],
             compilation_target: CompilationTarget::default(),
         }
     )
@@ -750,7 +762,8 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
                 term => term,
             };
 
-            self.predicates.push(term);
+            let location = self.term_stream.location(self.load_state.wam);
+            self.predicates.push(term, location);
         }
 
         Ok(None)
@@ -1410,6 +1423,7 @@ impl Machine {
                 CompilationTarget::User,
                 false,
                 AppendOrPrepend::Append,
+                None,
             )?;
 
             LiveTermStream::evacuate(loader)
@@ -1442,6 +1456,7 @@ impl Machine {
                 compilation_target,
                 false, // backtracking inferences are counted by call_with_inference_limit.
                 AppendOrPrepend::Append,
+                None,
             )?;
 
             LiveTermStream::evacuate(loader)
@@ -1547,6 +1562,31 @@ impl Machine {
             .bind(self.machine_st[temp_v!(1)].as_var().unwrap(), addr);
     }
 
+    // like push_load_state_payload, but the modules used by the load
+    // state are imported into the module named by A2.
+    pub(crate) fn push_module_load_state_payload(&mut self) {
+        let module_name = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(2)]))
+        );
+
+        let mut payload = Box::new(LoadStatePayload::new(self));
+
+        if module_name.as_str() != "user" {
+            payload.compilation_target = CompilationTarget::Module(module_name);
+        }
+
+        let addr = Addr::LoadStatePayload(
+            self.machine_st
+                .heap
+                .push(HeapCellValue::LoadStatePayload(payload)),
+        );
+
+        self.machine_st
+            .bind(self.machine_st[temp_v!(1)].as_var().unwrap(), addr);
+    }
+
     #[inline]
     pub(crate) fn pop_load_state_payload(&mut self) {
         let load_state_payload = match self
@@ -1602,6 +1642,42 @@ impl Machine {
             .push(LoadContext::new(path.as_str(), stream));
     }
 
+    // like push_load_context, but the terms of the file are compiled
+    // as part of the source and module of the enclosing load context.
+    pub(crate) fn push_include_context(&mut self) {
+        let (source, module) = match self.load_contexts.last() {
+            Some(load_context) => (
+                load_context
+                    .source
+                    .clone()
+                    .unwrap_or_else(|| load_context.path.clone()),
+                load_context.module.clone(),
+            ),
+            None => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        self.push_load_context();
+
+        if let Some(load_context) = self.load_contexts.last_mut() {
+            load_context.source = Some(source);
+            load_context.module = module;
+        }
+    }
+
+    // records the line and column of the stream of the load context,
+    // where the next clause begins.
+    pub(crate) fn set_clause_location(&mut self) {
+        if let Some(load_context) = self.load_contexts.last_mut() {
+            let line = load_context.stream.lines_read() + 1;
+            let column = load_context.stream.line_position();
+
+            load_context.location = Some((line, column));
+        }
+    }
+
     pub(crate) fn restore_load_state_payload(
         &mut self,
         result: Result<LoadStatePayload, SessionError>,
@@ -1662,7 +1738,8 @@ impl Machine {
 
     pub(crate) fn load_context_source(&mut self) {
         if let Some(load_context) = self.load_contexts.last() {
-            let path_str = load_context.path.to_str().unwrap();
+            let path = load_context.source.as_ref().unwrap_or(&load_context.path);
+            let path_str = path.to_str().unwrap();
             let path_atom = clause_name!(path_str.to_string(), self.machine_st.atom_tbl);
 
             let path_addr = Addr::Con(
@@ -1798,6 +1875,7 @@ impl Machine {
                 compilation_target.clone(),
                 false,
                 append_or_prepend,
+                None,
             )?;
 
             // the global clock is incremented after each assertion.
@@ -2133,6 +2211,84 @@ impl Machine {
         }
     }
 
+    // unifies A4 with the clauses of the predicate as a list of
+    // Start-Location pairs, where Start is the start of the code of a
+    // clause and Location is location(File, Line, Column) if the
+    // clause was read from a source file, and none otherwise.
+    pub(crate) fn predicate_clauses(&mut self) {
+        let module_name = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        );
+
+        let key = self
+            .machine_st
+            .read_predicate_key(self.machine_st[temp_v!(2)], self.machine_st[temp_v!(3)]);
+
+        let clause_starts =
+            match self
+                .code_repo
+                .predicate_clauses(&self.indices, &module_name, &key)
+            {
+                Some(clause_starts) => clause_starts,
+                None => {
+                    self.machine_st.fail = true;
+                    return;
+                }
+            };
+
+        let mut clauses = vec![];
+
+        for start in clause_starts {
+            let h = self.machine_st.heap.h();
+
+            self.machine_st
+                .heap
+                .push(HeapCellValue::NamedStr(2, clause_name!("-"), None));
+            self.machine_st
+                .heap
+                .push(HeapCellValue::Addr(Addr::Fixnum(start as isize)));
+
+            match self.code_repo.clause_locations.get(start) {
+                Some((file, line, column)) => {
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Addr(Addr::Str(h + 3)));
+                    self.machine_st.heap.push(HeapCellValue::NamedStr(
+                        3,
+                        clause_name!("location"),
+                        None,
+                    ));
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Atom(file.clone(), None));
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(*line as isize)));
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(*column as isize)));
+                }
+                None => {
+                    self.machine_st
+                        .heap
+                        .push(HeapCellValue::Atom(clause_name!("none"), None));
+                }
+            }
+
+            clauses.push(Addr::Str(h));
+        }
+
+        let list_loc = self
+            .machine_st
+            .heap
+            .to_list(clauses.into_iter().map(HeapCellValue::Addr));
+
+        self.machine_st
+            .unify(Addr::HeapCell(list_loc), self.machine_st[temp_v!(4)]);
+    }
+
     pub(crate) fn multifile_property(&mut self) {
         let module_name = atom_from!(
             self.machine_st,
@@ -150,6 +150,17 @@ impl MachineError {
         )
     }
 
+    #[inline]
+    pub(super) fn resource_error(resource: &'static str) -> Self {
+        let stub = functor!("resource_error", [atom(resource)]);
+
+        MachineError {
+            stub,
+            location: None,
+            from: ErrorProvenance::Received,
+        }
+    }
+
     #[inline]
     pub(super) fn interrupt_error() -> Self {
         let stub = functor!("$interrupt_thrown");
@@ -535,9 +546,11 @@ pub(crate) enum ValidType {
     List,
     Number,
     Pair,
+    Pointer,
     //    PredicateIndicator,
     //    Variable
     TcpListener,
+    Text,
 }
 
 impl ValidType {
@@ -558,15 +571,18 @@ impl ValidType {
             ValidType::List => "list",
             ValidType::Number => "number",
             ValidType::Pair => "pair",
+            ValidType::Pointer => "pointer",
             //            ValidType::PredicateIndicator => "predicate_indicator",
             //            ValidType::Variable => "variable"
             ValidType::TcpListener => "tcp_listener",
+            ValidType::Text => "text",
         }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum DomainErrorType {
+    ForeignType,
     IOMode,
     NotLessThanZero,
     Order,
@@ -578,6 +594,7 @@ pub(crate) enum DomainErrorType {
 impl DomainErrorType {
     pub(crate) fn as_str(self) -> &'static str {
         match self {
+            DomainErrorType::ForeignType => "foreign_type",
             DomainErrorType::IOMode => "io_mode",
             DomainErrorType::NotLessThanZero => "not_less_than_zero",
             DomainErrorType::Order => "order",
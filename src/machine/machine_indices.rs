@@ -6,6 +6,7 @@ use crate::fixtures::*;
 use crate::forms::*;
 use crate::instructions::*;
 use crate::machine::code_repo::CodeRepo;
+use crate::machine::ffi::ForeignLibraries;
 use crate::machine::heap::*;
 use crate::machine::machine_state::*;
 use crate::machine::partial_string::*;
@@ -17,6 +18,7 @@ use crate::rug::{Integer, Rational};
 use ordered_float::OrderedFloat;
 
 use indexmap::IndexMap;
+use libc::c_void;
 
 use std::cell::Cell;
 use std::cmp::Ordering;
@@ -68,6 +70,7 @@ pub(crate) enum Addr {
     LoadStatePayload(usize),
     HeapCell(usize),
     PStrLocation(usize, usize), // location of pstr in heap, offset into string in bytes.
+    Pointer(usize),
     StackCell(usize, usize),
     Str(usize),
     Stream(usize),
@@ -196,6 +199,7 @@ impl Addr {
                 }
                 Addr::CutPoint(_)
                 | Addr::LoadStatePayload(_)
+                | Addr::Pointer(_)
                 | Addr::Stream(_)
                 | Addr::TcpListener(_) => None,
             },
@@ -236,6 +240,7 @@ impl Add<usize> for Addr {
     fn add(self, rhs: usize) -> Self::Output {
         match self {
             Addr::Stream(h) => Addr::Stream(h + rhs),
+            Addr::Pointer(h) => Addr::Pointer(h + rhs),
             Addr::Con(h) => Addr::Con(h + rhs),
             Addr::Lis(a) => Addr::Lis(a + rhs),
             Addr::AttrVar(h) => Addr::AttrVar(h + rhs),
@@ -254,6 +259,7 @@ impl Sub<i64> for Addr {
         if rhs < 0 {
             match self {
                 Addr::Stream(h) => Addr::Stream(h + rhs.abs() as usize),
+                Addr::Pointer(h) => Addr::Pointer(h + rhs.abs() as usize),
                 Addr::Con(h) => Addr::Con(h + rhs.abs() as usize),
                 Addr::Lis(a) => Addr::Lis(a + rhs.abs() as usize),
                 Addr::AttrVar(h) => Addr::AttrVar(h + rhs.abs() as usize),
@@ -274,6 +280,7 @@ impl Sub<usize> for Addr {
     fn sub(self, rhs: usize) -> Self::Output {
         match self {
             Addr::Stream(h) => Addr::Stream(h - rhs),
+            Addr::Pointer(h) => Addr::Pointer(h - rhs),
             Addr::Con(h) => Addr::Con(h - rhs),
             Addr::Lis(a) => Addr::Lis(a - rhs),
             Addr::AttrVar(h) => Addr::AttrVar(h - rhs),
@@ -316,6 +323,7 @@ pub(crate) enum HeapCellValue {
     NamedStr(usize, ClauseName, Option<SharedOpDesc>), // arity, name, precedence/Specifier if it has one.
     Rational(Rc<Rational>),
     PartialString(PartialString, bool), // the partial string, a bool indicating whether it came from a Constant.
+    Pointer(*mut c_void), // a C pointer returned by a foreign function.
     Stream(Stream),
     TcpListener(TcpListener),
 }
@@ -332,6 +340,7 @@ impl HeapCellValue {
             HeapCellValue::LoadStatePayload(_) => Addr::LoadStatePayload(focus),
             HeapCellValue::NamedStr(_, _, _) => Addr::Str(focus),
             HeapCellValue::PartialString(..) => Addr::PStrLocation(focus, 0),
+            HeapCellValue::Pointer(_) => Addr::Pointer(focus),
             HeapCellValue::Stream(_) => Addr::Stream(focus),
             HeapCellValue::TcpListener(_) => Addr::TcpListener(focus),
         }
@@ -354,10 +363,13 @@ impl HeapCellValue {
             &HeapCellValue::PartialString(ref pstr, has_tail) => {
                 HeapCellValue::PartialString(pstr.clone(), has_tail)
             }
+            &HeapCellValue::Pointer(ptr) => HeapCellValue::Pointer(ptr),
             &HeapCellValue::Stream(ref stream) => HeapCellValue::Stream(stream.clone()),
-            &HeapCellValue::TcpListener(_) => {
-                HeapCellValue::Atom(clause_name!("$tcp_listener"), None)
-            }
+            // the copy shares the socket of the original.
+            HeapCellValue::TcpListener(tcp_listener) => match tcp_listener.try_clone() {
+                Ok(tcp_listener) => HeapCellValue::TcpListener(tcp_listener),
+                Err(_) => HeapCellValue::Atom(clause_name!("$tcp_listener"), None),
+            },
         }
     }
 }
@@ -438,14 +450,18 @@ pub(crate) enum REPLCodePtr {
     LoadContextStream,
     PopLoadContext,
     PopLoadStatePayload,
+    PushIncludeContext,
     PushLoadContext,
     PushLoadStatePayload,
+    PushModuleLoadStatePayload,
+    SetClauseLocation,
     UseModule,
     BuiltInProperty,
     MetaPredicateProperty,
     MultifileProperty,
     DiscontiguousProperty,
     DynamicProperty,
+    PredicateClauses,
     AbolishClause,
     Asserta,
     Assertz,
@@ -454,6 +470,22 @@ pub(crate) enum REPLCodePtr {
     FlushTermQueue,
     RemoveModuleExports,
     AddNonCountedBacktracking,
+    MutexLock,
+    MutexUnlock,
+    ThreadCreate,
+    ThreadFinish,
+    ThreadJoin,
+    ThreadPeekMessage,
+    ThreadPreempt,
+    ThreadRemoveMessage,
+    ThreadSelf,
+    ThreadSendMessage,
+    ThreadWaitJoin,
+    ThreadWaitMessage,
+    ThreadWaitReadable,
+    YieldAnswer,
+    ForeignCall,
+    QSaveProgram,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -679,6 +711,7 @@ pub(crate) type LocalExtensiblePredicates =
 pub(crate) struct IndexStore {
     pub(super) code_dir: CodeDir,
     pub(super) extensible_predicates: ExtensiblePredicates,
+    pub(super) foreign_libraries: ForeignLibraries,
     pub(super) local_extensible_predicates: LocalExtensiblePredicates,
     pub(super) global_variables: GlobalVarDir,
     pub(super) meta_predicates: MetaPredicateDir,
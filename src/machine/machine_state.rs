@@ -6,6 +6,7 @@ use crate::clause_types::*;
 use crate::forms::*;
 use crate::heap_print::*;
 use crate::machine::attributed_variables::*;
+use crate::machine::code_repo::*;
 use crate::machine::copier::*;
 use crate::machine::heap::*;
 use crate::machine::machine_errors::*;
@@ -15,6 +16,8 @@ use crate::machine::stack::*;
 use crate::machine::streams::*;
 use crate::rug::Integer;
 
+use cpu_time::ProcessTime;
+
 use downcast::{
     downcast, downcast_methods, downcast_methods_core, downcast_methods_std, impl_downcast, Any,
 };
@@ -27,6 +30,7 @@ use std::fmt;
 use std::mem;
 use std::ops::{Index, IndexMut};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub(crate) struct Ball {
@@ -304,6 +308,18 @@ pub(crate) struct MachineState {
     pub(crate) dynamic_mode: FirstOrNext,
     pub(crate) unify_fn: fn(&mut MachineState, Addr, Addr),
     pub(crate) bind_fn: fn(&mut MachineState, Ref, Addr),
+    pub(super) gc_threshold: usize,
+    pub(super) heap_limit: Option<usize>, // in bytes, or None if the heap is unbounded.
+    pub(super) stack_limit: Option<usize>, // of the stack and trail together, in bytes.
+    pub(super) memory_limit_raised: bool, // whether a limit was exceeded and memory is yet to fall below it.
+    pub(super) autoload: bool, // whether calls to undefined library predicates load their libraries.
+    pub(super) time_slice: usize, // calls left until preemption, or 0 if there's no other thread.
+    pub(super) arg_index_request: Option<(usize, usize)>, // a SwitchOnTerm location and an argument to index.
+    pub(super) coverage: Option<Vec<usize>>, // the executions of each code location, while coverage is recorded.
+    pub(super) backtrace: Vec<usize>, // the code and continuations of the active frames where the last error term was thrown.
+    pub(super) start_time: Instant,   // when the machine was created, for walltime.
+    pub(super) last_runtime: Duration, // the CPU time when runtime was last read by statistics/2.
+    pub(super) last_walltime: Duration, // likewise for walltime.
 }
 
 impl fmt::Debug for MachineState {
@@ -357,6 +373,16 @@ impl fmt::Debug for MachineState {
                     &"MachineState::bind_with_occurs_check_with_error_wrapper"
                 },
             )
+            .field("gc_threshold", &self.gc_threshold)
+            .field("heap_limit", &self.heap_limit)
+            .field("stack_limit", &self.stack_limit)
+            .field("autoload", &self.autoload)
+            .field("time_slice", &self.time_slice)
+            .field("coverage", &self.coverage.is_some())
+            .field("backtrace", &self.backtrace)
+            .field("start_time", &self.start_time)
+            .field("last_runtime", &self.last_runtime)
+            .field("last_walltime", &self.last_walltime)
             .finish()
     }
 }
@@ -686,37 +712,40 @@ impl MachineState {
         _last_call: bool,
         stream_aliases: &StreamAliasDir,
     ) -> CallResult {
-        if module_name.as_str() == "user" {
-            return call_policy.call_clause_type(
-                self,
-                key,
-                &indices.code_dir,
-                &indices.op_dir,
-                stream_aliases,
-            );
+        let (code_dir, op_dir) = if module_name.as_str() == "user" {
+            (&indices.code_dir, &indices.op_dir)
         } else if let Some(module) = indices.modules.get(&module_name) {
-            return call_policy.call_clause_type(
-                self,
-                key,
-                &module.code_dir,
-                &module.op_dir,
-                stream_aliases,
-            );
-        }
+            (&module.code_dir, &module.op_dir)
+        } else {
+            let (name, arity) = key;
 
-        let (name, arity) = key;
+            let h = self.heap.h();
+            let stub = MachineError::functor_stub(name.clone(), arity);
+            let err = MachineError::module_resolution_error(h, module_name, name, arity);
 
-        let h = self.heap.h();
-        let stub = MachineError::functor_stub(name.clone(), arity);
-        let err = MachineError::module_resolution_error(h, module_name, name, arity);
+            return Err(self.error_form(err, stub));
+        };
+
+        // as in handle_call_clause, undefined library predicates are
+        // autoloaded.
+        if code_dir.get(&key).map_or(true, CodeIndex::is_undefined) {
+            if let Some(handler) = self.autoload_handler(indices, module_name, &key.0, key.1) {
+                return call_policy.context_call(self, clause_name!("$autoload"), 3, &handler);
+            }
+        }
 
-        return Err(self.error_form(err, stub));
+        call_policy.call_clause_type(self, key, code_dir, op_dir, stream_aliases)
     }
 }
 
 pub(crate) type CallResult = Result<(), Vec<HeapCellValue>>;
 
 pub(crate) trait CallPolicy: Any + fmt::Debug {
+    // the policy wrapped by this one, if any.
+    fn inner_policy(&mut self) -> Option<&mut Box<dyn CallPolicy>> {
+        None
+    }
+
     fn retry_me_else(
         &mut self,
         machine_st: &mut MachineState,
@@ -1280,6 +1309,10 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
 }
 
 impl CallPolicy for CWILCallPolicy {
+    fn inner_policy(&mut self) -> Option<&mut Box<dyn CallPolicy>> {
+        Some(&mut self.prev_policy)
+    }
+
     fn context_call(
         &mut self,
         machine_st: &mut MachineState,
@@ -1363,8 +1396,334 @@ impl CallPolicy for CWILCallPolicy {
     }
 }
 
+impl CallPolicy for TimeLimitCallPolicy {
+    fn inner_policy(&mut self) -> Option<&mut Box<dyn CallPolicy>> {
+        Some(&mut self.prev_policy)
+    }
+
+    fn context_call(
+        &mut self,
+        machine_st: &mut MachineState,
+        name: ClauseName,
+        arity: usize,
+        idx: &CodeIndex,
+    ) -> CallResult {
+        self.prev_policy
+            .context_call(machine_st, name, arity, idx)?;
+        self.check_deadlines(machine_st)
+    }
+
+    fn retry_me_else(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy
+            .retry_me_else(machine_st, offset, global_variables)?;
+        self.check_deadlines(machine_st)
+    }
+
+    fn retry(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy
+            .retry(machine_st, offset, global_variables)?;
+        self.check_deadlines(machine_st)
+    }
+
+    fn trust_me(
+        &mut self,
+        machine_st: &mut MachineState,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.trust_me(machine_st, global_variables)?;
+        self.check_deadlines(machine_st)
+    }
+
+    fn trust(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy
+            .trust(machine_st, offset, global_variables)?;
+        self.check_deadlines(machine_st)
+    }
+
+    fn call_builtin(
+        &mut self,
+        machine_st: &mut MachineState,
+        ct: &BuiltInClauseType,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        self.prev_policy
+            .call_builtin(machine_st, ct, code_dir, op_dir, stream_aliases)?;
+
+        self.check_deadlines(machine_st)
+    }
+
+    fn call_n(
+        &mut self,
+        machine_st: &mut MachineState,
+        arity: usize,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        self.prev_policy
+            .call_n(machine_st, arity, code_dir, op_dir, stream_aliases)?;
+
+        self.check_deadlines(machine_st)
+    }
+}
+
+impl CallPolicy for DebugCallPolicy {
+    fn inner_policy(&mut self) -> Option<&mut Box<dyn CallPolicy>> {
+        Some(&mut self.prev_policy)
+    }
+
+    fn context_call(
+        &mut self,
+        machine_st: &mut MachineState,
+        name: ClauseName,
+        arity: usize,
+        idx: &CodeIndex,
+    ) -> CallResult {
+        let p = match idx.get() {
+            IndexPtr::Index(p) | IndexPtr::DynamicIndex(p) => p,
+            _ => return self.prev_policy.context_call(machine_st, name, arity, idx),
+        };
+
+        if !self.traces(machine_st, &name, arity, p) {
+            return self.prev_policy.context_call(machine_st, name, arity, idx);
+        }
+
+        let handler = (clause_name!("$trace_goal"), self.handler.clone());
+
+        call_handler(
+            &mut self.prev_policy,
+            machine_st,
+            &self.extents,
+            &name,
+            arity,
+            idx,
+            handler,
+        )
+    }
+
+    fn retry_me_else(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy
+            .retry_me_else(machine_st, offset, global_variables)
+    }
+
+    fn retry(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.retry(machine_st, offset, global_variables)
+    }
+
+    fn trust_me(
+        &mut self,
+        machine_st: &mut MachineState,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.trust_me(machine_st, global_variables)
+    }
+
+    fn trust(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.trust(machine_st, offset, global_variables)
+    }
+
+    fn call_builtin(
+        &mut self,
+        machine_st: &mut MachineState,
+        ct: &BuiltInClauseType,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        self.prev_policy
+            .call_builtin(machine_st, ct, code_dir, op_dir, stream_aliases)
+    }
+
+    // the predicates called by call/N are resolved here rather than
+    // by prev_policy, so that they pass through context_call.
+    fn call_n(
+        &mut self,
+        machine_st: &mut MachineState,
+        arity: usize,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        if let Some(key) = machine_st.setup_call_n(arity) {
+            self.call_clause_type(machine_st, key, code_dir, op_dir, stream_aliases)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CallPolicy for ProfileCallPolicy {
+    fn inner_policy(&mut self) -> Option<&mut Box<dyn CallPolicy>> {
+        Some(&mut self.prev_policy)
+    }
+
+    fn context_call(
+        &mut self,
+        machine_st: &mut MachineState,
+        name: ClauseName,
+        arity: usize,
+        idx: &CodeIndex,
+    ) -> CallResult {
+        let p = match idx.get() {
+            IndexPtr::Index(p) | IndexPtr::DynamicIndex(p) => p,
+            _ => return self.prev_policy.context_call(machine_st, name, arity, idx),
+        };
+
+        if is_library_call(&self.extents, machine_st, p) {
+            return self.prev_policy.context_call(machine_st, name, arity, idx);
+        }
+
+        if machine_st.last_call && self.is_tail_call(machine_st) {
+            let module_name = match self.extents.predicate_at(p) {
+                Some((_, module_name)) => module_name.clone(),
+                None => clause_name!("user"),
+            };
+
+            let now = ProcessTime::now().as_duration();
+            self.tail_call((module_name, (name.clone(), arity)), now);
+
+            return self.prev_policy.context_call(machine_st, name, arity, idx);
+        }
+
+        let handler = (clause_name!("$profile_goal"), self.handler.clone());
+
+        call_handler(
+            &mut self.prev_policy,
+            machine_st,
+            &self.extents,
+            &name,
+            arity,
+            idx,
+            handler,
+        )
+    }
+
+    fn retry_me_else(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy
+            .retry_me_else(machine_st, offset, global_variables)
+    }
+
+    fn retry(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.retry(machine_st, offset, global_variables)
+    }
+
+    fn trust_me(
+        &mut self,
+        machine_st: &mut MachineState,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.trust_me(machine_st, global_variables)
+    }
+
+    fn trust(
+        &mut self,
+        machine_st: &mut MachineState,
+        offset: usize,
+        global_variables: &mut GlobalVarDir,
+    ) -> CallResult {
+        self.prev_policy.trust(machine_st, offset, global_variables)
+    }
+
+    fn call_builtin(
+        &mut self,
+        machine_st: &mut MachineState,
+        ct: &BuiltInClauseType,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        self.prev_policy
+            .call_builtin(machine_st, ct, code_dir, op_dir, stream_aliases)
+    }
+
+    // the predicates called by call/N are resolved here rather than
+    // by prev_policy, so that they pass through context_call.
+    fn call_n(
+        &mut self,
+        machine_st: &mut MachineState,
+        arity: usize,
+        code_dir: &CodeDir,
+        op_dir: &OpDir,
+        stream_aliases: &StreamAliasDir,
+    ) -> CallResult {
+        if let Some(key) = machine_st.setup_call_n(arity) {
+            self.call_clause_type(machine_st, key, code_dir, op_dir, stream_aliases)?;
+        }
+
+        Ok(())
+    }
+}
+
 downcast!(dyn CallPolicy);
 
+// the policy of type T in the chain of policies wrapped by policy,
+// starting with policy itself.
+pub(crate) fn find_call_policy<T: CallPolicy>(policy: &mut Box<dyn CallPolicy>) -> Option<&mut T> {
+    if policy.is::<T>() {
+        policy.downcast_mut::<T>().ok()
+    } else {
+        policy.inner_policy().and_then(find_call_policy::<T>)
+    }
+}
+
+// replaces the policy of type T in the chain of policies wrapped by
+// policy with the policy it wraps.
+pub(crate) fn remove_call_policy<T: CallPolicy>(policy: &mut Box<dyn CallPolicy>) {
+    if policy.is::<T>() {
+        let inner = policy
+            .inner_policy()
+            .map(|inner| mem::replace(inner, Box::new(DefaultCallPolicy {})));
+
+        if let Some(inner) = inner {
+            *policy = inner;
+        }
+    } else if let Some(inner) = policy.inner_policy() {
+        remove_call_policy::<T>(inner);
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct DefaultCallPolicy {}
 
@@ -1406,11 +1765,10 @@ impl CWILCallPolicy {
                     "inference_limit_exceeded",
                     [addr(Addr::Usize(bp))]
                 ));
-            } else {
-                self.count += 1;
             }
         }
 
+        self.count += 1;
         Ok(())
     }
 
@@ -1429,20 +1787,458 @@ impl CWILCallPolicy {
         if let Some((_, bp)) = self.limits.last().cloned() {
             if bp == b {
                 self.limits.pop();
+                self.inference_limit_exceeded = false;
             }
         }
 
         &self.count
     }
 
+    #[inline]
+    pub(crate) fn count(&self) -> &Integer {
+        &self.count
+    }
+}
+
+#[derive(Debug)]
+struct TimeLimit {
+    b: usize,
+    deadline: Option<Instant>, // None if too far off to represent.
+    expired: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct TimeLimitCallPolicy {
+    pub(crate) prev_policy: Box<dyn CallPolicy>,
+    limits: Vec<TimeLimit>, // outermost first.
+}
+
+impl TimeLimitCallPolicy {
+    pub(crate) fn new_in_place(policy: &mut Box<dyn CallPolicy>) {
+        let mut prev_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
+        mem::swap(&mut prev_policy, policy);
+
+        let new_policy = TimeLimitCallPolicy {
+            prev_policy,
+            limits: vec![],
+        };
+
+        *policy = Box::new(new_policy);
+    }
+
+    // throws '$time_limit_exceeded'(B) for the outermost limit whose
+    // deadline has passed. the limits nested in it expire with it, so
+    // that they're not thrown again while the exception unwinds them.
+    fn check_deadlines(&mut self, machine_st: &MachineState) -> CallResult {
+        if machine_st.ball.stub.h() > 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+
+        let i = match self
+            .limits
+            .iter()
+            .position(|limit| !limit.expired && matches!(limit.deadline, Some(t) if t <= now))
+        {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+
+        for limit in &mut self.limits[i..] {
+            limit.expired = true;
+        }
+
+        Err(functor!(
+            "$time_limit_exceeded",
+            [addr(Addr::Usize(self.limits[i].b))]
+        ))
+    }
+
+    pub(crate) fn add_limit(&mut self, duration: Duration, b: usize) {
+        self.limits.push(TimeLimit {
+            b,
+            deadline: Instant::now().checked_add(duration),
+            expired: false,
+        });
+    }
+
+    pub(crate) fn remove_limit(&mut self, b: usize) {
+        self.limits.retain(|limit| limit.b != b);
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.limits.is_empty()
     }
+}
 
-    pub(crate) fn into_inner(&mut self) -> Box<dyn CallPolicy> {
-        let mut new_inner: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
-        mem::swap(&mut self.prev_policy, &mut new_inner);
-        new_inner
+// true if the module is that of a library, the loader or the
+// toplevel, whose predicates are traced only as called from other
+// modules.
+fn is_system_module(module_name: &ClauseName) -> bool {
+    use crate::LIBRARIES;
+    use ref_thread_local::RefThreadLocal;
+
+    let module_name = module_name.as_str();
+
+    module_name.starts_with('$')
+        || module_name == "loader"
+        || LIBRARIES
+            .borrow()
+            .keys()
+            .any(|library| library.rsplit('/').next() == Some(module_name))
+}
+
+// shows the ports of the predicates traced by the debugger of
+// library(debug), whose '$trace_goal'/2 is called in their place.
+// outside of spy points, only creeping traces predicates.
+#[derive(Debug)]
+pub(crate) struct DebugCallPolicy {
+    pub(crate) prev_policy: Box<dyn CallPolicy>,
+    handler: CodeIndex,
+    pub(crate) creep: bool,
+    pub(crate) spy_points: Vec<(Option<ClauseName>, PredicateKey)>,
+    // the predicate extents of the code repository as of its length
+    // code_len.
+    extents: PredicateExtents,
+    code_len: usize,
+}
+
+impl DebugCallPolicy {
+    pub(crate) fn new_in_place(policy: &mut Box<dyn CallPolicy>, handler: CodeIndex) {
+        let mut prev_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
+        mem::swap(&mut prev_policy, policy);
+
+        let new_policy = DebugCallPolicy {
+            prev_policy,
+            handler,
+            creep: false,
+            spy_points: vec![],
+            extents: PredicateExtents::default(),
+            code_len: 0,
+        };
+
+        *policy = Box::new(new_policy);
+    }
+
+    pub(crate) fn update_extents(&mut self, code_repo: &CodeRepo) {
+        if self.code_len != code_repo.code.len() {
+            self.extents = code_repo.predicate_extents.clone();
+            self.code_len = code_repo.code.len();
+        }
+    }
+
+    fn is_spy_point(
+        &self,
+        module_name: Option<&ClauseName>,
+        name: &ClauseName,
+        arity: usize,
+    ) -> bool {
+        self.spy_points.iter().any(|(spy_module_name, key)| {
+            key.0 == *name
+                && key.1 == arity
+                && match spy_module_name {
+                    Some(spy_module_name) => module_name == Some(spy_module_name),
+                    None => true,
+                }
+        })
+    }
+
+    // true if the call of name/arity at p is traced. calls from one
+    // library predicate to another aren't.
+    fn traces(&self, machine_st: &MachineState, name: &ClauseName, arity: usize, p: usize) -> bool {
+        let callee_module_name = self
+            .extents
+            .predicate_at(p)
+            .map(|(_, module_name)| module_name);
+
+        if self.is_spy_point(callee_module_name, name, arity) {
+            return true;
+        }
+
+        if !self.creep {
+            return false;
+        }
+
+        !is_library_call(&self.extents, machine_st, p)
+    }
+}
+
+// true if the predicate at p is called by a predicate of a system
+// module, and is itself of one.
+fn is_library_call(extents: &PredicateExtents, machine_st: &MachineState, p: usize) -> bool {
+    let callee_module_name = extents.predicate_at(p).map(|(_, module_name)| module_name);
+
+    let caller_module_name = match machine_st.p.local() {
+        LocalCodePtr::DirEntry(p) | LocalCodePtr::IndexingBuf(p, ..) => {
+            extents.predicate_at(p).map(|(_, module_name)| module_name)
+        }
+        LocalCodePtr::Halt => None,
+    };
+
+    matches!(callee_module_name, Some(name) if is_system_module(name))
+        && matches!(caller_module_name, Some(name) if is_system_module(name))
+}
+
+// calls the handler, a predicate by its name and index, in place of
+// the predicate name/arity indexed by idx, with M:Goal in the first
+// register and static(P) or dynamic(P) in the second, P being the
+// code of the predicate, as '$debug_call'/2 expects them.
+fn call_handler(
+    prev_policy: &mut Box<dyn CallPolicy>,
+    machine_st: &mut MachineState,
+    extents: &PredicateExtents,
+    name: &ClauseName,
+    arity: usize,
+    idx: &CodeIndex,
+    handler: (ClauseName, CodeIndex),
+) -> CallResult {
+    let (code_type, p) = match idx.get() {
+        IndexPtr::Index(p) => ("static", p),
+        IndexPtr::DynamicIndex(p) => ("dynamic", p),
+        _ => unreachable!(),
+    };
+
+    let module_name = match extents.predicate_at(p) {
+        Some((_, module_name)) => module_name.clone(),
+        None => clause_name!("user"),
+    };
+
+    let goal = machine_st.goal_from_registers(name, arity);
+    let h = machine_st.heap.h();
+
+    machine_st
+        .heap
+        .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+    machine_st.heap.push(HeapCellValue::Atom(module_name, None));
+    machine_st.heap.push(HeapCellValue::Addr(goal));
+    machine_st
+        .heap
+        .push(HeapCellValue::NamedStr(1, clause_name!(code_type), None));
+    machine_st.heap.push(HeapCellValue::Addr(Addr::Usize(p)));
+
+    machine_st.registers[1] = Addr::Str(h);
+    machine_st.registers[2] = Addr::Str(h + 3);
+
+    let (handler_name, handler) = handler;
+
+    prev_policy.context_call(machine_st, handler_name, 2, &handler)
+}
+
+// the ports of a predicate recorded by the profiler, with the CPU
+// time spent in it. self_time excludes the time of the profiled
+// predicates it calls, and cumulative_time counts recursive calls
+// once.
+#[derive(Debug, Default)]
+pub(crate) struct ProfileEntry {
+    pub(crate) calls: usize,
+    pub(crate) redos: usize,
+    pub(crate) exits: usize,
+    pub(crate) fails: usize,
+    pub(crate) self_time: Duration,
+    pub(crate) cumulative_time: Duration,
+    // the number of frames of the predicate on the frame stack.
+    active: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ProfilePort {
+    Call,
+    Redo,
+    Exit,
+    Fail,
+    Exception,
+}
+
+#[derive(Debug)]
+struct ProfileFrame {
+    entry: usize,
+    start: Duration,
+    // the newest choicepoint before the body of the box, once the
+    // body is entered by its call port.
+    body_b: Option<usize>,
+    // the predicate running in the box, which its last calls replace,
+    // and when it started.
+    current: usize,
+    current_start: Duration,
+    child_time: Duration,
+    // the predicates of the last calls made in the box, with their
+    // calls and the time of the first of them.
+    last_calls: IndexMap<usize, (usize, Duration)>,
+}
+
+// records the ports of the predicates profiled by library(profile),
+// whose '$profile_goal'/2 is called in their place. calls from one
+// library predicate to another aren't profiled. last calls made in a
+// box without choicepoints aren't wrapped, so that they run in
+// constant space, and leave the box with it.
+#[derive(Debug)]
+pub(crate) struct ProfileCallPolicy {
+    pub(crate) prev_policy: Box<dyn CallPolicy>,
+    handler: CodeIndex,
+    pub(crate) entries: IndexMap<(ClauseName, PredicateKey), ProfileEntry>,
+    // the boxes entered by a call or redo port and not yet left.
+    frames: Vec<ProfileFrame>,
+    extents: PredicateExtents,
+    code_len: usize,
+}
+
+impl ProfileCallPolicy {
+    pub(crate) fn new_in_place(policy: &mut Box<dyn CallPolicy>, handler: CodeIndex) {
+        let mut prev_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
+        mem::swap(&mut prev_policy, policy);
+
+        let new_policy = ProfileCallPolicy {
+            prev_policy,
+            handler,
+            entries: IndexMap::new(),
+            frames: vec![],
+            extents: PredicateExtents::default(),
+            code_len: 0,
+        };
+
+        *policy = Box::new(new_policy);
+    }
+
+    pub(crate) fn update_extents(&mut self, code_repo: &CodeRepo) {
+        if self.code_len != code_repo.code.len() {
+            self.extents = code_repo.predicate_extents.clone();
+            self.code_len = code_repo.code.len();
+        }
+    }
+
+    // enters the box of the predicate by its call or redo port.
+    pub(crate) fn enter(
+        &mut self,
+        key: (ClauseName, PredicateKey),
+        port: ProfilePort,
+        now: Duration,
+    ) {
+        let entry = self.entries.entry(key);
+        let index = entry.index();
+        let entry = entry.or_default();
+
+        match port {
+            ProfilePort::Redo => entry.redos += 1,
+            _ => entry.calls += 1,
+        }
+
+        entry.active += 1;
+
+        self.frames.push(ProfileFrame {
+            entry: index,
+            start: now,
+            body_b: None,
+            current: index,
+            current_start: now,
+            child_time: Duration::new(0, 0),
+            last_calls: IndexMap::new(),
+        });
+    }
+
+    pub(crate) fn enter_body(&mut self, b: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.body_b = Some(b);
+        }
+    }
+
+    // whether the call at machine_st.p is from the predicate running
+    // in the innermost box, which has left no choicepoints in it. its
+    // exit or failure is then that of the box.
+    fn is_tail_call(&self, machine_st: &MachineState) -> bool {
+        let frame = match self.frames.last() {
+            Some(frame) => frame,
+            None => return false,
+        };
+
+        if !matches!(frame.body_b, Some(b) if machine_st.b <= b) {
+            return false;
+        }
+
+        let caller = match machine_st.p.local() {
+            LocalCodePtr::DirEntry(p) | LocalCodePtr::IndexingBuf(p, ..) => {
+                self.extents.predicate_at(p)
+            }
+            LocalCodePtr::Halt => None,
+        };
+
+        match (caller, self.entries.get_index(frame.current)) {
+            (Some((key, module_name)), Some(((current_module_name, current_key), _))) => {
+                key == current_key && module_name == current_module_name
+            }
+            _ => false,
+        }
+    }
+
+    // replaces the predicate running in the innermost box by the
+    // predicate of its last call.
+    fn tail_call(&mut self, key: (ClauseName, PredicateKey), now: Duration) {
+        let entry = self.entries.entry(key);
+        let index = entry.index();
+        let entry = entry.or_default();
+
+        entry.calls += 1;
+        entry.active += 1;
+
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let elapsed = now.checked_sub(frame.current_start).unwrap_or_default();
+
+        if let Some((_, entry)) = self.entries.get_index_mut(frame.current) {
+            entry.self_time += elapsed.checked_sub(frame.child_time).unwrap_or_default();
+        }
+
+        frame.current = index;
+        frame.current_start = now;
+        frame.child_time = Duration::new(0, 0);
+        frame.last_calls.entry(index).or_insert((0, now)).0 += 1;
+    }
+
+    // leaves the innermost box by its exit, fail or exception port.
+    pub(crate) fn leave(&mut self, port: ProfilePort, now: Duration) {
+        let frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let elapsed = now.checked_sub(frame.start).unwrap_or_default();
+        let current_elapsed = now.checked_sub(frame.current_start).unwrap_or_default();
+
+        if let Some((_, entry)) = self.entries.get_index_mut(frame.current) {
+            entry.self_time += current_elapsed
+                .checked_sub(frame.child_time)
+                .unwrap_or_default();
+        }
+
+        // the box itself is left last, so that a predicate that made
+        // last calls of itself counts its time from the box's start.
+        let boxes = frame
+            .last_calls
+            .into_iter()
+            .chain(std::iter::once((frame.entry, (1, frame.start))));
+
+        for (index, (calls, start)) in boxes {
+            if let Some((_, entry)) = self.entries.get_index_mut(index) {
+                match port {
+                    ProfilePort::Exit => entry.exits += calls,
+                    ProfilePort::Fail => entry.fails += calls,
+                    _ => {}
+                }
+
+                entry.active -= calls;
+
+                if entry.active == 0 {
+                    entry.cumulative_time += now.checked_sub(start).unwrap_or_default();
+                }
+            }
+        }
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.child_time += elapsed;
+        }
     }
 }
 
@@ -1515,6 +2311,10 @@ impl SCCCutPolicy {
         self.cont_pts.pop()
     }
 
+    pub(crate) fn cont_pt_addrs_mut(&mut self) -> impl Iterator<Item = &mut Addr> {
+        self.cont_pts.iter_mut().map(|(addr, ..)| addr)
+    }
+
     fn run_cleaners(&self, machine_st: &mut MachineState) -> bool {
         if let Some(&(_, b_cutoff, prev_block)) = self.cont_pts.last() {
             if machine_st.b < b_cutoff {
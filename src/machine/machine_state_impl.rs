@@ -10,6 +10,7 @@ use crate::instructions::*;
 use crate::machine::attributed_variables::*;
 use crate::machine::code_repo::CodeRepo;
 use crate::machine::copier::*;
+use crate::machine::gc::*;
 use crate::machine::heap::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
@@ -17,15 +18,22 @@ use crate::machine::machine_state::*;
 use crate::machine::partial_string::*;
 use crate::machine::stack::*;
 use crate::machine::streams::*;
-use crate::machine::INTERRUPT;
+use crate::machine::{AUTOLOAD, INTERRUPT};
 use crate::rug::Integer;
 use ordered_float::*;
+use ref_thread_local::RefThreadLocal;
 
 use indexmap::{IndexMap, IndexSet};
 
 use std::cmp::Ordering;
 use std::convert::TryFrom;
+use std::mem;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// the most frames listed by the backtraces of the interrupt menu and
+// of error terms.
+const MAX_BACKTRACE_FRAMES: usize = 64;
 
 impl MachineState {
     pub(crate) fn new() -> Self {
@@ -58,6 +66,18 @@ impl MachineState {
             dynamic_mode: FirstOrNext::First,
             unify_fn: MachineState::unify,
             bind_fn: MachineState::bind,
+            gc_threshold: INITIAL_GC_THRESHOLD,
+            heap_limit: None,
+            stack_limit: None,
+            memory_limit_raised: false,
+            autoload: true,
+            time_slice: 0,
+            arg_index_request: None,
+            coverage: None,
+            backtrace: vec![],
+            start_time: Instant::now(),
+            last_runtime: Duration::default(),
+            last_walltime: Duration::default(),
         }
     }
 
@@ -408,6 +428,11 @@ impl MachineState {
                             self.fail = true;
                         }
                     }
+                    (Addr::Pointer(p1), Addr::Pointer(p2)) => {
+                        if self.heap.pointer(p1) != self.heap.pointer(p2) {
+                            self.fail = true;
+                        }
+                    }
                     (v, Addr::Con(h)) | (Addr::Con(h), v) => {
                         if let Ok(n1) = Number::try_from(&self.heap[h]) {
                             if let Ok(v) = Number::try_from(&HeapCellValue::Addr(v)) {
@@ -613,6 +638,11 @@ impl MachineState {
                             self.fail = true;
                         }
                     }
+                    (Addr::Pointer(p1), Addr::Pointer(p2)) => {
+                        if self.heap.pointer(p1) != self.heap.pointer(p2) {
+                            self.fail = true;
+                        }
+                    }
                     (v, Addr::Con(h)) | (Addr::Con(h), v) => {
                         if let Ok(n1) = Number::try_from(&self.heap[h]) {
                             if let Ok(v) = Number::try_from(&HeapCellValue::Addr(v)) {
@@ -1363,6 +1393,38 @@ impl MachineState {
         };
     }
 
+    // the location of a built index on another argument bound in the
+    // call, if the subsequence indexed on arg at index_loc has one. Builds
+    // of the indices it lacks are requested of the stepper.
+    fn arg_index_loc(
+        &mut self,
+        code_repo: &CodeRepo,
+        index_loc: usize,
+        arg: usize,
+    ) -> Option<usize> {
+        let arg_indices = code_repo.arg_indices.get(&index_loc);
+
+        for other_arg in 1..=self.num_of_args {
+            if other_arg == arg || self.store(self.deref(self[temp_v!(other_arg)])).is_ref() {
+                continue;
+            }
+
+            match arg_indices.and_then(|arg_indices| arg_indices.get(&other_arg)) {
+                Some(Some(arg_index)) if arg_index.is_current => {
+                    return Some(arg_index.loc);
+                }
+                Some(None) => {}
+                _ => {
+                    if self.arg_index_request.is_none() {
+                        self.arg_index_request = Some((index_loc, other_arg));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
     pub(super) fn execute_indexing_instr(
         &mut self,
         indexing_lines: &Vec<IndexingLine>,
@@ -1370,9 +1432,12 @@ impl MachineState {
     ) {
         fn dynamic_external_of_clause_is_valid(
             machine_st: &mut MachineState,
-            code: &Code,
+            code_repo: &CodeRepo,
             p: usize,
         ) -> bool {
+            let code = &code_repo.code;
+            let p = code_repo.jump_target(p);
+
             match &code[p] {
                 Line::Choice(ChoiceInstruction::DynamicInternalElse(..)) => {
                     machine_st.dynamic_mode = FirstOrNext::First;
@@ -1396,20 +1461,46 @@ impl MachineState {
         }
 
         let mut index = 0;
-        let addr = match &indexing_lines[0] {
+        let (arg, mut addr) = match &indexing_lines[0] {
             &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, ..)) => {
-                self.store(self.deref(self[temp_v!(arg)]))
+                (arg, self.store(self.deref(self[temp_v!(arg)])))
             }
             _ => {
                 unreachable!()
             }
         };
 
+        if addr.is_ref() {
+            let index_loc = self.p.local().abs_loc();
+
+            if let Some(loc) = self.arg_index_loc(code_repo, index_loc, arg) {
+                if let Line::IndexingCode(ref indexing_lines) = &code_repo.code[loc] {
+                    self.p = CodePtr::Local(LocalCodePtr::DirEntry(loc));
+                    return self.execute_indexing_instr(indexing_lines, code_repo);
+                }
+            }
+        }
+
         loop {
             match &indexing_lines[index] {
-                &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, v, c, l, s)) => {
+                &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, v, c, l, s))
+                | &IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(_, v, c, l, s)) => {
+                    if let &IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(
+                        subterm,
+                        ..
+                    )) = &indexing_lines[index]
+                    {
+                        addr = match addr {
+                            Addr::Str(h) => self.store(self.deref(Addr::HeapCell(h + subterm))),
+                            _ => unreachable!(),
+                        };
+                    }
+
                     let offset = match addr {
-                        Addr::LoadStatePayload(_) | Addr::Stream(_) | Addr::TcpListener(_) => {
+                        Addr::LoadStatePayload(_)
+                        | Addr::Pointer(_)
+                        | Addr::Stream(_)
+                        | Addr::TcpListener(_) => {
                             IndexingCodePtr::Fail
                         }
                         Addr::HeapCell(_) | Addr::StackCell(..) | Addr::AttrVar(..) => v,
@@ -1436,7 +1527,7 @@ impl MachineState {
                             // one. Or neither!
                             let p = self.p.local().abs_loc();
 
-                            if !dynamic_external_of_clause_is_valid(self, &code_repo.code, p + o) {
+                            if !dynamic_external_of_clause_is_valid(self, code_repo, p + o) {
                                 self.fail = true;
                             } else {
                                 self.p += o;
@@ -1473,7 +1564,7 @@ impl MachineState {
                             // one. Or neither!
                             let p = self.p.local().abs_loc();
 
-                            if !dynamic_external_of_clause_is_valid(self, &code_repo.code, p + o) {
+                            if !dynamic_external_of_clause_is_valid(self, code_repo, p + o) {
                                 self.fail = true;
                             } else {
                                 self.p += o;
@@ -1513,7 +1604,7 @@ impl MachineState {
                         IndexingCodePtr::DynamicExternal(o) => {
                             let p = self.p.local().abs_loc();
 
-                            if !dynamic_external_of_clause_is_valid(self, &code_repo.code, p + o) {
+                            if !dynamic_external_of_clause_is_valid(self, code_repo, p + o) {
                                 self.fail = true;
                             } else {
                                 self.p += o;
@@ -1671,6 +1762,23 @@ impl MachineState {
             addr,
             AttrVarPolicy::DeepCopy,
         );
+
+        // error terms keep the frames active where they were thrown,
+        // for their backtraces.
+        let is_error = match self.store(self.deref(addr)) {
+            Addr::Str(s) => match &self.heap[s] {
+                HeapCellValue::NamedStr(2, name, _) => name.as_str() == "error",
+                _ => false,
+            },
+            _ => false,
+        };
+
+        self.backtrace = match self.p.local() {
+            LocalCodePtr::DirEntry(p) | LocalCodePtr::IndexingBuf(p, ..) if is_error => {
+                self.active_continuations(p, false)
+            }
+            _ => vec![],
+        };
     }
 
     pub(super) fn handle_internal_call_n(&mut self, arity: usize) {
@@ -2048,6 +2156,11 @@ impl MachineState {
                         }
                     }
                 }
+                (Addr::Pointer(p1), Addr::Pointer(p2)) => {
+                    if self.heap.pointer(p1) != self.heap.pointer(p2) {
+                        return true;
+                    }
+                }
                 (a1, a2) => {
                     if let Ok(n1) = Number::try_from((a1, &self.heap)) {
                         if let Ok(n2) = Number::try_from((a2, &self.heap)) {
@@ -3045,6 +3158,46 @@ impl MachineState {
         self.p += 1;
     }
 
+    // true if the heap or the stack and trail have outgrown their
+    // limits.
+    // a limit raises one error when memory grows past it. memory may
+    // then grow by a quarter of the limit more, so that the handler
+    // can run, and the limit is rearmed once memory falls below it.
+    fn exceeds_memory_limits(&mut self) -> bool {
+        let heap_size = self.heap.h() * mem::size_of::<HeapCellValue>();
+        let stack_size = self.stack.size() + self.trail.len() * mem::size_of::<TrailRef>();
+
+        let exceeds = |size: usize, limit: Option<usize>, headroom: bool| match limit {
+            Some(limit) if headroom => size > limit + limit / 4,
+            Some(limit) => size > limit,
+            None => false,
+        };
+
+        let raised = self.memory_limit_raised;
+
+        if exceeds(heap_size, self.heap_limit, raised)
+            || exceeds(stack_size, self.stack_limit, raised)
+        {
+            self.memory_limit_raised = true;
+            true
+        } else {
+            if !exceeds(heap_size, self.heap_limit, false)
+                && !exceeds(stack_size, self.stack_limit, false)
+            {
+                self.memory_limit_raised = false;
+            }
+
+            false
+        }
+    }
+
+    fn throw_memory_exception(&mut self, name: ClauseName, arity: usize) {
+        let stub = MachineError::functor_stub(name, arity);
+        let err = self.error_form(MachineError::resource_error("memory"), stub);
+
+        self.throw_exception(err);
+    }
+
     fn throw_interrupt_exception(&mut self) {
         let err = MachineError::interrupt_error();
         let src = functor!("repl");
@@ -3053,6 +3206,187 @@ impl MachineState {
         self.throw_exception(err);
     }
 
+    // the code at p and the continuations of the active frames,
+    // innermost first.
+    fn active_continuations(&self, p: usize, lco: bool) -> Vec<usize> {
+        let mut cps = vec![p];
+
+        if lco {
+            cps.push(self.cp.abs_loc());
+        }
+
+        let mut e = self.e;
+
+        while e > 0 && cps.len() < MAX_BACKTRACE_FRAMES {
+            let frame = self.stack.index_and_frame(e);
+
+            if let LocalCodePtr::DirEntry(cp) = frame.prelude.cp {
+                cps.push(cp);
+            }
+
+            e = frame.prelude.e;
+        }
+
+        cps
+    }
+
+    // the predicates, with their modules, of the code at p and the
+    // continuations of the active frames, innermost first.
+    fn active_frames(
+        &self,
+        code_repo: &CodeRepo,
+        p: usize,
+        lco: bool,
+    ) -> Vec<(PredicateKey, ClauseName)> {
+        self.active_continuations(p, lco)
+            .into_iter()
+            .filter_map(|cp| code_repo.predicate_at(cp))
+            .map(|(key, module_name)| (key.clone(), module_name.clone()))
+            .collect()
+    }
+
+    // the goal name/arity as called with the arguments in the
+    // registers.
+    pub(super) fn goal_from_registers(&mut self, name: &ClauseName, arity: usize) -> Addr {
+        if arity == 0 {
+            self.heap
+                .to_unifiable(HeapCellValue::Atom(name.clone(), None))
+        } else {
+            let mut args = Vec::with_capacity(arity);
+
+            for i in 1..arity + 1 {
+                let addr = self.store(self.deref(self.registers[i]));
+
+                // variables of the environment are moved to the heap
+                // as by put_unsafe_value.
+                if let Addr::StackCell(..) = addr {
+                    let h = self.heap.h();
+
+                    self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+                    (self.bind_fn)(self, Ref::HeapCell(h), addr);
+
+                    args.push(self.heap[h].as_addr(h));
+                } else {
+                    args.push(addr);
+                }
+            }
+
+            let h = self.heap.h();
+
+            self.heap
+                .push(HeapCellValue::NamedStr(arity, name.clone(), None));
+
+            for addr in args {
+                self.heap.push(HeapCellValue::Addr(addr));
+            }
+
+            Addr::Str(h)
+        }
+    }
+
+    // prepares the call of '$toplevel':'$interrupt'(M:Goal, Frames, P)
+    // in place of the interrupted call of name/arity at P, returning
+    // its index, or None if the toplevel doesn't define it.
+    fn interrupt_handler(
+        &mut self,
+        indices: &IndexStore,
+        code_repo: &CodeRepo,
+        name: &ClauseName,
+        arity: usize,
+        lco: bool,
+    ) -> Option<CodeIndex> {
+        let p = match self.p {
+            CodePtr::Local(LocalCodePtr::DirEntry(p)) => p,
+            _ => return None,
+        };
+
+        let handler = indices
+            .modules
+            .get(&clause_name!("$toplevel"))?
+            .code_dir
+            .get(&(clause_name!("$interrupt"), 3))?
+            .clone();
+
+        let module_name = match code_repo.predicate_at(p) {
+            Some((_, module_name)) => module_name.clone(),
+            None => clause_name!("user"),
+        };
+
+        let goal = self.goal_from_registers(name, arity);
+
+        let frames = self.active_frames(code_repo, p, lco);
+        let mut frame_addrs = Vec::with_capacity(frames.len());
+
+        // each frame is written as Module:Name/Arity.
+        for ((name, arity), module_name) in frames {
+            let h = self.heap.h();
+
+            self.heap
+                .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+            self.heap.push(HeapCellValue::Atom(module_name, None));
+            self.heap.push(HeapCellValue::Addr(Addr::Str(h + 3)));
+            self.heap
+                .push(HeapCellValue::NamedStr(2, clause_name!("/"), None));
+            self.heap.push(HeapCellValue::Atom(name, None));
+            self.heap
+                .push(HeapCellValue::Addr(Addr::Fixnum(arity as isize)));
+
+            frame_addrs.push(Addr::Str(h));
+        }
+
+        let h = self.heap.h();
+
+        self.heap
+            .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+        self.heap.push(HeapCellValue::Atom(module_name, None));
+        self.heap.push(HeapCellValue::Addr(goal));
+
+        self.registers[1] = Addr::Str(h);
+        self.registers[2] = Addr::HeapCell(
+            self.heap
+                .to_list(frame_addrs.into_iter().map(HeapCellValue::Addr)),
+        );
+        self.registers[3] = Addr::Fixnum(p as isize);
+
+        Some(handler)
+    }
+
+    // prepares the call of loader:'$autoload'(Module, Goal, Library)
+    // in place of the call of name/arity, undefined in Module,
+    // returning its index, or None if the autoload flag is false or
+    // no library exports name/arity.
+    pub(super) fn autoload_handler(
+        &mut self,
+        indices: &IndexStore,
+        module_name: ClauseName,
+        name: &ClauseName,
+        arity: usize,
+    ) -> Option<CodeIndex> {
+        if !self.autoload {
+            return None;
+        }
+
+        let library = *AUTOLOAD.borrow().get(&(name.as_str(), arity))?;
+
+        let handler = indices
+            .modules
+            .get(&clause_name!("loader"))?
+            .code_dir
+            .get(&(clause_name!("$autoload"), 3))?
+            .clone();
+
+        let goal = self.goal_from_registers(name, arity);
+        let library = clause_name!(library.to_string(), self.atom_tbl);
+
+        self.registers[1] = self
+            .heap
+            .to_unifiable(HeapCellValue::Atom(module_name, None));
+        self.registers[2] = goal;
+        self.registers[3] = self.heap.to_unifiable(HeapCellValue::Atom(library, None));
+
+        Some(handler)
+    }
+
     fn handle_call_clause(
         &mut self,
         indices: &mut IndexStore,
@@ -3076,13 +3410,61 @@ impl MachineState {
         ) {
             Ok(interruption) => {
                 if interruption {
-                    self.throw_interrupt_exception();
-                    return;
+                    match ct {
+                        &ClauseType::Named(ref name, ..) | &ClauseType::Op(ref name, ..) => {
+                            match self.interrupt_handler(indices, code_repo, name, arity, lco) {
+                                Some(handler) => {
+                                    self.last_call = lco;
+
+                                    try_or_fail!(
+                                        self,
+                                        call_policy.context_call(
+                                            self,
+                                            clause_name!("$interrupt"),
+                                            3,
+                                            &handler
+                                        )
+                                    );
+                                }
+                                None => self.throw_interrupt_exception(),
+                            }
+
+                            return;
+                        }
+                        _ => {
+                            // the interrupt is handled at the next call
+                            // to a predicate.
+                            INTERRUPT.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                    }
                 }
             }
             Err(_) => unreachable!(),
         }
 
+        if self.heap.h() > self.gc_threshold {
+            self.collect_garbage(&mut indices.global_variables, cut_policy);
+        }
+
+        // the exception unwinds the heap and the stack to the catch/3
+        // handling it, so the machine goes on within its limits.
+        if self.exceeds_memory_limits() {
+            self.throw_memory_exception(ct.name(), arity);
+            return;
+        }
+
+        if self.time_slice > 0 {
+            if let CodePtr::Local(p) = self.p {
+                self.time_slice -= 1;
+
+                if self.time_slice == 0 {
+                    // resume at the call once this thread is rescheduled.
+                    self.p = CodePtr::REPL(REPLCodePtr::ThreadPreempt, p);
+                    return;
+                }
+            }
+        }
+
         let mut default_call_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
 
         let call_policy = if use_default_cp {
@@ -3122,10 +3504,28 @@ impl MachineState {
                 }
             }
             &ClauseType::Named(ref name, _, ref idx) | &ClauseType::Op(ref name, _, ref idx) => {
-                try_or_fail!(
-                    self,
-                    call_policy.context_call(self, name.clone(), arity, idx)
-                )
+                let handler = match self.p {
+                    CodePtr::Local(LocalCodePtr::DirEntry(p)) if idx.is_undefined() => {
+                        let module_name = match code_repo.predicate_at(p) {
+                            Some((_, module_name)) => module_name.clone(),
+                            None => clause_name!("user"),
+                        };
+
+                        self.autoload_handler(indices, module_name, name, arity)
+                    }
+                    _ => None,
+                };
+
+                match handler {
+                    Some(handler) => try_or_fail!(
+                        self,
+                        call_policy.context_call(self, clause_name!("$autoload"), 3, &handler)
+                    ),
+                    None => try_or_fail!(
+                        self,
+                        call_policy.context_call(self, name.clone(), arity, idx)
+                    ),
+                }
             }
             &ClauseType::System(ref ct) => try_or_fail!(
                 self,
@@ -3142,6 +3542,18 @@ impl MachineState {
         };
 
         self.last_call = false;
+
+        // built-in and system predicates build their terms without
+        // passing through another call, so memory is checked again
+        // once they succeed.
+        let is_builtin = matches!(
+            ct,
+            &ClauseType::BuiltIn(_) | &ClauseType::Inlined(_) | &ClauseType::System(_)
+        );
+
+        if is_builtin && !self.fail && self.exceeds_memory_limits() {
+            self.throw_memory_exception(ct.name(), arity);
+        }
     }
 
     pub(super) fn execute_ctrl_instr(
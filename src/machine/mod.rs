@@ -19,7 +19,12 @@ pub(crate) mod code_walker;
 pub(crate) mod loader;
 mod compile;
 mod copier;
+pub mod embedding;
+pub(crate) mod ffi;
+pub mod foreign;
+mod gc;
 pub(crate) mod heap;
+mod image;
 mod load_state;
 pub(crate) mod machine_errors;
 pub(crate) mod machine_indices;
@@ -30,6 +35,7 @@ mod raw_block;
 mod stack;
 pub(crate) mod streams;
 mod term_stream;
+mod threads;
 
 #[macro_use]
 mod arithmetic_ops;
@@ -39,9 +45,12 @@ mod system_calls;
 
 use crate::machine::code_repo::*;
 use crate::machine::compile::*;
+use crate::machine::embedding::QueryAnswer;
+use crate::machine::foreign::ForeignPredicates;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
+use crate::machine::threads::*;
 pub use crate::machine::streams::Stream;
 
 use indexmap::IndexMap;
@@ -50,7 +59,7 @@ use indexmap::IndexMap;
 use prolog_parser::ast::ClauseName;
 use std::fs::File;
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 
 #[derive(Debug)]
@@ -66,8 +75,14 @@ lazy_static! {
 impl MachinePolicies {
     #[inline]
     fn new() -> Self {
+        let mut call_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
+
+        // the inference count is kept from the start, for
+        // statistics/2 as well as call_with_inference_limit/3.
+        CWILCallPolicy::new_in_place(&mut call_policy);
+
         MachinePolicies {
-            call_policy: Box::new(DefaultCallPolicy {}),
+            call_policy,
             cut_policy: Box::new(DefaultCutPolicy {}),
         }
     }
@@ -85,6 +100,10 @@ pub(super) struct LoadContext {
     pub(super) path: PathBuf,
     pub(super) stream: Stream,
     pub(super) module: ClauseName,
+    // the line and column the clause being read begins at.
+    pub(super) location: Option<(usize, usize)>,
+    // the file being compiled, if path is a file it includes.
+    pub(super) source: Option<PathBuf>,
 }
 
 impl LoadContext {
@@ -98,10 +117,18 @@ impl LoadContext {
             path_buf = current_dir;
         }
 
+        // resolve ./, ../ and symbolic links, so that a file has the
+        // same path however it's reached.
+        if let Ok(canonical_path_buf) = path_buf.canonicalize() {
+            path_buf = canonical_path_buf;
+        }
+
         LoadContext {
             path: path_buf,
             stream,
             module: clause_name!("user"),
+            location: None,
+            source: None,
         }
     }
 }
@@ -117,6 +144,9 @@ pub struct Machine {
     pub(super) user_output: Stream,
     pub(super) user_error: Stream,
     pub(super) load_contexts: Vec<LoadContext>,
+    pub(super) threads: ThreadTable,
+    pub(super) query_answer: Option<(QueryAnswer, usize)>,
+    pub(super) foreign_predicates: ForeignPredicates,
 }
 
 #[inline]
@@ -275,21 +305,11 @@ impl Machine {
         }
     }
 
-    pub fn new(user_input: Stream, user_output: Stream, user_error: Stream) -> Self {
+    // compiles the bootstrapping libraries and loads the toplevel, as
+    // Machine::new does when it has no boot image to read.
+    fn compile_libraries(&mut self) {
         use ref_thread_local::RefThreadLocal;
 
-        let mut wam = Machine {
-            machine_st: MachineState::new(),
-            inner_heap: Heap::new(),
-            policies: MachinePolicies::new(),
-            indices: IndexStore::new(),
-            code_repo: CodeRepo::new(),
-            user_input,
-            user_output,
-            user_error,
-            load_contexts: vec![],
-        };
-
         let mut lib_path = current_dir();
 
         lib_path.pop();
@@ -297,7 +317,7 @@ impl Machine {
 
         bootstrapping_compile(
             Stream::from(LIBRARIES.borrow()["ops_and_meta_predicates"]),
-            &mut wam,
+            self,
             ListingSource::from_file_and_path(
                 clause_name!("ops_and_meta_predicates.pl"),
                 lib_path.clone(),
@@ -307,16 +327,16 @@ impl Machine {
 
         bootstrapping_compile(
             Stream::from(LIBRARIES.borrow()["builtins"]),
-            &mut wam,
+            self,
             ListingSource::from_file_and_path(clause_name!("builtins.pl"), lib_path.clone()),
         )
         .unwrap();
 
-        if let Some(builtins) = wam.indices.modules.get(&clause_name!("builtins")) {
+        if let Some(builtins) = self.indices.modules.get(&clause_name!("builtins")) {
             load_module(
-                &mut wam.indices.code_dir,
-                &mut wam.indices.op_dir,
-                &mut wam.indices.meta_predicates,
+                &mut self.indices.code_dir,
+                &mut self.indices.op_dir,
+                &mut self.indices.meta_predicates,
                 &CompilationTarget::User,
                 builtins,
             );
@@ -328,18 +348,18 @@ impl Machine {
 
         bootstrapping_compile(
             Stream::from(include_str!("../loader.pl")),
-            &mut wam,
+            self,
             ListingSource::from_file_and_path(clause_name!("loader.pl"), lib_path.clone()),
         )
         .unwrap();
 
-        wam.configure_modules();
+        self.configure_modules();
 
-        if let Some(loader) = wam.indices.modules.get(&clause_name!("loader")) {
+        if let Some(loader) = self.indices.modules.get(&clause_name!("loader")) {
             load_module(
-                &mut wam.indices.code_dir,
-                &mut wam.indices.op_dir,
-                &mut wam.indices.meta_predicates,
+                &mut self.indices.code_dir,
+                &mut self.indices.op_dir,
+                &mut self.indices.meta_predicates,
                 &CompilationTarget::User,
                 loader,
             );
@@ -347,13 +367,102 @@ impl Machine {
             unreachable!()
         }
 
-        wam.load_special_forms();
-        wam.load_top_level();
+        self.load_special_forms();
+        self.load_top_level();
+    }
+
+    fn with_streams(user_input: Stream, user_output: Stream, user_error: Stream) -> Self {
+        Machine {
+            machine_st: MachineState::new(),
+            inner_heap: Heap::new(),
+            policies: MachinePolicies::new(),
+            indices: IndexStore::new(),
+            code_repo: CodeRepo::new(),
+            user_input,
+            user_output,
+            user_error,
+            load_contexts: vec![],
+            threads: ThreadTable::new(),
+            query_answer: None,
+            foreign_predicates: ForeignPredicates::new(),
+        }
+    }
+
+    // starts the machine from the boot image of this build if there
+    // is one, and by compiling the libraries otherwise. The image is
+    // never written; see with_boot_image.
+    pub fn new(user_input: Stream, user_output: Stream, user_error: Stream) -> Self {
+        let mut wam = Machine::with_streams(user_input, user_output, user_error);
+
+        if !wam.load_image() {
+            wam.compile_libraries();
+        }
+
+        wam.configure_streams();
+
+        wam
+    }
+
+    // like new, but saves the boot image to the cache directory of the
+    // user if there was none to start from.
+    pub fn with_boot_image(user_input: Stream, user_output: Stream, user_error: Stream) -> Self {
+        let mut wam = Machine::with_streams(user_input, user_output, user_error);
+
+        if !wam.load_image() {
+            wam.compile_libraries();
+            wam.save_image();
+        }
+
+        wam.configure_streams();
+
+        wam
+    }
+
+    // starts the machine by compiling the libraries, neither reading
+    // nor writing a boot image.
+    pub fn without_boot_image(user_input: Stream, user_output: Stream, user_error: Stream) -> Self {
+        let mut wam = Machine::with_streams(user_input, user_output, user_error);
+
+        wam.compile_libraries();
         wam.configure_streams();
 
         wam
     }
 
+    // starts the machine from the state saved to path by
+    // qsave_program/2, which must have been saved by this build.
+    pub fn restore(
+        user_input: Stream,
+        user_output: Stream,
+        user_error: Stream,
+        path: &Path,
+    ) -> std::io::Result<Self> {
+        let mut wam = Machine::with_streams(user_input, user_output, user_error);
+
+        wam.load_state(path)?;
+        wam.configure_streams();
+
+        Ok(wam)
+    }
+
+    // starts the machine from the state saved in the running
+    // executable, if qsave_program/2 made it a stand-alone executable.
+    pub fn restore_embedded(
+        user_input: Stream,
+        user_output: Stream,
+        user_error: Stream,
+    ) -> Option<Self> {
+        let mut wam = Machine::with_streams(user_input, user_output, user_error);
+
+        if !wam.load_embedded_state() {
+            return None;
+        }
+
+        wam.configure_streams();
+
+        Some(wam)
+    }
+
     pub(crate) fn configure_streams(&mut self) {
         self.user_input.options_mut().alias = Some(clause_name!("user_input"));
 
@@ -421,9 +530,15 @@ impl Machine {
             REPLCodePtr::PopLoadContext => {
                 self.pop_load_context();
             }
+            REPLCodePtr::PushIncludeContext => {
+                self.push_include_context();
+            }
             REPLCodePtr::PushLoadContext => {
                 self.push_load_context();
             }
+            REPLCodePtr::SetClauseLocation => {
+                self.set_clause_location();
+            }
             REPLCodePtr::PopLoadStatePayload => {
                 self.pop_load_state_payload();
             }
@@ -439,6 +554,9 @@ impl Machine {
             REPLCodePtr::PushLoadStatePayload => {
                 self.push_load_state_payload();
             }
+            REPLCodePtr::PushModuleLoadStatePayload => {
+                self.push_module_load_state_payload();
+            }
             REPLCodePtr::LoadContextSource => {
                 self.load_context_source();
             }
@@ -469,6 +587,9 @@ impl Machine {
             REPLCodePtr::DynamicProperty => {
                 self.dynamic_property();
             }
+            REPLCodePtr::PredicateClauses => {
+                self.predicate_clauses();
+            }
             REPLCodePtr::Assertz => {
                 self.compile_assert(AppendOrPrepend::Append);
             }
@@ -493,6 +614,63 @@ impl Machine {
             REPLCodePtr::AddNonCountedBacktracking => {
                 self.add_non_counted_backtracking();
             }
+            REPLCodePtr::MutexUnlock => {
+                self.mutex_unlock();
+            }
+            REPLCodePtr::ThreadCreate => {
+                self.thread_create();
+            }
+            REPLCodePtr::ThreadJoin => {
+                self.thread_join();
+            }
+            REPLCodePtr::ThreadPeekMessage => {
+                self.thread_peek_message();
+            }
+            REPLCodePtr::ThreadRemoveMessage => {
+                self.thread_remove_message();
+            }
+            REPLCodePtr::ThreadSelf => {
+                self.thread_self();
+            }
+            REPLCodePtr::ThreadSendMessage => {
+                self.thread_send_message();
+            }
+            REPLCodePtr::ForeignCall => {
+                self.foreign_call();
+            }
+            REPLCodePtr::QSaveProgram => {
+                self.qsave_program();
+            }
+            // the remaining commands may switch threads, and with
+            // them the machine state p belongs to.
+            REPLCodePtr::ThreadFinish => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.thread_finish();
+            }
+            REPLCodePtr::ThreadPreempt => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.thread_preempt();
+            }
+            REPLCodePtr::ThreadWaitJoin => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.thread_wait_join();
+            }
+            REPLCodePtr::ThreadWaitMessage => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.thread_wait_message();
+            }
+            REPLCodePtr::ThreadWaitReadable => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.thread_wait_readable();
+            }
+            REPLCodePtr::MutexLock => {
+                self.machine_st.p = CodePtr::Local(p);
+                return self.mutex_lock();
+            }
+            // suspends the machine until the next answer is requested.
+            REPLCodePtr::YieldAnswer => {
+                return self.yield_answer();
+            }
         }
 
         self.machine_st.p = CodePtr::Local(p);
@@ -591,6 +769,16 @@ impl MachineState {
             None => return,
         };
 
+        if let Some(coverage) = &mut self.coverage {
+            if let CodePtr::Local(LocalCodePtr::DirEntry(p)) = self.p {
+                if coverage.len() <= p {
+                    coverage.resize(code_repo.code.len().max(p + 1), 0);
+                }
+
+                coverage[p] += 1;
+            }
+        }
+
         self.dispatch_instr(
             instr.as_ref(),
             indices,
@@ -684,6 +872,10 @@ impl MachineState {
         loop {
             self.execute_instr(indices, policies, code_repo, user_input, user_output);
 
+            if let Some((index_loc, arg)) = self.arg_index_request.take() {
+                code_repo.build_arg_index(index_loc, arg, self.atom_tbl.clone());
+            }
+
             if self.fail {
                 self.backtrack();
             }
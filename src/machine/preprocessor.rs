@@ -103,6 +103,7 @@ fn setup_predicate_indicator(term: &mut Term) -> Result<PredicateKey, Compilatio
                 .and_then(|c| match c {
                     Constant::Integer(n) => n.to_usize(),
                     Constant::Fixnum(n) => usize::try_from(n).ok(),
+                    Constant::Usize(n) => Some(n),
                     _ => None,
                 })
                 .ok_or(CompilationError::InvalidModuleExport)?;
@@ -42,6 +42,11 @@ impl<T: RawBlockTraits> RawBlock<T> {
         let layout = alloc::Layout::from_size_align_unchecked(cap, T::align());
 
         self.base = alloc::alloc(layout) as *const _;
+
+        if self.base.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
         self.size = cap;
 
         self.top = T::base_offset(self.base);
@@ -51,10 +56,15 @@ impl<T: RawBlockTraits> RawBlock<T> {
         if self.size == 0 {
             self.init_at_size(T::init_size());
         } else {
-            let layout = alloc::Layout::from_size_align_unchecked(T::init_size(), T::align());
+            let layout = alloc::Layout::from_size_align_unchecked(self.size, T::align());
             let top_dist = self.top as usize - self.base as usize;
 
             self.base = alloc::realloc(self.base as *mut _, layout, self.size * 2) as *const _;
+
+            if self.base.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+
             self.top = (self.base as usize + top_dist) as *const _;
             self.size *= 2;
         }
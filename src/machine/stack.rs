@@ -167,6 +167,12 @@ impl Stack {
         }
     }
 
+    // the size of the frames on the stack, in bytes.
+    #[inline]
+    pub(crate) fn size(&self) -> usize {
+        self.buf.top as usize - self.buf.base as usize
+    }
+
     pub(crate) fn allocate_and_frame(&mut self, num_cells: usize) -> usize {
         let frame_size = AndFrame::size_of(num_cells);
 
@@ -18,6 +18,8 @@ use std::io::{stderr, stdout, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::net::{Shutdown, TcpStream};
 use std::ops::DerefMut;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::rc::Rc;
 
 use native_tls::TlsStream;
@@ -121,6 +123,20 @@ enum StreamInstance {
 }
 
 impl StreamInstance {
+    /* Only plain sockets are waited on: TLS streams buffer their
+     * input, and the other streams are either never empty or, like
+     * user_input, read on behalf of the whole process. */
+    #[cfg(unix)]
+    fn input_fd(&self) -> Option<RawFd> {
+        match self {
+            StreamInstance::PausedPrologStream(ref put_back, ref stream) if put_back.is_empty() => {
+                stream.input_fd()
+            }
+            StreamInstance::TcpStream(_, ref tcp_stream) => Some(tcp_stream.as_raw_fd()),
+            _ => None,
+        }
+    }
+
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
             StreamInstance::PausedPrologStream(ref mut put_back, ref mut stream) => {
@@ -163,11 +179,12 @@ impl StreamInstance {
 impl Drop for StreamInstance {
     fn drop(&mut self) {
         match self {
+            // the peer may have hung up already, which is no error.
             StreamInstance::TcpStream(_, ref mut tcp_stream) => {
-                tcp_stream.shutdown(Shutdown::Both).unwrap();
+                let _ = tcp_stream.shutdown(Shutdown::Both);
             }
             StreamInstance::TlsStream(_, ref mut tls_stream) => {
-                tls_stream.shutdown().unwrap();
+                let _ = tls_stream.shutdown();
             }
             _ => {}
         }
@@ -206,6 +223,7 @@ pub(crate) struct InnerStream {
     stream_inst: StreamInstance,
     past_end_of_stream: bool,
     lines_read: usize,
+    line_position: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -219,6 +237,7 @@ impl WrappedStreamInstance {
             stream_inst,
             past_end_of_stream,
             lines_read: 0,
+            line_position: 0,
         })))
     }
 }
@@ -399,9 +418,25 @@ impl Stream {
         self.stream_inst.0.borrow_mut().lines_read
     }
 
+    // the column of the next character to be read by the parser.
     #[inline]
-    pub(crate) fn add_lines_read(&mut self, incr_num_lines_read: usize) {
-        self.stream_inst.0.borrow_mut().lines_read += incr_num_lines_read;
+    pub(crate) fn line_position(&self) -> usize {
+        self.stream_inst.0.borrow().line_position
+    }
+
+    // the column the parser left off at counts from the start of the
+    // line if it read a new line, and from line_position otherwise.
+    #[inline]
+    pub(crate) fn add_chars_read(&mut self, incr_num_lines_read: usize, col_num: usize) {
+        let mut inner_stream = self.stream_inst.0.borrow_mut();
+
+        inner_stream.lines_read += incr_num_lines_read;
+
+        if incr_num_lines_read > 0 {
+            inner_stream.line_position = col_num;
+        } else {
+            inner_stream.line_position += col_num;
+        }
     }
 
     #[inline]
@@ -504,6 +539,12 @@ impl Stream {
         }
     }
 
+    // the descriptor a read from the stream would block on, if any.
+    #[cfg(unix)]
+    pub(crate) fn input_fd(&self) -> Option<RawFd> {
+        self.stream_inst.0.borrow().stream_inst.input_fd()
+    }
+
     #[inline]
     pub(crate) fn file_name(&self) -> Option<ClauseName> {
         match self.stream_inst.0.borrow().stream_inst {
@@ -655,6 +696,7 @@ impl Stream {
     #[inline]
     pub(super) fn reset(&mut self) -> bool {
         self.stream_inst.0.borrow_mut().lines_read = 0;
+        self.stream_inst.0.borrow_mut().line_position = 0;
         self.stream_inst.0.borrow_mut().past_end_of_stream = false;
 
         loop {
@@ -17,6 +17,7 @@ use crate::machine;
 use crate::machine::code_repo::CodeRepo;
 use crate::machine::code_walker::*;
 use crate::machine::copier::*;
+use crate::machine::loader::CompilationTarget;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
@@ -27,7 +28,7 @@ use crate::read::readline;
 use crate::rug::Integer;
 use ordered_float::OrderedFloat;
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use ref_thread_local::RefThreadLocal;
 
@@ -37,6 +38,7 @@ use std::env;
 use std::fs;
 use std::io::{ErrorKind, Read, Write};
 use std::iter::{once, FromIterator};
+use std::mem;
 use std::net::{TcpListener, TcpStream};
 use std::num::NonZeroU32;
 use std::ops::Sub;
@@ -980,6 +982,37 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::LoadStatePayloadModule => {
+                // A1 = the load state of a file, A2 = the module it
+                // declared. fails if it declared none.
+                let payload = self.store(self.deref(self[temp_v!(1)]));
+
+                let module_name = match payload {
+                    Addr::LoadStatePayload(h) => match &self.heap[h] {
+                        HeapCellValue::LoadStatePayload(payload) => {
+                            match &payload.compilation_target {
+                                CompilationTarget::Module(ref module_name) => module_name.clone(),
+                                CompilationTarget::User => {
+                                    self.fail = true;
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        _ => unreachable!(),
+                    },
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let a2 = self[temp_v!(2)];
+                let module_name = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(module_name, None));
+
+                (self.unify_fn)(self, a2, module_name);
+            }
             &SystemClauseType::FileTime => {
                 let file = self.heap_pstr_iter(self[temp_v!(1)]).to_string();
 
@@ -2626,6 +2659,31 @@ impl MachineState {
                 stream.flush().unwrap();
             }
             &SystemClauseType::GetSingleChar => {
+                let user_input = indices
+                    .stream_aliases
+                    .get(&clause_name!("user_input"))
+                    .cloned()
+                    .unwrap();
+
+                // input that isn't typed at a terminal, as from a pipe
+                // or a Machine given its input as a string, is read a
+                // character at a time instead.
+                if !user_input.is_stdin() || unsafe { libc::isatty(0) } == 0 {
+                    let mut iter = self.open_parsing_stream(user_input, "get_single_char", 1)?;
+
+                    match iter.next() {
+                        Some(Ok(c)) => {
+                            let a1 = self[temp_v!(1)];
+                            (self.unify_fn)(self, Addr::Char(c), a1);
+                        }
+                        _ => {
+                            self.fail = true;
+                        }
+                    }
+
+                    return return_from_clause!(self.last_call, self);
+                }
+
                 let ctrl_c = KeyEvent {
                     code: KeyCode::Char('c'),
                     modifiers: KeyModifiers::CONTROL,
@@ -2706,7 +2764,11 @@ impl MachineState {
                 if !stream.is_stdin() && !stream.is_stdout() && !stream.is_stderr() {
                     stream.close();
 
-                    if let Some(ref alias) = stream.options().alias {
+                    // the closed stream lingers in the heap cells
+                    // referring to it, so its alias is released here.
+                    let alias = stream.options_mut().alias.take();
+
+                    if let Some(ref alias) = alias {
                         indices.stream_aliases.remove(alias);
                     }
                 }
@@ -3086,6 +3148,79 @@ impl MachineState {
 
                 (self.unify_fn)(self, a1, addr);
             }
+            &SystemClauseType::GarbageCollect => {
+                self.collect_garbage(&mut indices.global_variables, cut_policy);
+            }
+            &SystemClauseType::GarbageCollectAtoms => {
+                self.atom_tbl.sweep();
+            }
+            &SystemClauseType::Statistics => {
+                let key = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                // times are in milliseconds, and memory in bytes.
+                let value = match key.as_str() {
+                    "atoms" => Addr::Fixnum(self.atom_tbl.len() as isize),
+                    "choicepoints" => {
+                        let mut b = self.b;
+                        let mut count = 0;
+
+                        while b > 0 {
+                            b = self.stack.index_or_frame(b).prelude.b;
+                            count += 1;
+                        }
+
+                        Addr::Fixnum(count)
+                    }
+                    "clauses" => Addr::Fixnum(code_repo.number_of_clauses(indices) as isize),
+                    "cputime" => {
+                        let cputime = ProcessTime::now().as_duration().as_secs_f64();
+                        self.heap
+                            .put_constant(Constant::Float(OrderedFloat(cputime)))
+                    }
+                    "heapused" => {
+                        Addr::Fixnum((self.heap.h() * mem::size_of::<HeapCellValue>()) as isize)
+                    }
+                    "inferences" => match find_call_policy::<CWILCallPolicy>(call_policy) {
+                        Some(call_policy) => {
+                            let count = call_policy.count().clone();
+                            self.heap
+                                .to_unifiable(HeapCellValue::Integer(Rc::new(count)))
+                        }
+                        None => panic!("statistics: requires CWILCallPolicy."),
+                    },
+                    "modules" => Addr::Fixnum(indices.modules.len() as isize),
+                    "runtime" | "walltime" => {
+                        let (now, last) = if key.as_str() == "runtime" {
+                            let now = ProcessTime::now().as_duration();
+                            (now, mem::replace(&mut self.last_runtime, now))
+                        } else {
+                            let now = self.start_time.elapsed();
+                            (now, mem::replace(&mut self.last_walltime, now))
+                        };
+
+                        let since_last = now.checked_sub(last).unwrap_or_default();
+                        let times = vec![now.as_millis(), since_last.as_millis()];
+
+                        Addr::HeapCell(
+                            self.heap.to_list(
+                                times
+                                    .into_iter()
+                                    .map(|ms| HeapCellValue::Addr(Addr::Fixnum(ms as isize))),
+                            ),
+                        )
+                    }
+                    "stackused" => Addr::Fixnum(self.stack.size() as isize),
+                    "trailused" => {
+                        Addr::Fixnum((self.trail.len() * mem::size_of::<TrailRef>()) as isize)
+                    }
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                (self.unify_fn)(self, self[temp_v!(2)], value);
+            }
             &SystemClauseType::CurrentTime => {
                 let str = self.systemtime_to_timestamp(SystemTime::now());
                 (self.unify_fn)(self, self[temp_v!(1)], str);
@@ -3418,6 +3553,29 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::GetAutoload => {
+                let value = clause_name!(if self.autoload { "true" } else { "false" });
+                let value = self.heap.to_unifiable(HeapCellValue::Atom(value, None));
+
+                (self.unify_fn)(self, self[temp_v!(1)], value);
+            }
+            &SystemClauseType::GetMemoryLimit => {
+                let kind = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                let limit = match kind.as_str() {
+                    "heap" => self.heap_limit,
+                    _ => self.stack_limit,
+                };
+
+                let value = match limit {
+                    Some(limit) => Addr::Fixnum(limit as isize),
+                    None => self
+                        .heap
+                        .to_unifiable(HeapCellValue::Atom(clause_name!("infinite"), None)),
+                };
+
+                (self.unify_fn)(self, self[temp_v!(2)], value);
+            }
             &SystemClauseType::GetSCCCleaner => {
                 let dest = self[temp_v!(1)];
 
@@ -3488,10 +3646,6 @@ impl MachineState {
                 let a1 = self.store(self.deref(self[temp_v!(1)]));
                 let a2 = self.store(self.deref(self[temp_v!(2)]));
 
-                if call_policy.downcast_ref::<CWILCallPolicy>().is_err() {
-                    CWILCallPolicy::new_in_place(call_policy);
-                }
-
                 let n = match Number::try_from((a2, &self.heap)) {
                     Ok(Number::Integer(n)) => Integer::from(&*n.clone()),
                     Ok(Number::Fixnum(n)) => Integer::from(n),
@@ -3510,7 +3664,7 @@ impl MachineState {
 
                 match a1 {
                     Addr::Usize(bp) | Addr::CutPoint(bp) => {
-                        match call_policy.downcast_mut::<CWILCallPolicy>().ok() {
+                        match find_call_policy::<CWILCallPolicy>(call_policy) {
                             Some(call_policy) => {
                                 let count = call_policy.add_limit(n, bp).clone();
                                 let count = self
@@ -3533,6 +3687,37 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::InstallTimeLimit => {
+                // A1 = the limit in microseconds, A2 = B. limits too
+                // large for a u64 never expire.
+                let a1 = self.store(self.deref(self[temp_v!(1)]));
+
+                let micros = match Number::try_from((a1, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => u64::try_from(n).unwrap_or(0),
+                    Ok(Number::Integer(n)) => n.to_u64().unwrap_or(u64::MAX),
+                    _ => u64::MAX,
+                };
+
+                if find_call_policy::<TimeLimitCallPolicy>(call_policy).is_none() {
+                    TimeLimitCallPolicy::new_in_place(call_policy);
+                }
+
+                let a2 = self.store(self.deref(self[temp_v!(2)]));
+
+                match (find_call_policy::<TimeLimitCallPolicy>(call_policy), a2) {
+                    (Some(call_policy), Addr::Usize(bp))
+                    | (Some(call_policy), Addr::CutPoint(bp)) => {
+                        call_policy.add_limit(Duration::from_micros(micros), bp);
+                    }
+                    (Some(_), _) => {
+                        panic!("install_time_limit: expected Usize in A2.");
+                    }
+                    (None, _) => panic!(
+                        "install_time_limit: should have installed \\
+                         TimeLimitCallPolicy."
+                    ),
+                }
+            }
             &SystemClauseType::ModuleExists => {
                 let module = self.store(self.deref(self[temp_v!(1)]));
 
@@ -3638,36 +3823,8 @@ impl MachineState {
             &SystemClauseType::ResetAttrVarState => {
                 self.attr_var_init.reset();
             }
-            &SystemClauseType::RemoveCallPolicyCheck => {
-                let restore_default = match call_policy.downcast_mut::<CWILCallPolicy>().ok() {
-                    Some(call_policy) => {
-                        let a1 = self.store(self.deref(self[temp_v!(1)]));
-
-                        match a1 {
-                            Addr::Usize(bp) | Addr::CutPoint(bp) => {
-                                if call_policy.is_empty() && bp == self.b {
-                                    Some(call_policy.into_inner())
-                                } else {
-                                    None
-                                }
-                            }
-                            _ => {
-                                panic!("remove_call_policy_check: expected Usize in A1.");
-                            }
-                        }
-                    }
-                    None => panic!(
-                        "remove_call_policy_check: requires \\
-                             CWILCallPolicy."
-                    ),
-                };
-
-                if let Some(new_policy) = restore_default {
-                    *call_policy = new_policy;
-                }
-            }
             &SystemClauseType::RemoveInferenceCounter => {
-                match call_policy.downcast_mut::<CWILCallPolicy>().ok() {
+                match find_call_policy::<CWILCallPolicy>(call_policy) {
                     Some(call_policy) => {
                         let a1 = self.store(self.deref(self[temp_v!(1)]));
 
@@ -3693,6 +3850,251 @@ impl MachineState {
                     ),
                 }
             }
+            &SystemClauseType::InstallDebugger => {
+                // A1 = the spy points, as a list of spy(Module, Name,
+                // Arity), Module being unbound in those of any module.
+                let stub = MachineError::functor_stub(clause_name!("$install_debugger"), 1);
+                let mut spy_points = vec![];
+
+                for addr in self.try_from_list(temp_v!(1), stub)? {
+                    if let Addr::Str(s) = self.store(self.deref(addr)) {
+                        let module_name = match self.store(self.deref(Addr::HeapCell(s + 1))) {
+                            addr @ Addr::Con(_) | addr @ Addr::Char(_) => {
+                                Some(atom_from!(self, addr))
+                            }
+                            _ => None,
+                        };
+
+                        let name = atom_from!(self, self.store(self.deref(Addr::HeapCell(s + 2))));
+                        let arity = self.store(self.deref(Addr::HeapCell(s + 3)));
+
+                        let arity = match Number::try_from((arity, &self.heap)) {
+                            Ok(Number::Fixnum(n)) => n as usize,
+                            _ => unreachable!(),
+                        };
+
+                        spy_points.push((module_name, (name, arity)));
+                    }
+                }
+
+                if find_call_policy::<DebugCallPolicy>(call_policy).is_none() {
+                    let handler = indices
+                        .modules
+                        .get(&clause_name!("debug"))
+                        .and_then(|module| module.code_dir.get(&(clause_name!("$trace_goal"), 2)))
+                        .cloned();
+
+                    match handler {
+                        Some(handler) => DebugCallPolicy::new_in_place(call_policy, handler),
+                        None => {
+                            self.fail = true;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                if let Some(call_policy) = find_call_policy::<DebugCallPolicy>(call_policy) {
+                    call_policy.spy_points = spy_points;
+                    call_policy.update_extents(code_repo);
+                }
+            }
+            &SystemClauseType::RemoveDebugger => {
+                remove_call_policy::<DebugCallPolicy>(call_policy);
+            }
+            &SystemClauseType::InstallProfiler => {
+                let handler = indices
+                    .modules
+                    .get(&clause_name!("profile"))
+                    .and_then(|module| module.code_dir.get(&(clause_name!("$profile_goal"), 2)))
+                    .cloned();
+
+                match handler {
+                    Some(handler) => {
+                        remove_call_policy::<ProfileCallPolicy>(call_policy);
+                        ProfileCallPolicy::new_in_place(call_policy, handler);
+                    }
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                }
+
+                if let Some(call_policy) = find_call_policy::<ProfileCallPolicy>(call_policy) {
+                    call_policy.update_extents(code_repo);
+                }
+            }
+            &SystemClauseType::RemoveProfiler => {
+                // A1 = the recorded data, as a list of
+                // profile(Module:Name/Arity, Calls, Redos, Exits,
+                // Fails, SelfTime, CumulativeTime), in seconds of CPU
+                // time.
+                let mut profiles = vec![];
+
+                if let Some(call_policy) = find_call_policy::<ProfileCallPolicy>(call_policy) {
+                    for ((module_name, (name, arity)), entry) in &call_policy.entries {
+                        let h = self.heap.h();
+
+                        self.heap
+                            .push(HeapCellValue::NamedStr(7, clause_name!("profile"), None));
+                        self.heap.push(HeapCellValue::Addr(Addr::Str(h + 8)));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(entry.calls as isize)));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(entry.redos as isize)));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(entry.exits as isize)));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(entry.fails as isize)));
+                        self.heap.push(HeapCellValue::Addr(Addr::Float(OrderedFloat(
+                            entry.self_time.as_secs_f64(),
+                        ))));
+                        self.heap.push(HeapCellValue::Addr(Addr::Float(OrderedFloat(
+                            entry.cumulative_time.as_secs_f64(),
+                        ))));
+                        self.heap
+                            .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+                        self.heap
+                            .push(HeapCellValue::Atom(module_name.clone(), None));
+                        self.heap.push(HeapCellValue::Addr(Addr::Str(h + 11)));
+                        self.heap
+                            .push(HeapCellValue::NamedStr(2, clause_name!("/"), None));
+                        self.heap.push(HeapCellValue::Atom(name.clone(), None));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(*arity as isize)));
+
+                        profiles.push(Addr::Str(h));
+                    }
+                }
+
+                remove_call_policy::<ProfileCallPolicy>(call_policy);
+
+                let profiles = Addr::HeapCell(
+                    self.heap
+                        .to_list(profiles.into_iter().map(HeapCellValue::Addr)),
+                );
+
+                (self.unify_fn)(self, self[temp_v!(1)], profiles);
+            }
+            &SystemClauseType::InstallCoverage => {
+                self.coverage = Some(vec![]);
+            }
+            &SystemClauseType::RemoveCoverage => {
+                // A1 = the clauses of the files at least one of whose
+                // clauses ran, as a list of clause(File, Line,
+                // Module:Name/Arity, Count, GoalsRun, Goals), where
+                // Count is the number of times the clause was entered
+                // and GoalsRun is the number of its Goals that ran.
+                let coverage = self.coverage.take().unwrap_or_default();
+                let executions = |p: usize| coverage.get(p).cloned().unwrap_or(0);
+
+                let mut clauses = vec![];
+
+                let mut live_clauses = IndexMap::new();
+
+                for (start, end, location) in code_repo.clause_locations.iter() {
+                    let (file, line) = match location {
+                        Some((file, line, _)) if start < code_repo.code.len() => (file, *line),
+                        _ => continue,
+                    };
+
+                    let (key, module_name) = match code_repo.predicate_extents.extent_at(start) {
+                        Some((_, _, key, module_name)) => (key, module_name),
+                        None => continue,
+                    };
+
+                    // the clauses of reloaded predicates, and retracted
+                    // clauses, linger in the code, so those no longer
+                    // among the clauses of their predicates are skipped.
+                    let is_live = live_clauses
+                        .entry((key, module_name))
+                        .or_insert_with(|| {
+                            code_repo
+                                .predicate_clauses(indices, module_name, key)
+                                .unwrap_or_default()
+                                .into_iter()
+                                .collect::<IndexSet<_>>()
+                        })
+                        .contains(&start);
+
+                    if !is_live {
+                        continue;
+                    }
+
+                    let count = executions(code_repo.clause_entry(start, end));
+                    let goals = code_repo.clause_goals(start, end);
+                    let goals_run = goals.iter().filter(|&&p| executions(p) > 0).count();
+
+                    clauses.push((file, line, key, module_name, count, goals_run, goals.len()));
+                }
+
+                let files_run: IndexSet<_> = clauses
+                    .iter()
+                    .filter(|clause| clause.4 > 0)
+                    .map(|clause| clause.0.clone())
+                    .collect();
+
+                let mut clause_terms = vec![];
+
+                for (file, line, (name, arity), module_name, count, goals_run, goals) in clauses {
+                    if !files_run.contains(file) {
+                        continue;
+                    }
+
+                    let h = self.heap.h();
+
+                    self.heap
+                        .push(HeapCellValue::NamedStr(6, clause_name!("clause"), None));
+                    self.heap.push(HeapCellValue::Atom(file.clone(), None));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(line as isize)));
+                    self.heap.push(HeapCellValue::Addr(Addr::Str(h + 7)));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(count as isize)));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(goals_run as isize)));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(goals as isize)));
+                    self.heap
+                        .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+                    self.heap
+                        .push(HeapCellValue::Atom(module_name.clone(), None));
+                    self.heap.push(HeapCellValue::Addr(Addr::Str(h + 10)));
+                    self.heap
+                        .push(HeapCellValue::NamedStr(2, clause_name!("/"), None));
+                    self.heap.push(HeapCellValue::Atom(name.clone(), None));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(*arity as isize)));
+
+                    clause_terms.push(Addr::Str(h));
+                }
+
+                let clause_terms = Addr::HeapCell(
+                    self.heap
+                        .to_list(clause_terms.into_iter().map(HeapCellValue::Addr)),
+                );
+
+                (self.unify_fn)(self, self[temp_v!(1)], clause_terms);
+            }
+            &SystemClauseType::RemoveTimeLimit => {
+                let restore_default = match find_call_policy::<TimeLimitCallPolicy>(call_policy) {
+                    Some(call_policy) => {
+                        match self.store(self.deref(self[temp_v!(1)])) {
+                            Addr::Usize(bp) | Addr::CutPoint(bp) => call_policy.remove_limit(bp),
+                            _ => panic!("remove_time_limit: expected Usize in A1."),
+                        }
+
+                        call_policy.is_empty()
+                    }
+                    None => panic!(
+                        "remove_time_limit: requires \\
+                         TimeLimitCallPolicy."
+                    ),
+                };
+
+                if restore_default {
+                    remove_call_policy::<TimeLimitCallPolicy>(call_policy);
+                }
+            }
             &SystemClauseType::REPL(repl_code_ptr) => {
                 return self.repl_redirect(repl_code_ptr);
             }
@@ -3717,6 +4119,34 @@ impl MachineState {
 
                 return Ok(());
             }
+            &SystemClauseType::ResumeInterrupted => {
+                let goal = self.store(self.deref(self[temp_v!(1)]));
+                let p = self.store(self.deref(self[temp_v!(2)]));
+
+                // the arguments of the goal are loaded as they were
+                // when the call at p was interrupted, and the call is
+                // made again.
+                match goal {
+                    Addr::Str(s) => {
+                        if let HeapCellValue::NamedStr(arity, ..) = &self.heap[s] {
+                            for i in 1..arity + 1 {
+                                self.registers[i] = self.heap[s + i].as_addr(s + i);
+                            }
+                        }
+                    }
+                    Addr::Lis(l) => {
+                        self.registers[1] = self.heap[l].as_addr(l);
+                        self.registers[2] = self.heap[l + 1].as_addr(l + 1);
+                    }
+                    _ => {}
+                }
+
+                if let Addr::Fixnum(p) = p {
+                    self.p = CodePtr::Local(LocalCodePtr::DirEntry(p as usize));
+                }
+
+                return Ok(());
+            }
             &SystemClauseType::RestoreCutPolicy => {
                 let restore_default =
                     if let Ok(cut_policy) = cut_policy.downcast_ref::<SCCCutPolicy>() {
@@ -3803,6 +4233,29 @@ impl MachineState {
                     self.fail = true;
                 }
             },
+            &SystemClauseType::SetAutoload => {
+                let value = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+                self.autoload = value.as_str() == "true";
+            }
+            &SystemClauseType::SetMemoryLimit => {
+                let kind = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                // infinite and limits too large for a fixnum are no
+                // limit at all.
+                let a2 = self.store(self.deref(self[temp_v!(2)]));
+
+                let limit = match Number::try_from((a2, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+                    _ => None,
+                };
+
+                match kind.as_str() {
+                    "heap" => self.heap_limit = limit,
+                    _ => self.stack_limit = limit,
+                }
+
+                self.memory_limit_raised = false;
+            }
             &SystemClauseType::InferenceLevel => {
                 let a1 = self[temp_v!(1)];
                 let a2 = self.store(self.deref(self[temp_v!(2)]));
@@ -3852,6 +4305,78 @@ impl MachineState {
             &SystemClauseType::Fail => {
                 self.fail = true;
             }
+            &SystemClauseType::GetBacktrace => {
+                // A1 = the frames active where the last error term was
+                // thrown, innermost first, as a list of
+                // frame(Module:Name/Arity, Clause, Location), where
+                // Clause is the number of the clause and Location is
+                // File:Line, either of which is none if unknown.
+                let mut frames = vec![];
+
+                for &p in &self.backtrace {
+                    let (key, module_name) = match code_repo.predicate_at(p) {
+                        Some(predicate) => predicate,
+                        None => continue,
+                    };
+
+                    let (clause, location) = match code_repo.clause_locations.clause_at(p) {
+                        Some((start, location)) => {
+                            let clause = code_repo
+                                .predicate_clauses(indices, module_name, key)
+                                .and_then(|clause_starts| {
+                                    clause_starts.iter().position(|&s| s == start)
+                                });
+
+                            (clause, location)
+                        }
+                        None => (None, None),
+                    };
+
+                    let h = self.heap.h();
+
+                    self.heap
+                        .push(HeapCellValue::NamedStr(3, clause_name!("frame"), None));
+                    self.heap.push(HeapCellValue::Addr(Addr::Str(h + 4)));
+
+                    self.heap.push(match clause {
+                        Some(n) => HeapCellValue::Addr(Addr::Fixnum(n as isize + 1)),
+                        None => HeapCellValue::Atom(clause_name!("none"), None),
+                    });
+
+                    self.heap.push(match location {
+                        Some(_) => HeapCellValue::Addr(Addr::Str(h + 10)),
+                        None => HeapCellValue::Atom(clause_name!("none"), None),
+                    });
+
+                    self.heap
+                        .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+                    self.heap
+                        .push(HeapCellValue::Atom(module_name.clone(), None));
+                    self.heap.push(HeapCellValue::Addr(Addr::Str(h + 7)));
+                    self.heap
+                        .push(HeapCellValue::NamedStr(2, clause_name!("/"), None));
+                    self.heap.push(HeapCellValue::Atom(key.0.clone(), None));
+                    self.heap
+                        .push(HeapCellValue::Addr(Addr::Fixnum(key.1 as isize)));
+
+                    if let Some((file, line, _)) = location {
+                        self.heap
+                            .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+                        self.heap.push(HeapCellValue::Atom(file.clone(), None));
+                        self.heap
+                            .push(HeapCellValue::Addr(Addr::Fixnum(*line as isize)));
+                    }
+
+                    frames.push(Addr::Str(h));
+                }
+
+                let frames = Addr::HeapCell(
+                    self.heap
+                        .to_list(frames.into_iter().map(HeapCellValue::Addr)),
+                );
+
+                self.unify(frames, self[temp_v!(1)]);
+            }
             &SystemClauseType::GetBall => {
                 let addr = self.store(self.deref(self[temp_v!(1)]));
                 let h = self.heap.h();
@@ -4068,6 +4593,35 @@ impl MachineState {
                 self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
                 self[temp_v!(4)] = Addr::HeapCell(h);
             }
+            &SystemClauseType::Rethrow => {
+                // throws the ball in A1 with the backtrace saved in A2
+                // by $save_backtrace, rather than the frames of the
+                // handler rethrowing it.
+                let stub = MachineError::functor_stub(clause_name!("$rethrow"), 2);
+                let mut backtrace = vec![];
+
+                for addr in self.try_from_list(temp_v!(2), stub)? {
+                    match self.store(self.deref(addr)) {
+                        Addr::Usize(p) => backtrace.push(p),
+                        _ => unreachable!(),
+                    }
+                }
+
+                self.set_ball();
+                self.backtrace = backtrace;
+                self.unwind_stack();
+            }
+            &SystemClauseType::SaveBacktrace => {
+                let backtrace = Addr::HeapCell(
+                    self.heap.to_list(
+                        self.backtrace
+                            .iter()
+                            .map(|&p| HeapCellValue::Addr(Addr::Usize(p))),
+                    ),
+                );
+
+                self.unify(backtrace, self[temp_v!(1)]);
+            }
             &SystemClauseType::SetBall => {
                 self.set_ball();
             }
@@ -4399,6 +4953,15 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::FFICall => {
+                self.call_foreign_function(&indices.foreign_libraries)?;
+            }
+            &SystemClauseType::FFIDefine => {
+                self.define_foreign_function(&mut indices.foreign_libraries)?;
+            }
+            &SystemClauseType::FFILoadLibrary => {
+                self.load_foreign_library(&mut indices.foreign_libraries);
+            }
             &SystemClauseType::SocketServerClose => {
                 match self.store(self.deref(self[temp_v!(1)])) {
                     Addr::TcpListener(h) => {
@@ -5539,6 +6102,114 @@ impl MachineState {
             &SystemClauseType::DebugHook => {
                 self.fail = false;
             }
+            &SystemClauseType::DebugCall => {
+                // A1 = the goal, A2 = static(P) or dynamic(P), P being
+                // the code of its predicate, which is called as the
+                // goal was, without passing through the call policy.
+                let (dynamic, p) = match self.store(self.deref(self[temp_v!(2)])) {
+                    Addr::Str(s) => match (&self.heap[s], self.heap[s + 1].as_addr(s + 1)) {
+                        (HeapCellValue::NamedStr(1, name, _), Addr::Usize(p)) => {
+                            (name.as_str() == "dynamic", p)
+                        }
+                        _ => unreachable!(),
+                    },
+                    _ => unreachable!(),
+                };
+
+                let arity = match self.store(self.deref(self[temp_v!(1)])) {
+                    Addr::Str(s) => match self.heap.clone(s) {
+                        HeapCellValue::NamedStr(arity, ..) => {
+                            for i in 1..arity + 1 {
+                                self.registers[i] = self.heap[s + i].as_addr(s + i);
+                            }
+
+                            arity
+                        }
+                        _ => unreachable!(),
+                    },
+                    _ => 0,
+                };
+
+                if let Some(call_policy) = find_call_policy::<DebugCallPolicy>(call_policy) {
+                    call_policy.update_extents(code_repo);
+                }
+
+                if let Some(call_policy) = find_call_policy::<ProfileCallPolicy>(call_policy) {
+                    call_policy.update_extents(code_repo);
+                }
+
+                if dynamic {
+                    self.dynamic_mode = FirstOrNext::First;
+                }
+
+                if self.last_call {
+                    self.execute_at_index(arity, dir_entry!(p));
+                } else {
+                    self.call_at_index(arity, dir_entry!(p));
+                }
+
+                return Ok(());
+            }
+            &SystemClauseType::DebuggerCreep => {
+                let creep = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                if let Some(call_policy) = find_call_policy::<DebugCallPolicy>(call_policy) {
+                    call_policy.creep = creep.as_str() == "true";
+                }
+            }
+            &SystemClauseType::ProfileBody => {
+                // A1 = the newest choicepoint before the body of the
+                // innermost box.
+                if let Addr::Usize(b) = self.store(self.deref(self[temp_v!(1)])) {
+                    if let Some(call_policy) = find_call_policy::<ProfileCallPolicy>(call_policy) {
+                        call_policy.enter_body(b);
+                    }
+                }
+            }
+            &SystemClauseType::ProfilePort => {
+                // A1 = the port, A2 = the goal of the box, as
+                // Module:Goal.
+                let now = ProcessTime::now().as_duration();
+                let port = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                let port = match port.as_str() {
+                    "call" => ProfilePort::Call,
+                    "redo" => ProfilePort::Redo,
+                    "exit" => ProfilePort::Exit,
+                    "fail" => ProfilePort::Fail,
+                    _ => ProfilePort::Exception,
+                };
+
+                let key = match port {
+                    ProfilePort::Call | ProfilePort::Redo => {
+                        let (module_name, goal) = match self.store(self.deref(self[temp_v!(2)])) {
+                            Addr::Str(s) => (
+                                atom_from!(self, self.store(self.deref(Addr::HeapCell(s + 1)))),
+                                self.store(self.deref(Addr::HeapCell(s + 2))),
+                            ),
+                            _ => unreachable!(),
+                        };
+
+                        let key = match goal {
+                            Addr::Str(s) => match &self.heap[s] {
+                                HeapCellValue::NamedStr(arity, name, _) => (name.clone(), *arity),
+                                _ => unreachable!(),
+                            },
+                            addr => (atom_from!(self, addr), 0),
+                        };
+
+                        Some((module_name, key))
+                    }
+                    _ => None,
+                };
+
+                if let Some(call_policy) = find_call_policy::<ProfileCallPolicy>(call_policy) {
+                    match key {
+                        Some(key) => call_policy.enter(key, port, now),
+                        None => call_policy.leave(port, now),
+                    }
+                }
+            }
             &SystemClauseType::PopCount => {
                 let number  = self.store(self.deref(self[temp_v!(1)]));
                 let count = match Number::try_from((number, &self.heap)) {
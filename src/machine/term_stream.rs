@@ -1,6 +1,7 @@
 use prolog_parser::ast::*;
 use prolog_parser::parser::*;
 
+use crate::machine::code_repo::ClauseLocation;
 use crate::machine::machine_errors::CompilationError;
 use crate::machine::*;
 
@@ -15,6 +16,7 @@ pub(crate) trait TermStream: Sized {
     fn next(&mut self, op_dir: &CompositeOpDir) -> Result<Term, CompilationError>;
     fn eof(&mut self) -> Result<bool, CompilationError>;
     fn listing_src(&self) -> &ListingSource;
+    fn location(&self, wam: &Machine) -> Option<ClauseLocation>;
     fn evacuate<'a>(loader: Loader<'a, Self>) -> Result<Self::Evacuable, SessionError>;
 }
 
@@ -62,6 +64,13 @@ impl<'a> TermStream for BootstrappingTermStream<'a> {
         &self.listing_src
     }
 
+    // the locations of libraries compiled while bootstrapping aren't
+    // recorded, since they have no load context.
+    #[inline]
+    fn location(&self, _: &Machine) -> Option<ClauseLocation> {
+        None
+    }
+
     fn evacuate(mut loader: Loader<Self>) -> Result<Self::Evacuable, SessionError> {
         if !loader.predicates.is_empty() {
             loader.compile_and_submit()?;
@@ -141,6 +150,28 @@ impl TermStream for LiveTermStream {
         &self.listing_src
     }
 
+    // the file, line and column loader.pl read the term from, as
+    // set by '$set_clause_location'/0. The file is that of the load
+    // context, which is an included file while it is read, and the
+    // libraries, loaded from /lib/Name, are located in src/lib/Name.pl
+    // of the source tree.
+    fn location(&self, wam: &Machine) -> Option<ClauseLocation> {
+        use crate::LIBRARIES;
+        use ref_thread_local::RefThreadLocal;
+
+        let load_context = wam.load_contexts.last()?;
+        let (line, column) = load_context.location?;
+        let path_str = load_context.path.to_str()?;
+
+        let file = match path_str.strip_prefix("/lib/") {
+            Some(name) if LIBRARIES.borrow().contains_key(name) => format!("src/lib/{}.pl", name),
+            _ if path_str.is_empty() => return None,
+            _ => path_str.to_string(),
+        };
+
+        Some((clause_name!(file, wam.machine_st.atom_tbl), line, column))
+    }
+
     #[inline]
     fn evacuate(loader: Loader<Self>) -> Result<LoadStatePayload, SessionError> {
         Ok(loader.to_load_state_payload())
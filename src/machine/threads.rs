@@ -0,0 +1,750 @@
+use prolog_parser::ast::*;
+use prolog_parser::{clause_name, temp_v};
+
+use crate::machine::attributed_variables::*;
+use crate::machine::copier::*;
+use crate::machine::machine_indices::*;
+use crate::machine::*;
+
+use indexmap::IndexMap;
+
+use std::collections::VecDeque;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+
+// the number of predicate calls a thread makes before it's preempted.
+pub(crate) const TIME_SLICE: usize = 10_000;
+
+/* Prolog threads are scheduled cooperatively on the OS thread that
+ * runs the Machine. Each thread owns a MachineState, its policies and
+ * its global variables, all of which are swapped into the Machine
+ * when it's scheduled. Compiled code, modules, streams and the atom
+ * table are shared between threads.
+ *
+ * A thread about to read from a socket that has no input is parked
+ * on the socket's descriptor. The scheduler polls the descriptors of
+ * parked threads whenever it switches threads, and when no thread is
+ * runnable, it waits in poll(2) for one of them to become readable. */
+
+#[derive(Debug)]
+pub(crate) struct ThreadState {
+    machine_st: MachineState,
+    policies: MachinePolicies,
+    global_variables: GlobalVarDir,
+}
+
+// the event a blocked thread waits on before it can be rescheduled.
+#[derive(Debug)]
+enum Wait {
+    Join(usize),
+    Message,
+    Mutex(ClauseName),
+    #[cfg(unix)]
+    Readable(RawFd),
+}
+
+#[derive(Debug)]
+pub(crate) struct PrologThread {
+    alias: Option<ClauseName>,
+    state: Option<ThreadState>, // the saved state of a parked thread.
+    status: Option<Ball>,       // the exit status of a finished thread.
+    queue: VecDeque<Ball>,
+    waiting: Option<Wait>,
+    detached: bool, // a detached thread is forgotten once it finishes.
+}
+
+impl PrologThread {
+    fn new(alias: Option<ClauseName>, state: Option<ThreadState>, detached: bool) -> Self {
+        PrologThread {
+            alias,
+            state,
+            status: None,
+            queue: VecDeque::new(),
+            waiting: None,
+            detached,
+        }
+    }
+
+    #[inline]
+    fn is_runnable(&self) -> bool {
+        self.state.is_some() && self.waiting.is_none()
+    }
+}
+
+#[derive(Debug)]
+struct PrologMutex {
+    owner: usize,
+    count: usize,
+    waiters: VecDeque<usize>, // the threads blocked on the mutex, in arrival order.
+}
+
+#[derive(Debug)]
+pub(crate) struct ThreadTable {
+    threads: IndexMap<usize, PrologThread>,
+    mutexes: IndexMap<ClauseName, PrologMutex>,
+    current: usize,
+    next_id: usize,
+}
+
+impl ThreadTable {
+    pub(super) fn new() -> Self {
+        let mut threads = IndexMap::new();
+        threads.insert(
+            0,
+            PrologThread::new(Some(clause_name!("main")), None, false),
+        );
+
+        ThreadTable {
+            threads,
+            mutexes: IndexMap::new(),
+            current: 0,
+            next_id: 1,
+        }
+    }
+
+    fn thread_id(&self, machine_st: &MachineState, addr: Addr) -> Option<usize> {
+        match machine_st.store(machine_st.deref(addr)) {
+            Addr::Fixnum(n) if n >= 0 => {
+                if self.threads.contains_key(&(n as usize)) {
+                    Some(n as usize)
+                } else {
+                    None
+                }
+            }
+            addr @ Addr::Con(_) | addr @ Addr::Char(_) => {
+                let name = atom_from!(machine_st, addr);
+
+                self.threads.iter().find_map(|(&id, thread)| {
+                    if thread.alias.as_ref() == Some(&name) {
+                        Some(id)
+                    } else {
+                        None
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn thread_id_addr(&self, machine_st: &mut MachineState, id: usize) -> Addr {
+        match self
+            .threads
+            .get(&id)
+            .and_then(|thread| thread.alias.clone())
+        {
+            Some(alias) => Addr::Con(machine_st.heap.push(HeapCellValue::Atom(alias, None))),
+            None => Addr::Fixnum(id as isize),
+        }
+    }
+
+    #[inline]
+    fn has_runnable_threads(&self) -> bool {
+        self.threads.values().any(PrologThread::is_runnable)
+    }
+
+    #[cfg(unix)]
+    fn has_readers(&self) -> bool {
+        self.threads
+            .values()
+            .any(|thread| matches!(thread.waiting, Some(Wait::Readable(_))))
+    }
+
+    #[cfg(not(unix))]
+    fn has_readers(&self) -> bool {
+        false
+    }
+
+    // a thread that isn't runnable now may become so once its input arrives.
+    #[inline]
+    fn has_live_threads(&self) -> bool {
+        self.has_runnable_threads() || self.has_readers()
+    }
+
+    #[inline]
+    fn time_slice(&self) -> usize {
+        if self.has_live_threads() {
+            TIME_SLICE
+        } else {
+            0
+        }
+    }
+
+    /* Wake the threads whose descriptors have become readable. If
+     * block is true, wait until at least one of them has. */
+    #[cfg(unix)]
+    fn poll_readers(&mut self, block: bool) {
+        let (ids, mut fds): (Vec<_>, Vec<_>) = self
+            .threads
+            .iter()
+            .filter_map(|(&id, thread)| match thread.waiting {
+                Some(Wait::Readable(fd)) => Some((
+                    id,
+                    libc::pollfd {
+                        fd,
+                        events: libc::POLLIN,
+                        revents: 0,
+                    },
+                )),
+                _ => None,
+            })
+            .unzip();
+
+        if fds.is_empty() {
+            return;
+        }
+
+        let timeout = if block { -1 } else { 0 };
+
+        let result = loop {
+            let result =
+                unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+
+            if result >= 0
+                || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted
+            {
+                break result;
+            }
+        };
+
+        for (id, pollfd) in ids.into_iter().zip(fds) {
+            // if poll itself failed, the readers find out why from their reads.
+            if result < 0 || pollfd.revents != 0 {
+                self.wake(id, false);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn poll_readers(&mut self, _block: bool) {}
+
+    /* Make a blocked thread runnable again. A deadlocked thread is
+     * woken to fail the call it blocked in. */
+    fn wake(&mut self, id: usize, deadlocked: bool) {
+        let thread = match self.threads.get_mut(&id) {
+            Some(thread) => thread,
+            None => return,
+        };
+
+        if let Some(Wait::Mutex(name)) = thread.waiting.take() {
+            if let Some(mutex) = self.mutexes.get_mut(&name) {
+                mutex.waiters.retain(|&waiter| waiter != id);
+            }
+        }
+
+        if deadlocked {
+            if let Some(state) = thread.state.as_mut() {
+                state.machine_st.fail = true;
+            }
+        }
+    }
+
+    // hand a released mutex to the thread that's waited on it longest.
+    fn release_mutex(&mut self, name: &ClauseName) {
+        let waiter = match self.mutexes.get_mut(name) {
+            Some(mutex) => mutex.waiters.pop_front(),
+            None => return,
+        };
+
+        match waiter {
+            Some(waiter) => {
+                let mutex = self.mutexes.get_mut(name).unwrap();
+
+                mutex.owner = waiter;
+                mutex.count = 1;
+
+                self.wake(waiter, false);
+            }
+            None => {
+                self.mutexes.shift_remove(name);
+            }
+        }
+    }
+}
+
+fn copy_to_ball(machine_st: &mut MachineState, r: RegType) -> Ball {
+    let addr = machine_st[r];
+    let mut ball = Ball::new();
+    ball.boundary = machine_st.heap.h();
+
+    copy_term(
+        CopyBallTerm::new(&mut machine_st.stack, &mut machine_st.heap, &mut ball.stub),
+        addr,
+        AttrVarPolicy::DeepCopy,
+    );
+
+    ball
+}
+
+fn ball_to_heap(machine_st: &mut MachineState, ball: &Ball) -> Addr {
+    let h = machine_st.heap.h();
+    let stub = ball.copy_and_align(h);
+
+    machine_st.heap.extend(stub.into_iter());
+    Addr::HeapCell(h)
+}
+
+impl Machine {
+    /* Schedule the next runnable thread after the current one,
+     * parking the current thread if park_current is true and
+     * discarding its state otherwise. Returns false if there is no
+     * other thread to run. */
+    fn switch_thread(&mut self, park_current: bool) -> bool {
+        let current = self.threads.current;
+        let current_idx = self.threads.threads.get_index_of(&current).unwrap();
+        let num_threads = self.threads.threads.len();
+
+        self.threads.poll_readers(false);
+
+        let next_idx = loop {
+            let next = (1..num_threads)
+                .map(|offset| (current_idx + offset) % num_threads)
+                .find(|&idx| self.threads.threads[idx].is_runnable());
+
+            if let Some(next_idx) = next {
+                break next_idx;
+            }
+
+            let current_blocked =
+                !park_current || self.threads.threads[current_idx].waiting.is_some();
+
+            // wait for input only if the current thread can't go on.
+            if !current_blocked || !self.threads.has_readers() {
+                self.machine_st.time_slice = self.threads.time_slice();
+                return false;
+            }
+
+            self.threads.poll_readers(true);
+        };
+
+        let next_state = self.threads.threads[next_idx].state.take().unwrap();
+        let global_clock = self.machine_st.global_clock;
+
+        let prev_state = ThreadState {
+            machine_st: mem::replace(&mut self.machine_st, next_state.machine_st),
+            policies: mem::replace(&mut self.policies, next_state.policies),
+            global_variables: mem::replace(
+                &mut self.indices.global_variables,
+                next_state.global_variables,
+            ),
+        };
+
+        // the dynamic database is shared, and so is its clock.
+        self.machine_st.global_clock = global_clock;
+
+        if park_current {
+            self.threads.threads[current_idx].state = Some(prev_state);
+        }
+
+        self.threads.current = *self.threads.threads.get_index(next_idx).unwrap().0;
+        self.machine_st.time_slice = self.threads.time_slice();
+
+        true
+    }
+
+    /* Park the current thread until the event it waits on wakes it.
+     * Fails without blocking if no other thread could ever wake it. */
+    fn block_current(&mut self, wait: Wait) {
+        let current = self.threads.current;
+
+        if !self.threads.has_live_threads() {
+            self.machine_st.fail = true;
+            return;
+        }
+
+        if let Wait::Mutex(ref name) = wait {
+            if let Some(mutex) = self.threads.mutexes.get_mut(name) {
+                mutex.waiters.push_back(current);
+            }
+        }
+
+        self.threads.threads[&current].waiting = Some(wait);
+        self.switch_thread(true);
+    }
+
+    pub(crate) fn thread_create(&mut self) {
+        let alias = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(2)]))
+        {
+            addr @ Addr::Con(_) | addr @ Addr::Char(_) => {
+                let alias = atom_from!(self.machine_st, addr);
+
+                let in_use = self
+                    .threads
+                    .threads
+                    .values()
+                    .any(|thread| thread.alias.as_ref() == Some(&alias));
+
+                if in_use {
+                    self.machine_st.fail = true;
+                    return;
+                }
+
+                Some(alias)
+            }
+            _ => None,
+        };
+
+        let detached = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(3)]))
+        );
+
+        let p = match self.indices.modules.get(&clause_name!("threads")) {
+            Some(module) => match module.code_dir.get(&(clause_name!("$thread_start"), 1)) {
+                Some(code_index) => code_index.local().unwrap(),
+                None => unreachable!(),
+            },
+            None => unreachable!(),
+        };
+
+        let goal = copy_to_ball(&mut self.machine_st, temp_v!(1));
+        let mut machine_st = MachineState::new();
+
+        machine_st.atom_tbl = self.machine_st.atom_tbl.clone();
+        machine_st.flags = self.machine_st.flags;
+        machine_st.global_clock = self.machine_st.global_clock;
+        machine_st.unify_fn = self.machine_st.unify_fn;
+        machine_st.bind_fn = self.machine_st.bind_fn;
+        machine_st.heap_limit = self.machine_st.heap_limit;
+        machine_st.stack_limit = self.machine_st.stack_limit;
+        machine_st.autoload = self.machine_st.autoload;
+        machine_st.attr_var_init =
+            AttrVarInitializer::new(self.machine_st.attr_var_init.verify_attrs_loc);
+
+        machine_st[temp_v!(1)] = ball_to_heap(&mut machine_st, &goal);
+        machine_st.num_of_args = 1;
+        machine_st.cp = LocalCodePtr::Halt;
+        machine_st.p = CodePtr::Local(LocalCodePtr::DirEntry(p));
+        machine_st.time_slice = TIME_SLICE;
+
+        let state = ThreadState {
+            machine_st,
+            policies: MachinePolicies::new(),
+            global_variables: GlobalVarDir::new(),
+        };
+
+        let id = self.threads.next_id;
+
+        self.threads.next_id += 1;
+        self.threads.threads.insert(
+            id,
+            PrologThread::new(alias, Some(state), detached.as_str() == "true"),
+        );
+
+        self.machine_st.time_slice = TIME_SLICE;
+
+        let id_addr = self.threads.thread_id_addr(&mut self.machine_st, id);
+        self.machine_st.unify(id_addr, self.machine_st[temp_v!(4)]);
+    }
+
+    pub(crate) fn thread_finish(&mut self) {
+        let current = self.threads.current;
+
+        if current == 0 {
+            self.machine_st.fail = true;
+            return;
+        }
+
+        let status = copy_to_ball(&mut self.machine_st, temp_v!(1));
+
+        if let Some(thread) = self.threads.threads.get_mut(&current) {
+            thread.status = Some(status);
+        }
+
+        let owned: Vec<_> = self
+            .threads
+            .mutexes
+            .iter()
+            .filter(|(_, mutex)| mutex.owner == current)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in owned {
+            self.threads.release_mutex(&name);
+        }
+
+        let joiners: Vec<_> = self
+            .threads
+            .threads
+            .iter()
+            .filter(|(_, thread)| matches!(thread.waiting, Some(Wait::Join(id)) if id == current))
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in joiners {
+            self.threads.wake(id, false);
+        }
+
+        /* the main thread never finishes, so if no other thread can
+         * run, it's blocked on a wait that will never end. */
+        if !self.threads.has_live_threads() {
+            self.threads.wake(0, true);
+        }
+
+        self.switch_thread(false);
+
+        if matches!(self.threads.threads.get(&current), Some(thread) if thread.detached) {
+            self.threads.threads.shift_remove(&current);
+        }
+    }
+
+    pub(crate) fn thread_join(&mut self) {
+        let id = match self
+            .threads
+            .thread_id(&self.machine_st, self.machine_st[temp_v!(1)])
+        {
+            Some(id) if id != self.threads.current => id,
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        let status = match self.threads.threads.get(&id) {
+            Some(PrologThread {
+                status: Some(_), ..
+            }) => {
+                let thread = self.threads.threads.shift_remove(&id).unwrap();
+                ball_to_heap(&mut self.machine_st, thread.status.as_ref().unwrap())
+            }
+            _ => {
+                let h = self.machine_st.heap.h();
+
+                self.machine_st
+                    .heap
+                    .push(HeapCellValue::Atom(clause_name!("$running"), None));
+
+                Addr::Con(h)
+            }
+        };
+
+        self.machine_st.unify(status, self.machine_st[temp_v!(2)]);
+    }
+
+    pub(crate) fn thread_wait_join(&mut self) {
+        let id = match self
+            .threads
+            .thread_id(&self.machine_st, self.machine_st[temp_v!(1)])
+        {
+            Some(id) if id != self.threads.current => id,
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        if self.threads.threads[&id].status.is_none() {
+            self.block_current(Wait::Join(id));
+        }
+    }
+
+    pub(crate) fn thread_self(&mut self) {
+        let id_addr = self
+            .threads
+            .thread_id_addr(&mut self.machine_st, self.threads.current);
+
+        self.machine_st.unify(id_addr, self.machine_st[temp_v!(1)]);
+    }
+
+    pub(crate) fn thread_send_message(&mut self) {
+        let id = match self
+            .threads
+            .thread_id(&self.machine_st, self.machine_st[temp_v!(1)])
+        {
+            Some(id) => id,
+            None => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        let message = copy_to_ball(&mut self.machine_st, temp_v!(2));
+
+        if let Some(thread) = self.threads.threads.get_mut(&id) {
+            thread.queue.push_back(message);
+
+            if let Some(Wait::Message) = thread.waiting {
+                self.threads.wake(id, false);
+                self.machine_st.time_slice = TIME_SLICE;
+            }
+        }
+    }
+
+    // copy out the message at the given index of the current thread's queue.
+    pub(crate) fn thread_peek_message(&mut self) {
+        let current = self.threads.current;
+
+        let message = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            Addr::Fixnum(n) if n >= 0 => self.threads.threads[&current].queue.get(n as usize),
+            _ => None,
+        };
+
+        match message {
+            Some(message) => {
+                let message = ball_to_heap(&mut self.machine_st, message);
+                self.machine_st.unify(message, self.machine_st[temp_v!(2)]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    pub(crate) fn thread_remove_message(&mut self) {
+        let current = self.threads.current;
+
+        match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            Addr::Fixnum(n) if n >= 0 => {
+                if let Some(thread) = self.threads.threads.get_mut(&current) {
+                    thread.queue.remove(n as usize);
+                }
+            }
+            _ => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    // block until the current thread's queue holds more than the given number of messages.
+    pub(crate) fn thread_wait_message(&mut self) {
+        let current = self.threads.current;
+
+        match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            Addr::Fixnum(n) if n >= 0 => {
+                if self.threads.threads[&current].queue.len() <= n as usize {
+                    self.block_current(Wait::Message);
+                }
+            }
+            _ => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    /* Park the current thread until the socket or stream it's about to
+     * read from has input, if other threads could run in the meantime.
+     * Streams without a descriptor to wait on are read right away. */
+    #[cfg(unix)]
+    pub(crate) fn thread_wait_readable(&mut self) {
+        if !self.threads.has_live_threads() {
+            return;
+        }
+
+        let fd = match self
+            .machine_st
+            .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        {
+            Addr::TcpListener(h) => match &self.machine_st.heap[h] {
+                HeapCellValue::TcpListener(ref tcp_listener) => Some(tcp_listener.as_raw_fd()),
+                _ => None,
+            },
+            Addr::Stream(h) => match &self.machine_st.heap[h] {
+                HeapCellValue::Stream(ref stream) => stream.input_fd(),
+                _ => None,
+            },
+            Addr::Con(h) if self.machine_st.heap.atom_at(h) => match &self.machine_st.heap[h] {
+                HeapCellValue::Atom(ref alias, _) => self
+                    .indices
+                    .stream_aliases
+                    .get(alias)
+                    .and_then(Stream::input_fd),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let fd = match fd {
+            Some(fd) => fd,
+            None => return,
+        };
+
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        if unsafe { libc::poll(&mut pollfd, 1, 0) } == 0 {
+            let current = self.threads.current;
+
+            self.threads.threads[&current].waiting = Some(Wait::Readable(fd));
+            self.switch_thread(true);
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn thread_wait_readable(&mut self) {}
+
+    pub(crate) fn thread_preempt(&mut self) {
+        // loading a file isn't reentrant, so wait until it's done.
+        if !self.load_contexts.is_empty() {
+            self.machine_st.time_slice = TIME_SLICE;
+        } else {
+            self.switch_thread(true);
+        }
+    }
+
+    pub(crate) fn mutex_lock(&mut self) {
+        let name = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        );
+
+        let current = self.threads.current;
+
+        match self.threads.mutexes.get_mut(&name) {
+            Some(mutex) if mutex.owner == current => {
+                mutex.count += 1;
+            }
+            Some(_) => {
+                // the owner hands the mutex over on release.
+                self.block_current(Wait::Mutex(name));
+            }
+            None => {
+                let mutex = PrologMutex {
+                    owner: current,
+                    count: 1,
+                    waiters: VecDeque::new(),
+                };
+
+                self.threads.mutexes.insert(name, mutex);
+            }
+        }
+    }
+
+    pub(crate) fn mutex_unlock(&mut self) {
+        let name = atom_from!(
+            self.machine_st,
+            self.machine_st
+                .store(self.machine_st.deref(self.machine_st[temp_v!(1)]))
+        );
+
+        let current = self.threads.current;
+
+        match self.threads.mutexes.get_mut(&name) {
+            Some(mutex) if mutex.owner == current => {
+                mutex.count -= 1;
+
+                if mutex.count == 0 {
+                    self.threads.release_mutex(&name);
+                    self.machine_st.time_slice = self.threads.time_slice();
+                }
+            }
+            _ => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+}
@@ -184,7 +184,7 @@ impl MachineState {
 
         parser.devour_whitespace()?;
 
-        inner.add_lines_read(parser.num_lines_read());
+        inner.add_chars_read(parser.num_lines_read(), parser.col_num());
 
         let result = parser.eof();
         let buf = stream.take_buf();
@@ -202,17 +202,22 @@ impl MachineState {
     ) -> Result<TermWriteResult, ParserError> {
         let mut stream = parsing_stream(inner.clone())?;
 
-        let (term, num_lines_read) = {
+        let (term, num_lines_read, col_num) = {
             let prior_num_lines_read = inner.lines_read();
             let mut parser = Parser::new(&mut stream, atom_tbl, self.flags);
 
             parser.add_lines_read(prior_num_lines_read);
 
             let term = parser.read_term(&CompositeOpDir::new(op_dir, None))?;
-            (term, parser.num_lines_read() - prior_num_lines_read)
+
+            (
+                term,
+                parser.num_lines_read() - prior_num_lines_read,
+                parser.col_num(),
+            )
         };
 
-        inner.add_lines_read(num_lines_read);
+        inner.add_chars_read(num_lines_read, col_num);
 
         // 'pausing' the stream saves the pending top buffer
         // created by the parsing stream, which was created in this
@@ -0,0 +1,53 @@
+:- module(autoload_tests, []).
+
+test_compiled_calls :-
+    append([a], [b], Xs),
+    Xs == [a, b],
+    sum_list([1, 2, 3], Sum),
+    Sum == 6.
+
+test_dynamic_calls :-
+    Goal = reverse([a, b]),
+    call(Goal, Ys),
+    Ys == [b, a],
+    autoload_tests:nth0(0, [z], Z),
+    Z == z.
+
+test_autoload_flag :-
+    set_prolog_flag(autoload, false),
+    catch((list_max([1, 2], _), false),
+          error(existence_error(procedure, list_max/2), _),
+          true),
+    current_prolog_flag(autoload, false),
+    set_prolog_flag(autoload, true),
+    list_max([1, 2], Max),
+    Max == 2.
+
+test_meta_arguments :-
+    call_with_inference_limit(local_goal(X), 100000, R),
+    X == local,
+    R == !.
+
+local_goal(local).
+
+test_undefined_export :-
+    catch(loader:'$autoload'(autoload_tests, no_such_predicate(a), lists),
+          error(existence_error(procedure, no_such_predicate/1), _),
+          true).
+
+test_computed_indicators :-
+    functor(list_min(_, _), Name, Arity),
+    use_module(library(lists), [Name/Arity]),
+    predicate_property(user:list_min(_, _), number_of_clauses(_)).
+
+run_tests :-
+    test_compiled_calls,
+    test_dynamic_calls,
+    test_autoload_flag,
+    test_meta_arguments,
+    test_undefined_export,
+    test_computed_indicators,
+    write('autoload tests passed'),
+    nl.
+
+:- initialization(run_tests).
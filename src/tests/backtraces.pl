@@ -0,0 +1,38 @@
+:- module(backtrace_tests, []).
+
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+p(X) :- q(X), true.
+q(X) :- r(X), true.
+r(X) :- atom_length(X, _), true.
+
+s(X) :- setup_call_cleanup(true, p(X), true).
+
+test_backtrace :-
+    catch_with_backtrace(p(1), error(Formal, backtrace(Context, Frames)), true),
+    Formal == type_error(atom, 1),
+    Context == atom_length/2,
+    append(_, [frame(backtrace_tests:r/1, 1, File:8),
+               frame(backtrace_tests:q/1, 1, File:7),
+               frame(backtrace_tests:p/1, 1, File:6) | _], Frames),
+    atom_concat(_, 'backtraces.pl', File).
+
+test_rethrown_backtrace :-
+    catch_with_backtrace(s(1), error(_, backtrace(_, Frames)), true),
+    memberchk(frame(backtrace_tests:r/1, 1, _), Frames).
+
+test_unmatched_balls :-
+    catch(catch_with_backtrace(p(1), error(foo, _), true), E, true),
+    E == error(type_error(atom, 1), atom_length/2),
+    catch_with_backtrace(throw(ball), B, true),
+    B == ball.
+
+run_tests :-
+    test_backtrace,
+    test_rethrown_backtrace,
+    test_unmatched_balls,
+    write('backtrace tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,67 @@
+:- module(clause_property_tests, []).
+
+:- use_module(library(between)).
+:- use_module(library(lists)).
+
+:- discontiguous(d/1).
+:- dynamic(e/1).
+
+p(1).
+  p(2) :- true.
+p(3). p(4).
+
+d(a).
+q.
+d(b).
+
+e(1).
+
+clause_location(Head, N, Line, Column) :-
+    nth_clause(Head, N, Ref),
+    clause_property(Ref, line_count(Line)),
+    clause_property(Ref, line_position(Column)).
+
+test_clause_properties :-
+    findall(N-Line-Column, clause_location(p(_), N, Line, Column), Locations),
+    Locations == [1-9-0, 2-10-2, 3-11-0, 4-11-6],
+    nth_clause(p(_), 1, Ref),
+    clause_property(Ref, file(File)),
+    atom_concat(_, 'clause_properties.pl', File),
+    findall(N-Line, clause_location(d(_), N, Line, _), DLocations),
+    DLocations == [1-13, 2-15].
+
+test_predicate_properties :-
+    predicate_property(p(_), number_of_clauses(4)),
+    predicate_property(p(_), line_count(9)),
+    predicate_property(p(_), file(File)),
+    atom_concat(_, 'clause_properties.pl', File),
+    predicate_property(q, number_of_clauses(1)),
+    predicate_property(between:numlist(_, _, _), file('src/lib/between.pl')),
+    predicate_property(append(_, _, _), number_of_clauses(2)),
+    \+ predicate_property(undefined, number_of_clauses(_)).
+
+test_dynamic_clauses :-
+    assertz(e(2)),
+    predicate_property(e(_), number_of_clauses(2)),
+    nth_clause(e(_), 2, Ref),
+    \+ clause_property(Ref, file(_)),
+    retract(e(1)),
+    predicate_property(e(_), number_of_clauses(1)),
+    \+ predicate_property(e(_), line_count(_)).
+
+test_errors :-
+    catch(clause_property(_, _), error(instantiation_error, _), true),
+    catch(clause_property(foo, _), error(domain_error(clause_reference, foo), _), true),
+    catch(nth_clause(_, _, _), error(instantiation_error, _), true),
+    catch(nth_clause(p(_), a, _), error(type_error(integer, a), _), true),
+    \+ nth_clause(p(_), 0, _).
+
+run_tests :-
+    test_clause_properties,
+    test_predicate_properties,
+    test_dynamic_clauses,
+    test_errors,
+    write('clause property tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,67 @@
+:- module(conditional_compilation_tests, []).
+
+:- use_module(library(lists)).
+
+:- if(true).
+a(1).
+:- if(fail).
+a(2).
+:- elif(true).
+a(3).
+:- else.
+a(4).
+:- endif.
+:- elif(true).
+a(5).
+:- else.
+a(6).
+:- endif.
+
+:- if(fail).
+b(1).
+:- if(true).
+b(2).
+:- else.
+b(3).
+:- endif.
+:- elif(fail).
+b(4).
+:- elif(current_prolog_flag(bounded, false)).
+b(5).
+:- else.
+b(6).
+:- endif.
+
+:- if(\+ catch(member(_, []), _, true)).
+c(1).
+:- else.
+c(2).
+:- endif.
+
+user:term_expansion(d(skipped), _) :-
+    throw(expanded).
+
+:- if(fail).
+d(skipped).
+:- endif.
+d(compiled).
+
+test_branches :-
+    findall(X, a(X), As),
+    As == [1, 3],
+    findall(X, b(X), Bs),
+    Bs == [5],
+    findall(X, c(X), Cs),
+    Cs == [1].
+
+test_skipped_terms :-
+    findall(X, d(X), Ds),
+    Ds == [compiled].
+
+run_tests :-
+    test_branches,
+    test_skipped_terms,
+    write('conditional compilation tests passed'),
+    nl.
+
+:- initialization(run_tests).
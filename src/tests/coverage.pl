@@ -0,0 +1,86 @@
+:- module(coverage_tests, []).
+
+:- use_module(library(between)).
+:- use_module(library(charsio)).
+:- use_module(library(cover)).
+:- use_module(library(files)).
+:- use_module(library(lists)).
+
+p(X) :- q(X), r(X).
+p(_) :- fail.
+
+q(1).
+q(2).
+q(3) :- true.
+
+r(1).
+r(2) :-
+    q(2),
+    s.
+
+s.
+
+clause_coverage(Data, PI, Line, Count, GoalsRun, Goals) :-
+    member(clause(_, Line, coverage_tests:PI, Count, GoalsRun, Goals), Data).
+
+test_clauses :-
+    cover(p(2), [report(false)]),
+    coverage_data(Data),
+    clause_coverage(Data, p/1, 9, 1, 2, 2),
+    clause_coverage(Data, p/1, 10, 0, 0, 1),
+    clause_coverage(Data, q/1, 12, 0, 0, 0),
+    clause_coverage(Data, q/1, 13, 2, 0, 0),
+    clause_coverage(Data, q/1, 14, 0, 0, 1),
+    clause_coverage(Data, r/1, 16, 0, 0, 0),
+    clause_coverage(Data, r/1, 17, 1, 2, 2),
+    clause_coverage(Data, s/0, 21, 1, 0, 0).
+
+test_failure :-
+    \+ cover(p(4), [report(false)]),
+    coverage_data(Data),
+    clause_coverage(Data, p/1, 9, 1, 1, 2),
+    clause_coverage(Data, p/1, 10, 1, 1, 1),
+    clause_coverage(Data, r/1, 17, 0, 0, 2).
+
+test_libraries :-
+    cover(numlist(1, 3, _), [report(false)]),
+    coverage_data(Data),
+    member(clause('src/lib/between.pl', _, between:numlist/3, Count, _, _), Data),
+    Count > 0,
+    nth_clause(between:numlist(_, _, _), 1, Ref),
+    clause_property(Ref, file('src/lib/between.pl')).
+
+% the source files of lcov records are the files of clause_property/2.
+test_lcov :-
+    File = 'coverage_tests.info',
+    cover(numlist(1, 3, _), [report(false), files([library(between)]), lcov(File)]),
+    setup_call_cleanup(open(File, read, Stream),
+                       (  read_line_to_chars(Stream, _, []),
+                          read_line_to_chars(Stream, SourceFile, [])
+                       ),
+                       close(Stream)),
+    atom_chars(File, FileChars),
+    delete_file(FileChars),
+    SourceFile == "SF:src/lib/between.pl\n".
+
+test_options :-
+    catch(cover(true, [lcov(_)]),
+          error(instantiation_error, _),
+          true),
+    catch(cover(true, [files(foo)]),
+          error(type_error(list, foo), _),
+          true),
+    catch(cover(true, [verbose]),
+          error(domain_error(cover_option, verbose), _),
+          true).
+
+run_tests :-
+    test_clauses,
+    test_failure,
+    test_libraries,
+    test_lcov,
+    test_options,
+    write('coverage tests passed'),
+    nl.
+
+:- initialization(run_tests).
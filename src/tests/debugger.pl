@@ -0,0 +1,40 @@
+:- module(debugger_tests, []).
+
+:- use_module(library(debug)).
+
+p(X) :- q(X), r(X).
+
+q(1).
+q(2).
+
+r(2).
+
+s(X) :- X > 0, throw(ball).
+
+test_trace :-
+    leash(none),
+    trace,
+    p(2),
+    nodebug.
+
+test_exception :-
+    trace,
+    catch(s(1), ball, true),
+    nodebug.
+
+test_spy :-
+    spy(r/1),
+    \+ p(1),
+    debugging,
+    nospyall,
+    nodebug,
+    debugging.
+
+run_tests :-
+    test_trace,
+    test_exception,
+    test_spy,
+    write('debugger tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,50 @@
+:- module(debugger_command_tests, []).
+
+:- use_module(library(debug)).
+
+% the commands are read from user_input, one character for each
+% leashed port.
+
+t :- b, c(2).
+
+b.
+b.
+
+c(1).
+
+% c creeps through every port, including the redo port of b/0, and n
+% turns off the debugger once \+/1 succeeds.
+test_creep :-
+    trace,
+    \+ t,
+    nodebug.
+
+% s skips over the ports inside t/0 to its fail port.
+test_skip :-
+    trace,
+    \+ t,
+    nodebug.
+
+% l leaps from t/0 to the ports of the spy point on c/1.
+test_leap :-
+    spy(c/1),
+    trace,
+    \+ t,
+    nospyall,
+    nodebug.
+
+% r retries c/1 at its fail port, and f fails b/0 at its redo port.
+test_retry_and_fail :-
+    trace,
+    \+ t,
+    nodebug.
+
+run_tests :-
+    test_creep,
+    test_skip,
+    test_leap,
+    test_retry_and_fail,
+    write('debugger command tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,17 @@
+:- module(diag_tests, []).
+
+:- use_module(library(diag)).
+:- use_module(library(lists)).
+
+% calls to system predicates are listed under the names they're
+% called by.
+test_system_predicate_names :-
+    wam_instructions(iso_ext:bb_b_put/2, Listing),
+    memberchk(execute('$store_backtrackable_global_var', 2), Listing).
+
+run_tests :-
+    test_system_predicate_names,
+    write('diag tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,193 @@
+:- module(garbage_collection_tests, []).
+
+:- use_module(library(between)).
+:- use_module(library(dif)).
+:- use_module(library(freeze)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+% churn allocates garbage until the heap is collected at a call port
+% within it, or fails if it isn't collected after 100000 rounds.
+churn :-
+    statistics(heapused, Bytes),
+    churn(Bytes, 100000).
+
+churn(Bytes0, N) :-
+    N > 0,
+    functor(_, f, 1000),
+    statistics(heapused, Bytes),
+    (  Bytes < Bytes0 ->
+       true
+    ;  N1 is N - 1,
+       churn(Bytes, N1)
+    ).
+
+make_atoms(0) :- !.
+make_atoms(N) :-
+    number_codes(N, Cs),
+    atom_codes(_, [0'a|Cs]),
+    N1 is N - 1,
+    make_atoms(N1).
+
+test_explicit_collection :-
+    X = f(Y, "a partial string", [1,2,3|T], Y),
+    length(Garbage, 1000),
+    maplist(=(g), Garbage),
+    garbage_collect,
+    X == f(Y, "a partial string", [1,2,3|T], Y),
+    Y = 1,
+    T = [],
+    X == f(1, "a partial string", [1,2,3], 1).
+
+test_choice_points_survive :-
+    findall(X-L, (member(X, [a,b,c]),
+                  length(L, 2),
+                  garbage_collect,
+                  L = [X,X]),
+            Solutions),
+    Solutions == [a-[a,a], b-[b,b], c-[c,c]].
+
+test_global_variables :-
+    bb_put(gc_test_key, h(W, "xyz", W)),
+    length(Garbage, 1000),
+    maplist(=(g), Garbage),
+    garbage_collect,
+    bb_get(gc_test_key, Value),
+    Value = h(A, S, B),
+    A == B,
+    S == "xyz".
+
+test_attributed_variables :-
+    dif(X, Y),
+    freeze(Z, Z == 2),
+    garbage_collect,
+    X = a,
+    \+ Y = a,
+    Y = b,
+    Z = 2.
+
+test_automatic_collection :-
+    churn,
+    length(L, 10),
+    maplist(=(done), L),
+    L = [done|_].
+
+test_automatic_choice_points :-
+    findall(X-L, (member(X, [a,b,c]),
+                  length(L, 2),
+                  churn,
+                  L = [X,X]),
+            Solutions),
+    Solutions == [a-[a,a], b-[b,b], c-[c,c]],
+    T = t(A, B),
+    (  A = 1,
+       B = "a partial string",
+       churn,
+       fail
+    ;  T = t(C, D),
+       var(C),
+       var(D)
+    ).
+
+test_automatic_attributed_variables :-
+    dif(X, Y),
+    freeze(Z, Z == 2),
+    churn,
+    X = a,
+    \+ Y = a,
+    Y = b,
+    Z = 2,
+    freeze(W, (churn, V = woken)),
+    W = go,
+    V == woken.
+
+test_automatic_all_solutions :-
+    findall(N-S, (between(1, 3, N), churn, S = "abc"), Solutions),
+    Solutions == [1-"abc", 2-"abc", 3-"abc"],
+    bagof(X-Y, (member(X, [p,q]), churn, Y = f(X)), Pairs),
+    Pairs == [p-f(p), q-f(q)].
+
+test_automatic_balls :-
+    catch((X = b(V, "abc", V), churn, throw(X)), b(P, S, Q), true),
+    P == Q,
+    S == "abc",
+    catch(throw(c(W, "xyz", W)),
+          Ball,
+          (  churn,
+             Ball = c(A, T, B),
+             A == B,
+             T == "xyz"
+          )).
+
+test_automatic_global_variables :-
+    bb_put(gc_automatic_key, h(W, "xyz", W)),
+    bb_b_put(gc_automatic_b_key, k(V, "uvw", V)),
+    churn,
+    bb_get(gc_automatic_key, h(A, S, B)),
+    A == B,
+    S == "xyz",
+    bb_get(gc_automatic_b_key, k(C, T, D)),
+    C == D,
+    T == "uvw".
+
+% the heap is collected while the file is loaded, by the expansion of
+% the second clause of load_payload/1.
+:- discontiguous(load_payload/1).
+
+load_payload(before("a partial string", [1,2,3])).
+
+term_expansion(churned_load_payload(P), load_payload(P)) :-
+    garbage_collection_tests:churn.
+
+churned_load_payload(after(f(X, X))).
+
+test_automatic_load_payloads :-
+    findall(P, load_payload(P), Payloads),
+    Payloads = [before(S, L), after(f(A, B))],
+    S == "a partial string",
+    L == [1,2,3],
+    A == B.
+
+test_atom_collection :-
+    statistics(atoms, A0),
+    make_atoms(1000),
+    statistics(atoms, A1),
+    A1 >= A0 + 1000,
+    garbage_collect,
+    statistics(atoms, A2),
+    A2 < A1 - 900.
+
+% the alias of a stream is released when the stream is closed, though
+% the heap still refers to the stream.
+test_released_atoms :-
+    \+ \+ (  atom_chars(Alias, "gc_test_alias"),
+            open('src/tests/garbage_collection.pl', read, S, [alias(Alias)]),
+            bb_put(gc_test_stream, S)
+         ),
+    bb_get(gc_test_stream, Stream),
+    statistics(atoms, A0),
+    close(Stream),
+    statistics(atoms, A1),
+    A1 =:= A0 - 1,
+    garbage_collect_atoms,
+    statistics(atoms, A2),
+    A2 =< A1.
+
+run_tests :-
+    test_explicit_collection,
+    test_choice_points_survive,
+    test_global_variables,
+    test_attributed_variables,
+    test_automatic_collection,
+    test_automatic_choice_points,
+    test_automatic_attributed_variables,
+    test_automatic_all_solutions,
+    test_automatic_balls,
+    test_automatic_global_variables,
+    test_automatic_load_payloads,
+    test_atom_collection,
+    test_released_atoms,
+    write('garbage collection tests passed'),
+    nl.
+
+:- initialization(run_tests).
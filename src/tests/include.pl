@@ -0,0 +1,48 @@
+:- module(include_tests, []).
+
+:- use_module(library(lists)).
+
+:- dynamic(helper_loads/1).
+
+user:term_expansion(context_marker, context(Source, Module)) :-
+    prolog_load_context(source, Source),
+    prolog_load_context(module, Module).
+
+p(1).
+:- include('include/clauses.pl').
+p(3).
+
+:- include('include/context.pl').
+
+:- ensure_loaded('include/helper.pl').
+:- ensure_loaded('./include/../include/helper.pl').
+:- ensure_loaded('include/importer.pl').
+
+test_included_clauses :-
+    findall(X, p(X), Ps),
+    Ps == [1, 2, 3].
+
+test_include_context :-
+    context(Source, Module),
+    atom_concat(_, 'src/tests/include.pl', Source),
+    Module == include_tests,
+    nth_clause(p(_), 2, Ref),
+    clause_property(Ref, file(File)),
+    atom_concat(_, 'src/tests/include/clauses.pl', File).
+
+test_ensure_loaded :-
+    findall(X, helper_loads(X), Loads),
+    Loads == [helper],
+    helper_value(Value),
+    Value == 42,
+    importer:importer_value(ImportedValue),
+    ImportedValue == 42.
+
+run_tests :-
+    test_included_clauses,
+    test_include_context,
+    test_ensure_loaded,
+    write('include tests passed'),
+    nl.
+
+:- initialization(run_tests).
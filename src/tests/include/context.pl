@@ -0,0 +1 @@
+context_marker.
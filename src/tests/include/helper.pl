@@ -0,0 +1,5 @@
+:- module(helper, [helper_value/1]).
+
+:- initialization(assertz(include_tests:helper_loads(helper))).
+
+helper_value(42).
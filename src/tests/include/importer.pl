@@ -0,0 +1,5 @@
+:- module(importer, [importer_value/1]).
+
+:- ensure_loaded(helper).
+
+importer_value(Value) :- helper_value(Value).
@@ -0,0 +1,82 @@
+:- module(indexing_tests, []).
+
+:- use_module(library(diag)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+:- dynamic(colour/2).
+
+edge(a, b, 1).
+edge(b, c, 2).
+edge(c, d, 1).
+edge(d, e, 3).
+edge(e, [f], s(1)).
+edge(f, "gh", s(2)).
+
+eval(op(add, X, Y), Z) :- Z is X + Y.
+eval(op(sub, X, Y), Z) :- Z is X - Y.
+eval(op(mul, X, Y), Z) :- Z is X * Y.
+eval(num(N), N).
+
+% true iff Goal succeeds without leaving a choice point.
+deterministic(Goal) :-
+    setup_call_cleanup(true, Goal, Det = true),
+    Det == true.
+
+test_static_second_argument :-
+    findall(X, edge(X, c, _), [b]),
+    deterministic(edge(X0, c, _)),
+    X0 == b,
+    findall(X, edge(X, [_|_], _), [e, f]),
+    findall(X, edge(X, [_], _), [e]),
+    \+ edge(_, z, _).
+
+test_static_third_argument :-
+    findall(X, edge(X, _, 1), [a, c]),
+    findall(X, edge(X, _, 1), [a, c]),
+    deterministic(edge(X0, _, 3)),
+    X0 == d,
+    findall(X-N, edge(X, _, s(N)), [e-1, f-2]),
+    deterministic(edge(X1, _, s(2))),
+    X1 == f.
+
+test_dynamic_assertz :-
+    forall(member(X-C, [apple-red, banana-yellow, cherry-red]),
+           assertz(colour(X, C))),
+    findall(X, colour(X, red), [apple, cherry]),
+    deterministic(colour(X0, yellow)),
+    X0 == banana,
+    assertz(colour(lime, green)),
+    asserta(colour(tomato, red)),
+    findall(X, colour(X, red), [tomato, apple, cherry]),
+    deterministic(colour(X1, green)),
+    X1 == lime,
+    retract(colour(apple, red)),
+    findall(X, colour(X, red), [tomato, cherry]),
+    (  colour(_, red),
+       assertz(colour(strawberry, red)),
+       fail
+    ;  true
+    ),
+    findall(X, colour(X, red), [tomato, cherry, strawberry, strawberry]),
+    assertz(colour(_, blue)),
+    findall(X, colour(X, blue), [Y]),
+    var(Y).
+
+test_subterm_indexing :-
+    deterministic(eval(op(sub, 5, 3), Z)),
+    Z == 2,
+    findall(Op-Z1, eval(op(Op, 2, 3), Z1), [add-5, sub-(-1), mul-6]),
+    \+ eval(op(div, 6, 3), _),
+    wam_instructions(indexing_tests:(eval/2), Listing),
+    memberchk(switch_on_subterm(1, _, _, _, _), Listing).
+
+run_tests :-
+    test_static_second_argument,
+    test_static_third_argument,
+    test_dynamic_assertz,
+    test_subterm_indexing,
+    write('indexing tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,51 @@
+:- module(make_tests, []).
+
+:- use_module(library(files)).
+:- use_module(library(lists)).
+
+:- multifile(hook/1).
+
+hook(make_tests).
+
+:- use_module('make/importer').
+:- ensure_loaded('make/hooks').
+:- ensure_loaded('make/broken').
+:- use_module('make/includer_a').
+:- use_module('make/includer_b').
+
+%% the files of make/ are replaced by their _v2 versions, which the
+%% test harness made newer than them.
+replace_file(Name) :-
+    prolog_load_context(directory, Dir),
+    atom_chars(Dir, DirChars),
+    atom_chars(Name, NameChars),
+    append([DirChars, "/make/", NameChars, "_v2.pl"], Replacement),
+    append([DirChars, "/make/", NameChars, ".pl"], File),
+    rename_file(Replacement, File).
+
+test_unmodified :-
+    make,
+    findall(X, importer:t(X), Xs),
+    Xs == [1, 2],
+    findall(H, hook(H), Hs),
+    Hs == [make_tests, hooks].
+
+test_modified :-
+    maplist(replace_file, [library, hooks, broken, shared]),
+    make,
+    findall(X, importer:t(X), Xs),
+    Xs == [3],
+    findall(H, hook(H), Hs),
+    Hs == [make_tests, hooks_v2],
+    findall(A, includer_a:shared(A), As),
+    As == [2],
+    findall(B, includer_b:shared(B), Bs),
+    Bs == [2].
+
+run_tests :-
+    test_unmodified,
+    test_modified,
+    write('make tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,2 @@
+broken(2).
+broken(3) :- .
@@ -0,0 +1,3 @@
+:- multifile(make_tests:hook/1).
+
+make_tests:hook(hooks).
@@ -0,0 +1,5 @@
+:- module(importer, [t/1]).
+
+:- use_module(library).
+
+t(X) :- a(X).
@@ -0,0 +1,3 @@
+:- module(includer_a, []).
+
+:- include(shared).
@@ -0,0 +1,3 @@
+:- module(includer_b, []).
+
+:- include(shared).
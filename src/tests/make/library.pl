@@ -0,0 +1,4 @@
+:- module(library, [a/1]).
+
+a(1).
+a(2).
@@ -0,0 +1,3 @@
+:- module(library, [a/1]).
+
+a(3).
@@ -0,0 +1 @@
+shared(2).
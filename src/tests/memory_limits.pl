@@ -0,0 +1,81 @@
+:- module(memory_limit_tests, []).
+
+:- use_module(library(lists)).
+
+% a list of N elements, built on the heap.
+long_list(0, []) :- !.
+long_list(N, [N|Ns]) :-
+    N1 is N - 1,
+    long_list(N1, Ns).
+
+% an atom of 2^N characters, built without a long list.
+long_atom(0, a) :- !.
+long_atom(N, A) :-
+    N1 is N - 1,
+    long_atom(N1, A0),
+    atom_concat(A0, A0, A).
+
+% a recursion that isn't a last call, and so keeps a frame for each
+% level on the stack.
+deep(0) :- !.
+deep(N) :-
+    N1 is N - 1,
+    deep(N1),
+    true.
+
+test_flags :-
+    current_prolog_flag(heap_limit, infinite),
+    current_prolog_flag(stack_limit, infinite),
+    set_prolog_flag(heap_limit, 16777216),
+    current_prolog_flag(heap_limit, 16777216),
+    catch(set_prolog_flag(stack_limit, 1024), error(domain_error(flag_value, _), _), true),
+    current_prolog_flag(stack_limit, infinite),
+    set_prolog_flag(heap_limit, infinite).
+
+test_heap_limit :-
+    set_prolog_flag(heap_limit, 16777216),
+    catch(long_list(10000000, _), error(resource_error(memory), _), true),
+    long_list(1000, Ns),
+    length(Ns, 1000),
+    set_prolog_flag(heap_limit, infinite).
+
+% a limit set below the memory in use raises one error, and the calls
+% after it go on within the headroom.
+test_raised_once :-
+    long_list(200000, Ns),
+    statistics(heapused, Used),
+    Limit is Used - 65536,
+    catch(set_prolog_flag(heap_limit, Limit), error(resource_error(memory), _), true),
+    current_prolog_flag(heap_limit, Limit),
+    length(Ns, 200000),
+    set_prolog_flag(heap_limit, infinite).
+
+% a built-in predicate that takes the heap past the limit raises the
+% error itself.
+test_builtin_growth :-
+    long_atom(20, A),
+    statistics(heapused, Used),
+    Limit is Used + 4194304,
+    set_prolog_flag(heap_limit, Limit),
+    catch(( atom_codes(A, _), Raised = false ),
+          error(resource_error(memory), _),
+          Raised = true),
+    Raised == true,
+    set_prolog_flag(heap_limit, infinite).
+
+test_stack_limit :-
+    set_prolog_flag(stack_limit, 16777216),
+    catch(deep(10000000), error(resource_error(memory), _), true),
+    deep(1000),
+    set_prolog_flag(stack_limit, infinite).
+
+run_tests :-
+    test_flags,
+    test_heap_limit,
+    test_raised_once,
+    test_builtin_growth,
+    test_stack_limit,
+    write('memory limit tests passed'),
+    nl.
+
+:- initialization(run_tests).
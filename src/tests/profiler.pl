@@ -0,0 +1,87 @@
+:- module(profiler_tests, []).
+
+:- use_module(library(lists)).
+:- use_module(library(profile)).
+
+p(X) :- q(X), r(X).
+
+q(1).
+q(2).
+q(3).
+
+r(3).
+
+nrev([], []).
+nrev([X|Xs], Ys) :-
+    nrev(Xs, Ys0),
+    append(Ys0, [X], Ys).
+
+s(X) :- X > 0, throw(ball).
+
+count_down(0) :- !.
+count_down(N) :-
+    N1 is N - 1,
+    count_down(N1).
+
+counts(Data, PI, Counts) :-
+    member(profile(PI, Calls, Redos, Exits, Fails, Self, Cumulative), Data),
+    Counts = [Calls, Redos, Exits, Fails],
+    Self >= 0,
+    Cumulative >= Self.
+
+test_ports :-
+    profile(p(X), [report(false)]),
+    X == 3,
+    profile_data(Data),
+    counts(Data, profiler_tests:p/1, [1, 0, 1, 0]),
+    counts(Data, profiler_tests:q/1, [1, 2, 3, 0]),
+    counts(Data, profiler_tests:r/1, [3, 0, 1, 2]).
+
+test_recursion :-
+    profile(nrev([a,b,c], Ys), [report(false)]),
+    Ys == [c,b,a],
+    profile_data(Data),
+    counts(Data, profiler_tests:nrev/2, [4, 0, 4, 0]),
+    % append/3 calls itself from library(lists), unprofiled.
+    counts(Data, lists:append/3, [3, 0, 3, 0]).
+
+test_failure_and_exceptions :-
+    \+ profile(p(4), [report(false)]),
+    profile_data(Data0),
+    counts(Data0, profiler_tests:p/1, [1, 0, 0, 1]),
+    catch(profile(s(1), [report(false)]), ball, true),
+    profile_data(Data1),
+    counts(Data1, profiler_tests:s/1, [1, 0, 0, 0]).
+
+% the last calls of count_down/1 run in constant space, well within
+% the memory limits.
+test_last_calls :-
+    set_prolog_flag(heap_limit, 16777216),
+    set_prolog_flag(stack_limit, 16777216),
+    profile(count_down(1000000), [report(false)]),
+    set_prolog_flag(heap_limit, infinite),
+    set_prolog_flag(stack_limit, infinite),
+    profile_data(Data),
+    counts(Data, profiler_tests:count_down/1, [1000001, 0, 1000001, 0]).
+
+test_options :-
+    catch(profile(true, [top(-1)]),
+          error(domain_error(not_less_than_zero, -1), _),
+          true),
+    catch(profile(true, [sort_by(time)]),
+          error(domain_error(profile_field, time), _),
+          true),
+    catch(profile(true, [verbose]),
+          error(domain_error(profile_option, verbose), _),
+          true).
+
+run_tests :-
+    test_ports,
+    test_recursion,
+    test_failure_and_exceptions,
+    test_last_calls,
+    test_options,
+    write('profiler tests passed'),
+    nl.
+
+:- initialization(run_tests).
@@ -0,0 +1,72 @@
+:- module(statistics_tests, []).
+
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+p(X) :- q(X).
+q(X) :- r(X).
+r(_).
+
+test_counters :-
+    statistics(inferences, I0),
+    p(a),
+    statistics(inferences, I1),
+    I1 - I0 >= 3,
+    I1 - I0 < 10,
+    statistics(atoms, Atoms),
+    statistics(clauses, Clauses),
+    statistics(modules, Modules),
+    Atoms > 0,
+    Clauses > 0,
+    Modules > 0.
+
+test_times :-
+    statistics(runtime, [T0, _]),
+    statistics(runtime, [T1, S1]),
+    T0 =< T1,
+    S1 =:= T1 - T0,
+    statistics(walltime, [W, _]),
+    integer(W),
+    statistics(cputime, C),
+    float(C).
+
+test_memory :-
+    statistics(heapused, Heap),
+    statistics(stackused, Stack),
+    statistics(trailused, Trail),
+    Heap > 0,
+    Stack > 0,
+    Trail >= 0,
+    statistics(choicepoints, B0),
+    member(_, [a, b]),
+    statistics(choicepoints, B1),
+    B1 > B0,
+    !.
+
+test_inference_limits :-
+    statistics(inferences, I0),
+    call_with_inference_limit(p(a), 10000, !),
+    statistics(inferences, I1),
+    I1 - I0 >= 3,
+    call_with_inference_limit(length(_, _), 10000, inference_limit_exceeded),
+    statistics(inferences, I2),
+    p(a),
+    statistics(inferences, I3),
+    I3 - I2 >= 3,
+    I3 - I2 < 10.
+
+test_errors :-
+    catch(statistics(foo, _), error(domain_error(statistics_key, foo), _), true),
+    catch(statistics(_, _), error(instantiation_error, _), true),
+    catch(statistics(1, _), error(type_error(atom, 1), _), true).
+
+run_tests :-
+    test_counters,
+    test_times,
+    test_memory,
+    test_inference_limits,
+    test_errors,
+    write('statistics tests passed'),
+    nl.
+
+:- initialization(run_tests).
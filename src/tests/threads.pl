@@ -0,0 +1,152 @@
+:- module(threads_tests, []).
+
+:- use_module(library(charsio)).
+:- use_module(library(sockets)).
+:- use_module(library(threads)).
+
+:- dynamic(counter/1).
+
+count_to(N, N) :- !.
+count_to(I, N) :-
+    I1 is I + 1,
+    count_to(I1, N).
+
+echo :-
+    thread_get_message(ping(From, X)),
+    count_to(0, 20000),
+    thread_send_message(From, pong(X)).
+
+test_create_and_join :-
+    thread_create(true, T1, []),
+    thread_create(fail, T2, []),
+    thread_create(throw(ball), T3, []),
+    thread_join(T1, S1),
+    thread_join(T2, S2),
+    thread_join(T3, S3),
+    S1 == true,
+    S2 == false,
+    S3 == exception(ball),
+    catch(thread_join(T1, _), error(existence_error(thread, T1), _), true).
+
+test_aliases :-
+    thread_self(main),
+    thread_create(thread_self(echo), T, [alias(echo)]),
+    T == echo,
+    catch(thread_create(true, _, [alias(main)]),
+          error(permission_error(create, thread, main), _),
+          true),
+    thread_join(echo, true).
+
+test_messages :-
+    thread_self(Me),
+    thread_create(echo, T1, []),
+    thread_create(echo, T2, []),
+    thread_send_message(T2, ping(Me, f(Y, Y))),
+    thread_send_message(T1, ping(Me, 1)),
+    thread_get_message(pong(1)),
+    thread_get_message(pong(f(A, B))),
+    A == B,
+    thread_join(T1, true),
+    thread_join(T2, true).
+
+test_mutexes :-
+    retractall(counter(_)),
+    assertz(counter(0)),
+    thread_create(increment(100), T1, []),
+    thread_create(increment(100), T2, []),
+    increment(100),
+    thread_join(T1, true),
+    thread_join(T2, true),
+    counter(300).
+
+increment(0) :- !.
+increment(N) :-
+    with_mutex(counter, ( retract(counter(C0)),
+                          count_to(0, 20),
+                          C is C0 + 1,
+                          assertz(counter(C))
+                        )),
+    N1 is N - 1,
+    increment(N1).
+
+hold(Mutex, Owner) :-
+    mutex_lock(Mutex),
+    thread_send_message(Owner, locked),
+    thread_get_message(release),
+    mutex_unlock(Mutex).
+
+test_mutex_handoff :-
+    thread_self(Me),
+    thread_create(hold(handoff, Me), T, []),
+    thread_get_message(locked),
+    thread_send_message(T, release),
+    mutex_lock(handoff),
+    mutex_unlock(handoff),
+    thread_join(T, true).
+
+test_deadlocks :-
+    catch(thread_get_message(never), error(system_error(deadlock), _), true),
+    thread_create(true, T1, []),
+    catch(thread_get_message(never), error(system_error(deadlock), _), true),
+    thread_join(T1, true),
+    thread_self(Me),
+    thread_create(hold(deadlock, Me), T2, []),
+    thread_get_message(locked),
+    catch(mutex_lock(deadlock), error(system_error(deadlock), _), true),
+    catch(thread_join(T2, _), error(system_error(deadlock), _), true),
+    thread_send_message(T2, release),
+    thread_join(T2, true),
+    mutex_lock(deadlock),
+    mutex_unlock(deadlock).
+
+test_detached :-
+    thread_self(Me),
+    thread_create(thread_send_message(Me, done), T, [detached(true)]),
+    thread_get_message(done),
+    catch(thread_join(T, _), error(existence_error(thread, T), _), true).
+
+accept_clients(_, 0) :- !.
+accept_clients(Socket, N) :-
+    socket_server_accept(Socket, _, Stream, []),
+    thread_create(echo_line(Stream), _, [detached(true)]),
+    N1 is N - 1,
+    accept_clients(Socket, N1).
+
+echo_line(Stream) :-
+    read_line_to_chars(Stream, Cs, []),
+    format(Stream, "echo: ~s", [Cs]),
+    close(Stream).
+
+%% the handler of the first client waits for its input while the
+%% second client is served.
+test_sockets :-
+    socket_server_open('127.0.0.1':Port, Socket),
+    thread_create(accept_clients(Socket, 2), T, []),
+    socket_client_open('127.0.0.1':Port, Silent, []),
+    socket_client_open('127.0.0.1':Port, Chatty, []),
+    format(Chatty, "hello~n", []),
+    flush_output(Chatty),
+    read_line_to_chars(Chatty, Reply1, []),
+    Reply1 == "echo: hello\n",
+    format(Silent, "at last~n", []),
+    flush_output(Silent),
+    read_line_to_chars(Silent, Reply2, []),
+    Reply2 == "echo: at last\n",
+    close(Chatty),
+    close(Silent),
+    thread_join(T, true),
+    socket_server_close(Socket).
+
+run_tests :-
+    test_create_and_join,
+    test_aliases,
+    test_messages,
+    test_mutexes,
+    test_mutex_handoff,
+    test_deadlocks,
+    test_detached,
+    test_sockets,
+    write('thread tests passed'),
+    nl.
+
+:- initialization(run_tests).
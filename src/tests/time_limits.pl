@@ -0,0 +1,88 @@
+:- module(time_limit_tests, []).
+
+:- use_module(library(between)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(time)).
+
+loop :-
+    repeat,
+    fail.
+
+count_down(0) :- !.
+count_down(N) :-
+    N1 is N - 1,
+    count_down(N1).
+
+test_time_limit_exceeded :-
+    catch((call_with_time_limit(0.1, loop), fail), time_limit_exceeded, true),
+    catch((call_with_time_limit(0.1, loop, request(1)), fail),
+          time_limit_exceeded(request(1)),
+          true),
+    catch((call_with_time_limit(0, true), fail), time_limit_exceeded, true).
+
+test_within_time_limit :-
+    call_with_time_limit(10, member(X, [a,b,c])),
+    X == a,
+    \+ call_with_time_limit(10, fail),
+    catch(call_with_time_limit(10, throw(ball)), ball, true),
+    % limits that have been removed don't expire later.
+    findall(N, (between(1, 10, N), call_with_time_limit(0.2, true)), Ns),
+    length(Ns, 10),
+    sleep(0.3),
+    count_down(1000).
+
+test_nested_time_limits :-
+    catch((call_with_time_limit(0.1, call_with_time_limit(10, loop, inner), outer), fail),
+          time_limit_exceeded(outer),
+          true),
+    catch((call_with_time_limit(10, call_with_time_limit(0.1, loop, inner), outer), fail),
+          time_limit_exceeded(inner),
+          true),
+    catch((call_with_time_limit(1,
+                                (  catch(call_with_time_limit(0.1, loop, inner),
+                                         time_limit_exceeded(inner),
+                                         true),
+                                   loop
+                                ),
+                                outer),
+           fail),
+          time_limit_exceeded(outer),
+          true).
+
+% a time limit that expires during a term expansion isn't taken for an
+% error of the expansion.
+user:term_expansion(expand_forever, _) :-
+    repeat,
+    fail.
+
+test_expansion_time_limits :-
+    catch((call_with_time_limit(0.1, expand_term(expand_forever, _)), fail),
+          time_limit_exceeded,
+          true).
+
+test_cleanup :-
+    catch(call_with_time_limit(0.1,
+                               setup_call_cleanup(true,
+                                                  loop,
+                                                  bb_put(cleaned_up, true))),
+          time_limit_exceeded,
+          true),
+    bb_get(cleaned_up, true).
+
+test_errors :-
+    catch(call_with_time_limit(_, true), error(instantiation_error, _), true),
+    catch(call_with_time_limit(a, true), error(type_error(number, a), _), true),
+    catch(call_with_time_limit(-1, true), error(domain_error(not_less_than_zero, -1), _), true).
+
+run_tests :-
+    test_time_limit_exceeded,
+    test_within_time_limit,
+    test_nested_time_limits,
+    test_expansion_time_limits,
+    test_cleanup,
+    test_errors,
+    write('time limit tests passed'),
+    nl.
+
+:- initialization(run_tests).
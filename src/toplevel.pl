@@ -2,9 +2,11 @@
                         copy_term/3]).
 
 :- use_module(library(charsio)).
+:- use_module(library(debug), [trace/0]).
 :- use_module(library(files)).
 :- use_module(library(iso_ext)).
 :- use_module(library(lists)).
+:- use_module(library(qsave)).
 :- use_module(library(si)).
 
 :- use_module(library('$project_atts')).
@@ -21,21 +23,31 @@ load_scryerrc :-
     ;  true
     ).
 
-:- dynamic(argv/1).
+% argv/1 isn't dynamic itself, since the modules importing it would
+% keep the empty predicate it is before '$repl' asserts its clause.
+:- dynamic('$argv'/1).
+:- dynamic(stand_alone/0).
 
+argv(Args) :-
+    '$argv'(Args).
+
+'$repl'([_|Args]) :-
+    stand_alone,
+    asserta('$toplevel':'$argv'(Args)),
+    run_main.
 '$repl'([_|Args0]) :-
     \+ argv(_),
     (   append(Args1, ["--"|Args2], Args0) ->
-        asserta('$toplevel':argv(Args2)),
+        asserta('$toplevel':'$argv'(Args2)),
         Args = Args1
-    ;   asserta('$toplevel':argv([])),
+    ;   asserta('$toplevel':'$argv'([])),
         Args = Args0
     ),
     load_scryerrc,
     delegate_task(Args, []),
     repl.
 '$repl'(_) :-
-    (   \+ argv(_) -> asserta('$toplevel':argv([]))
+    (   \+ argv(_) -> asserta('$toplevel':'$argv'([]))
     ;   true
     ),
     load_scryerrc,
@@ -46,6 +58,29 @@ delegate_task([], Goals0) :-
     reverse(Goals0, Goals),
     run_goals(Goals),
     repl.
+delegate_task(["--compile-exe"|Args], _) :-
+    !,
+    (  Args = [File, "-o", Exe] ->
+       compile_exe(File, Exe)
+    ;  print_help
+    ).
+delegate_task([Arg0, Size0|Args], Goals0) :-
+    member(Arg0-Flag, ["--heap-limit"-heap_limit, "--stack-limit"-stack_limit]),
+    !,
+    (   memory_size(Size0, Size) ->
+        catch(set_prolog_flag(Flag, Size), E, print_exception(E))
+    ;   print_help
+    ),
+    delegate_task(Args, Goals0).
+delegate_task([Arg0, _|Args], Goals0) :-
+    % the state was restored before the toplevel started.
+    member(Arg0, ["-x", "--state"]),
+    !,
+    delegate_task(Args, Goals0).
+delegate_task(["--no-boot-image"|Args], Goals0) :-
+    % the machine was started before the toplevel.
+    !,
+    delegate_task(Args, Goals0).
 delegate_task([Arg0|Args], Goals0) :-
     (   member(Arg0, ["-h", "--help"]) -> print_help
     ;   member(Arg0, ["-v", "--version"]) -> print_version
@@ -65,6 +100,19 @@ print_help :-
     write('Print version information and exit'), nl,
     write('   -g, --goal GOAL      '),
     write('Run the query GOAL'), nl,
+    write('   -x, --state FILE     '),
+    write('Start from the state saved to FILE'), nl,
+    write('   --no-boot-image      '),
+    write('Compile the libraries without using a boot image'), nl,
+    write('   --heap-limit SIZE    '),
+    write('Limit the heap to SIZE bytes'), nl,
+    write('   --stack-limit SIZE   '),
+    write('Limit the stack and trail to SIZE bytes'), nl,
+    write('                        '),
+    write('(SIZE may end in K, M or G)'), nl,
+    write('   --compile-exe FILE -o EXE'), nl,
+    write('                        '),
+    write('Compile FILE to the executable EXE, which runs main/0'), nl,
     % write('                        '),
     halt.
 
@@ -73,6 +121,41 @@ print_version :-
     write(Version), nl,
     halt.
 
+% a size in bytes, optionally in units of K, M or G.
+memory_size(Cs0, Size) :-
+    (   append(Cs, [Unit], Cs0),
+        unit_size(Unit, UnitSize) ->
+        true
+    ;   Cs = Cs0,
+        UnitSize = 1
+    ),
+    catch(number_chars(N, Cs), _, false),
+    integer(N),
+    Size is N * UnitSize.
+
+unit_size('K', 1024).
+unit_size('M', 1048576).
+unit_size('G', 1073741824).
+
+compile_exe(File0, Exe0) :-
+    atom_chars(File, File0),
+    atom_chars(Exe, Exe0),
+    (  catch((use_module(File),
+              qsave_program(Exe, [stand_alone(true)])),
+             E,
+             (print_exception(E), halt(1))) ->
+       halt
+    ;  halt(1)
+    ).
+
+% the entry point of a stand-alone executable.
+run_main :-
+    (  catch_with_backtrace(user:main, E, (print_exception(E), halt(1))) ->
+       halt
+    ;  write('Warning: main/0 failed'), nl,
+       halt(1)
+    ).
+
 gather_goal(Type, Args0, Goals) :-
     length(Args0, N),
     (   N < 1 -> print_help, halt
@@ -117,7 +200,7 @@ run_goals([Goal|_]) :-
     halt.
 
 repl :-
-    catch(read_and_match, E, print_exception(E)),
+    catch_with_backtrace(read_and_match, E, print_exception(E)),
     false. %% this is for GC, until we get actual GC.
 repl :-
     repl.
@@ -367,7 +450,12 @@ gather_equations([Var = Value | Pairs], OrigVarList, Goals) :-
        gather_equations(Pairs, OrigVarList, Goals0)
     ).
 
-print_exception(E) :-
+print_exception(E0) :-
+    (  E0 = error(Formal, backtrace(Context, Frames)) ->
+       E = error(Formal, Context)
+    ;  E = E0,
+       Frames = []
+    ),
     (  E == error('$interrupt_thrown', repl) -> nl % print the
     % exception on a
     % newline to evade
@@ -376,7 +464,32 @@ print_exception(E) :-
     ),
     write_term('caught: ', [quoted(false), max_depth(20)]),
     writeq(E),
-    nl.
+    nl,
+    print_backtrace(Frames).
+
+% the frames of the toplevel itself, which called the goal, are left
+% out of the backtraces of its exceptions.
+print_backtrace([]).
+print_backtrace([frame(Module:PI, Clause, Location) | Frames]) :-
+    (  Module == '$toplevel' ->
+       true
+    ;  write('   in '),
+       writeq(Module:PI),
+       (  integer(Clause) ->
+          write(', clause '),
+          write(Clause)
+       ;  true
+       ),
+       (  Location = File:Line ->
+          write(' at '),
+          write(File),
+          write(':'),
+          write(Line)
+       ;  true
+       ),
+       nl,
+       print_backtrace(Frames)
+    ).
 
 print_exception_with_check(E) :-
     (  E = error(_, _:_) -> true % if the error source contains a line
@@ -384,3 +497,94 @@ print_exception_with_check(E) :-
     % is expected to be printed instead.
     ;  print_exception(E)
     ).
+
+%% '$interrupt'(Goal, Frames, P) is called by the machine in place
+%% of Goal, the call at code location P, once Ctrl-C is pressed.
+%% Frames are the predicates of the active frames, innermost first, as
+%% Module:Name/Arity. Continuing makes the call at P again, so that
+%% the computation resumes unchanged, and tracing makes it in the
+%% creep mode of library(debug), which is loaded with the toplevel so
+%% that no module is loaded in the middle of the computation.
+
+'$interrupt'(_:Goal, Frames, P) :-
+    nl(user_output),
+    interrupt_action(Frames, Action),
+    (  Action == continue ->
+       true
+    ;  Action == trace ->
+       trace
+    ),
+    '$resume_interrupted'(Goal, P).
+
+interrupt_action(Frames, Action) :-
+    write(user_output, 'Action (h for help) ? '),
+    flush_output(user_output),
+    get_single_char(C),
+    nl(user_output),
+    (  C = a ->
+       throw(error('$interrupt_thrown', repl))
+    ;  C = c ->
+       Action = continue
+    ;  C = t ->
+       Action = trace
+    ;  C = b ->
+       print_frames(Frames),
+       interrupt_action(Frames, Action)
+    ;  C = e ->
+       halt
+    ;  C = h ->
+       interrupt_help_message,
+       interrupt_action(Frames, Action)
+    ;  interrupt_action(Frames, Action)
+    ).
+
+print_frames([]).
+print_frames([Frame | Frames]) :-
+    write(user_output, '   '),
+    writeq(user_output, Frame),
+    nl(user_output),
+    print_frames(Frames).
+
+interrupt_help_message :-
+    nl(user_output),
+    write(user_output, '"a": abort the query\n'),
+    write(user_output, '"c": continue the query\n'),
+    write(user_output, '"t": trace the rest of the query\n'),
+    write(user_output, '"b": print the predicates of the active frames\n'),
+    write(user_output, '"e": exit Scryer Prolog\n'),
+    write(user_output, '"h": display this help message\n\n').
+
+
+%% Drivers for the embedding API of machine/embedding.rs. Answers are
+%% handed to Rust by '$yield_answer'/2, which suspends the machine
+%% until the next answer is requested.
+
+'$run_query'(Stream) :-
+    yield_answers('$toplevel':read_and_call_query(Stream, VarList), VarList).
+
+'$consult_stream'(Stream) :-
+    yield_answers(loader:consult_stream(Stream), []).
+
+read_and_call_query(Stream, VarList) :-
+    catch(read_term(Stream, Term0, [variable_names(VarList)]),
+          error(syntax_error(Error), Context),
+          throw('$query_syntax_error'(error(syntax_error(Error), Context)))),
+    (  nonvar(Term0),
+       functor(Term0, call, _) ->
+       Term = Term0
+    ;  expand_goal(call(Term0), user, call(Term))
+    ),
+    '$call'(Term).
+
+yield_answers(Goal, VarList) :-
+    '$get_b_value'(B),
+    catch(Goal, E, true),
+    (  var(E) ->
+       '$yield_answer'(true(VarList), B)
+    ;  E = '$query_syntax_error'(Ball) ->
+       '$yield_answer'(syntax_error(Ball), B)
+    ;  '$yield_answer'(exception(E), B)
+    ),
+    false.
+yield_answers(_, _) :-
+    '$yield_answer'(false, 0).
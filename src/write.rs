@@ -65,10 +65,18 @@ impl fmt::Display for REPLCodePtr {
                 write!(f, "REPLCodePtr::PopLoadContext"),
             REPLCodePtr::PopLoadStatePayload =>
                 write!(f, "REPLCodePtr::PopLoadStatePayload"),
+            REPLCodePtr::PushIncludeContext =>
+                write!(f, "REPLCodePtr::PushIncludeContext"),
             REPLCodePtr::PushLoadContext =>
                 write!(f, "REPLCodePtr::PushLoadContext"),
             REPLCodePtr::PushLoadStatePayload =>
                 write!(f, "REPLCodePtr::PushLoadStatePayload"),
+            REPLCodePtr::PushModuleLoadStatePayload =>
+                write!(f, "REPLCodePtr::PushModuleLoadStatePayload"),
+            REPLCodePtr::PredicateClauses =>
+                write!(f, "REPLCodePtr::PredicateClauses"),
+            REPLCodePtr::SetClauseLocation =>
+                write!(f, "REPLCodePtr::SetClauseLocation"),
 	        REPLCodePtr::UseModule =>
 		        write!(f, "REPLCodePtr::UseModule"),
             REPLCodePtr::MetaPredicateProperty =>
@@ -89,6 +97,38 @@ impl fmt::Display for REPLCodePtr {
                 write!(f, "REPLCodePtr::RemoveModuleExports"),
             REPLCodePtr::AddNonCountedBacktracking =>
                 write!(f, "REPLCodePtr::AddNonCountedBacktracking"),
+            REPLCodePtr::MutexLock =>
+                write!(f, "REPLCodePtr::MutexLock"),
+            REPLCodePtr::MutexUnlock =>
+                write!(f, "REPLCodePtr::MutexUnlock"),
+            REPLCodePtr::ThreadCreate =>
+                write!(f, "REPLCodePtr::ThreadCreate"),
+            REPLCodePtr::ThreadFinish =>
+                write!(f, "REPLCodePtr::ThreadFinish"),
+            REPLCodePtr::ThreadJoin =>
+                write!(f, "REPLCodePtr::ThreadJoin"),
+            REPLCodePtr::ThreadPeekMessage =>
+                write!(f, "REPLCodePtr::ThreadPeekMessage"),
+            REPLCodePtr::ThreadPreempt =>
+                write!(f, "REPLCodePtr::ThreadPreempt"),
+            REPLCodePtr::ThreadRemoveMessage =>
+                write!(f, "REPLCodePtr::ThreadRemoveMessage"),
+            REPLCodePtr::ThreadSelf =>
+                write!(f, "REPLCodePtr::ThreadSelf"),
+            REPLCodePtr::ThreadSendMessage =>
+                write!(f, "REPLCodePtr::ThreadSendMessage"),
+            REPLCodePtr::ThreadWaitJoin =>
+                write!(f, "REPLCodePtr::ThreadWaitJoin"),
+            REPLCodePtr::ThreadWaitMessage =>
+                write!(f, "REPLCodePtr::ThreadWaitMessage"),
+            REPLCodePtr::ThreadWaitReadable =>
+                write!(f, "REPLCodePtr::ThreadWaitReadable"),
+            REPLCodePtr::YieldAnswer =>
+                write!(f, "REPLCodePtr::YieldAnswer"),
+            REPLCodePtr::ForeignCall =>
+                write!(f, "REPLCodePtr::ForeignCall"),
+            REPLCodePtr::QSaveProgram =>
+                write!(f, "REPLCodePtr::QSaveProgram"),
         }
     }
 }
@@ -253,6 +293,7 @@ impl fmt::Display for HeapCellValue {
                     has_tail,
                 )
             }
+            &HeapCellValue::Pointer(ptr) => write!(f, "$pointer({:?})", ptr),
             &HeapCellValue::Stream(ref stream) => {
                 write!(f, "$stream({})", stream.as_ptr() as usize)
             }
@@ -290,6 +331,7 @@ impl fmt::Display for Addr {
             &Addr::StackCell(fr, sc) => write!(f, "Addr::StackCell({}, {})", fr, sc),
             &Addr::Str(s) => write!(f, "Addr::Str({})", s),
             &Addr::PStrLocation(h, n) => write!(f, "Addr::PStrLocation({}, {})", h, n),
+            &Addr::Pointer(h) => write!(f, "Addr::Pointer({})", h),
             &Addr::Stream(stream) => write!(f, "Addr::Stream({})", stream),
             &Addr::TcpListener(tcp_listener) => write!(f, "Addr::TcpListener({})", tcp_listener),
             &Addr::Usize(cp) => write!(f, "Addr::Usize({})", cp),
@@ -411,6 +453,9 @@ impl fmt::Display for IndexingInstruction {
             &IndexingInstruction::SwitchOnStructure(ref structures) => {
                 write!(f, "switch_on_structure {}", structures.len())
             }
+            &IndexingInstruction::SwitchOnSubterm(a, v, c, l, s) => {
+                write!(f, "switch_on_subterm {}, {}, {}, {}, {}", a, v, c, l, s)
+            }
         }
     }
 }
@@ -0,0 +1,7 @@
+:- use_module(library(format)).
+:- use_module(library(lists)).
+
+main :-
+    argv(Args),
+    length(Args, N),
+    format("~d arguments: ~q~n", [N, Args]).
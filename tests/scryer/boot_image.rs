@@ -0,0 +1,46 @@
+use crate::helper::SCRYER_PROLOG;
+use assert_cmd::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn start(cache: &Path) -> Command {
+    let mut cmd = Command::cargo_bin(SCRYER_PROLOG).unwrap();
+
+    cmd.env("XDG_CACHE_HOME", cache)
+        .env_remove("SCRYER_PROLOG_NO_BOOT_IMAGE")
+        .args(&["-g", "halt"])
+        .write_stdin("");
+
+    cmd
+}
+
+// a build writes its own boot image, removes those of other builds,
+// and leaves the other files of the cache directory alone.
+#[test]
+fn boot_image_files() {
+    let cache = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("boot_image_cache");
+    let dir = cache.join("scryer-prolog");
+
+    let _ = fs::remove_dir_all(&cache);
+    fs::create_dir_all(&dir).unwrap();
+
+    fs::write(dir.join("boot-0.img"), "").unwrap();
+    fs::write(dir.join("notes.img"), "").unwrap();
+
+    let file_count = || fs::read_dir(&dir).unwrap().count();
+
+    start(&cache).arg("--no-boot-image").assert().success();
+    assert_eq!(file_count(), 2);
+
+    start(&cache)
+        .env("SCRYER_PROLOG_NO_BOOT_IMAGE", "1")
+        .assert()
+        .success();
+    assert_eq!(file_count(), 2);
+
+    start(&cache).assert().success();
+    assert_eq!(file_count(), 2);
+
+    assert!(!dir.join("boot-0.img").exists());
+    assert!(dir.join("notes.img").exists());
+}
@@ -0,0 +1,321 @@
+use scryer_prolog::machine::embedding::*;
+use scryer_prolog::machine::{Machine, Stream};
+
+fn machine() -> Machine {
+    Machine::new(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+    )
+}
+
+fn atom(name: &str) -> Term {
+    Term::Atom(name.to_string())
+}
+
+#[test]
+fn consult_and_query() {
+    let mut wam = machine();
+
+    wam.consult_str("colour(red). colour(green). colour(blue).")
+        .unwrap();
+
+    let colours: Vec<Term> = wam
+        .query("colour(C)")
+        .map(|answer| answer.unwrap()["C"].clone())
+        .collect();
+
+    assert_eq!(colours, vec![atom("red"), atom("green"), atom("blue")]);
+    assert_eq!(wam.query("colour(purple).").count(), 0);
+}
+
+#[test]
+fn owned_terms() {
+    let mut wam = machine();
+
+    let answer = wam
+        .query("R is 1 rdiv 3, X = f(Y, \"abc\", [1, 2.5|T], R), Z = Y")
+        .next()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        answer["X"],
+        Term::Compound(
+            "f".to_string(),
+            vec![
+                Term::Var("Z".to_string()),
+                Term::String("abc".to_string()),
+                Term::Compound(
+                    ".".to_string(),
+                    vec![
+                        Term::Integer(Integer::from(1)),
+                        Term::Compound(
+                            ".".to_string(),
+                            vec![Term::Float(2.5), Term::Var("T".to_string())]
+                        ),
+                    ]
+                ),
+                Term::Rational(Rational::from(Integer::from(1)) / Rational::from(Integer::from(3))),
+            ]
+        )
+    );
+
+    assert_eq!(answer["Y"], Term::Var("Z".to_string()));
+    assert_eq!(answer["Z"], Term::Var("Z".to_string()));
+
+    // lists of one-character atoms built at runtime aren't strings.
+    let answer = wam
+        .query("X = [A, b], A = a, Y = \"ab\"")
+        .next()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(answer["X"], Term::List(vec![atom("a"), atom("b")]));
+    assert_eq!(answer["Y"], Term::String("ab".to_string()));
+}
+
+#[test]
+fn abandoned_queries() {
+    let mut wam = machine();
+
+    wam.consult_str("nat(0). nat(N) :- nat(N0), N is N0 + 1.")
+        .unwrap();
+
+    let mut answers = wam.query("nat(N)");
+    assert_eq!(
+        answers.next().unwrap().unwrap()["N"],
+        Term::Integer(Integer::from(0))
+    );
+    drop(answers);
+
+    let answers: Vec<_> = wam.query("nat(N), N >= 2, !").collect();
+    assert_eq!(
+        answers,
+        vec![Ok(Bindings::from([(
+            "N".to_string(),
+            Term::Integer(Integer::from(2))
+        )]))]
+    );
+}
+
+// each answer is found after the heap was collected, with the
+// variables of the query held by the frames of the query driver.
+#[test]
+fn answers_across_collections() {
+    let mut wam = machine();
+
+    wam.consult_str(
+        ":- use_module(library(iso_ext)).
+         churn :- statistics(heapused, B), churn(B).
+         churn(B0) :- functor(_, f, 1000), statistics(heapused, B), ( B < B0 -> true ; churn(B) ).",
+    )
+    .unwrap();
+
+    let answers: Vec<Term> = wam
+        .query("member(N, [1,2,3]), X = f(N, Y, \"abc\", Y), churn")
+        .map(|answer| answer.unwrap()["X"].clone())
+        .collect();
+
+    let expected: Vec<Term> = (1..4)
+        .map(|n| {
+            Term::Compound(
+                "f".to_string(),
+                vec![
+                    Term::Integer(Integer::from(n)),
+                    Term::Var("Y".to_string()),
+                    Term::String("abc".to_string()),
+                    Term::Var("Y".to_string()),
+                ],
+            )
+        })
+        .collect();
+
+    assert_eq!(answers, expected);
+}
+
+#[test]
+fn typed_errors() {
+    let mut wam = machine();
+
+    match wam.query("X = ").next() {
+        Some(Err(PrologError::Syntax(_))) => {}
+        answer => panic!("expected a syntax error, got {:?}", answer),
+    }
+
+    match wam.query("atom_length(X, Y)").next() {
+        Some(Err(PrologError::Exception(Term::Compound(name, args)))) => {
+            assert_eq!(name, "error");
+            assert_eq!(args[0], atom("instantiation_error"));
+        }
+        answer => panic!("expected an instantiation error, got {:?}", answer),
+    }
+
+    // syntax errors raised by the query once it's read are exceptions.
+    match wam.query("number_codes(N, \"3x\")").next() {
+        Some(Err(PrologError::Exception(Term::Compound(name, args)))) => {
+            assert_eq!(name, "error");
+            assert!(matches!(&args[0], Term::Compound(name, _) if name == "syntax_error"));
+        }
+        answer => panic!("expected a syntax error exception, got {:?}", answer),
+    }
+
+    assert!(wam.consult_str("p :- .").is_err());
+    assert!(wam.query("true").next().unwrap().is_ok());
+}
+
+// a machine started from the boot image, if there is one, should
+// answer just as one that compiled the libraries. The answers name
+// every variable, since the two may number fresh variables
+// differently.
+#[test]
+fn boot_image() {
+    let queries = [
+        "lists:append(Xs, Ys, [a, b])",
+        "X = (a :- b, c ; d), X = (_ :- Body)",
+        "current_op(P, T, is)",
+        "iso_ext:bb_b_put(v, 1), iso_ext:bb_get(v, X)",
+        "catch(atom_length(1, _), E, true)",
+        "lists:nth0(1, [a, b, c], X)",
+        "lists:length(Xs, 2), Xs = [A, B]",
+    ];
+
+    let answers = |wam: &mut Machine| -> Vec<String> {
+        let mut answers = vec![];
+
+        for query in &queries {
+            answers.extend(wam.query(query).map(|answer| format!("{:?}", answer)));
+        }
+
+        answers
+    };
+
+    let first = answers(&mut Machine::without_boot_image(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+    ));
+    let second = answers(&mut machine());
+
+    assert_eq!(first.len(), 9);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn saved_state() {
+    let path = std::env::temp_dir().join(format!("scryer-state-{}.sav", std::process::id()));
+    let mut wam = machine();
+
+    wam.consult_str(
+        ":- use_module(library(qsave)).
+         :- use_module(library(iso_ext)).
+         :- dynamic(counter/1).
+         :- op(700, xfx, ===>).
+         counter(0).",
+    )
+    .unwrap();
+
+    let query = format!(
+        "assertz(counter(1)), bb_put(config, f(\"abc\", [x|_])), \
+         set_prolog_flag(occurs_check, true), qsave_program('{}')",
+        path.display()
+    );
+
+    assert_eq!(wam.query(&query).count(), 1);
+
+    let mut restored = Machine::restore(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+        &path,
+    )
+    .unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    let counters: Vec<Term> = restored
+        .query("counter(C)")
+        .map(|answer| answer.unwrap()["C"].clone())
+        .collect();
+
+    assert_eq!(
+        counters,
+        vec![
+            Term::Integer(Integer::from(0)),
+            Term::Integer(Integer::from(1))
+        ]
+    );
+
+    let answer = restored
+        .query("iso_ext:bb_get(config, f(S, [X|_])), T = (a ===> b)")
+        .next()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(answer["S"], Term::String("abc".to_string()));
+    assert_eq!(answer["X"], atom("x"));
+    assert_eq!(
+        answer["T"],
+        Term::Compound("===>".to_string(), vec![atom("a"), atom("b")])
+    );
+
+    assert_eq!(restored.query("X = f(X)").count(), 0);
+
+    assert!(Machine::restore(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+        &path,
+    )
+    .is_err());
+}
+
+// a state that can't be saved is a representation error, and an I/O
+// error in writing it names the file.
+#[test]
+fn qsave_program_errors() {
+    let path = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("unsaveable_state");
+    let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+    let mut wam = machine();
+
+    wam.consult_str(":- use_module(library(qsave)). :- use_module(library(iso_ext)).")
+        .unwrap();
+
+    let error = |wam: &mut Machine, query: &str| match wam.query(query).next() {
+        Some(Err(PrologError::Exception(Term::Compound(_, args)))) => args[0].clone(),
+        answer => panic!("expected an exception, got {:?}", answer),
+    };
+
+    let query = format!(
+        "current_output(S), bb_put(stream, S), qsave_program('{}')",
+        path.display()
+    );
+
+    assert_eq!(
+        error(&mut wam, &query),
+        Term::Compound(
+            "representation_error".to_string(),
+            vec![atom("saved_state")]
+        )
+    );
+
+    // the state is written to a temporary file first, which can't be
+    // where a directory is.
+    std::fs::create_dir_all(&tmp_path).unwrap();
+
+    let query = format!("bb_put(stream, []), qsave_program('{}')", path.display());
+
+    match error(&mut wam, &query) {
+        Term::Compound(name, args) if name == "system_error" => match &args[0] {
+            Term::Compound(name, args) if name == "io_error" => {
+                assert_eq!(args[0], atom(&path.display().to_string()));
+            }
+            term => panic!("expected an io_error, got {:?}", term),
+        },
+        term => panic!("expected a system_error, got {:?}", term),
+    }
+
+    std::fs::remove_dir(&tmp_path).unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,163 @@
+use scryer_prolog::machine::embedding::*;
+use scryer_prolog::machine::{Machine, Stream};
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Once;
+
+static BUILD_TEST_LIBRARY: Once = Once::new();
+
+fn machine() -> Machine {
+    Machine::new(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+    )
+}
+
+// build tests/scryer/ffi_test.c as a shared library, once for all tests.
+fn test_library() -> PathBuf {
+    let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/scryer/ffi_test.c");
+    let lib = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("libffi_test.so");
+
+    BUILD_TEST_LIBRARY.call_once(|| {
+        let status = Command::new("cc")
+            .args(&["-shared", "-fPIC", "-o"])
+            .arg(&lib)
+            .arg(&src)
+            .status()
+            .expect("failed to run cc");
+
+        assert!(status.success());
+    });
+
+    lib
+}
+
+fn load_test_library(wam: &mut Machine) {
+    let lib = test_library();
+
+    wam.consult_str(&format!(
+        ":- use_module(library(ffi)).
+         :- use_foreign_library({:?}).
+         :- foreign(add_ints, [int, int], int).
+         :- foreign(add_longs, [long, long], long).
+         :- foreign(sum_ints, [int, int, int, int, int, int, int, int], int).
+         :- foreign(scale, [int, double], double).
+         :- foreign(weighted_sum, [double, int, double, int], double).
+         :- foreign(string_length, [string], int).
+         :- foreign(greeting, [], string).
+         :- foreign(counter_new, [int], pointer).
+         :- foreign(counter_next, [pointer], int).
+         :- foreign(counter_free, [pointer], void).
+         :- foreign(reverse_into, [string, buffer(16)], void).",
+        lib.to_str().unwrap()
+    ))
+    .unwrap();
+}
+
+fn answer(wam: &mut Machine, query: &str, var: &str) -> Term {
+    wam.query(query).next().unwrap().unwrap()[var].clone()
+}
+
+#[test]
+fn ffi_calls() {
+    let mut wam = machine();
+    load_test_library(&mut wam);
+
+    assert_eq!(
+        answer(&mut wam, "add_ints(2, -5, X)", "X"),
+        Term::Integer(Integer::from(-3))
+    );
+    assert_eq!(
+        answer(&mut wam, "add_longs(4000000000, 1, X)", "X"),
+        Term::Integer(Integer::from(4000000001isize))
+    );
+    assert_eq!(
+        answer(&mut wam, "sum_ints(1, 2, 3, 4, 5, 6, 7, 8, X)", "X"),
+        Term::Integer(Integer::from(36))
+    );
+    assert_eq!(answer(&mut wam, "scale(3, 1.5, X)", "X"), Term::Float(4.5));
+    assert_eq!(
+        answer(&mut wam, "weighted_sum(1.5, 2, 0.25, 4, X)", "X"),
+        Term::Float(4.0)
+    );
+    assert_eq!(
+        answer(&mut wam, "string_length(\"abc\", X)", "X"),
+        Term::Integer(Integer::from(3))
+    );
+    assert_eq!(
+        answer(&mut wam, "string_length(abcd, X)", "X"),
+        Term::Integer(Integer::from(4))
+    );
+    assert_eq!(
+        answer(&mut wam, "greeting(X)", "X"),
+        Term::String("hello".to_string())
+    );
+    assert_eq!(
+        answer(&mut wam, "reverse_into(\"abc\", X)", "X"),
+        Term::String("cba".to_string())
+    );
+    assert_eq!(wam.query("add_ints(1, 1, 3)").count(), 0);
+}
+
+#[test]
+fn ffi_pointers() {
+    let mut wam = machine();
+    load_test_library(&mut wam);
+
+    assert_eq!(
+        answer(
+            &mut wam,
+            "counter_new(10, C), counter_next(C, _), counter_next(C, N), counter_free(C)",
+            "N"
+        ),
+        Term::Integer(Integer::from(11))
+    );
+
+    match answer(&mut wam, "counter_new(0, C), counter_free(C)", "C") {
+        Term::Compound(name, _) => assert_eq!(name, "$pointer"),
+        term => panic!("expected a pointer, got {:?}", term),
+    }
+}
+
+#[test]
+fn ffi_errors() {
+    let mut wam = machine();
+    load_test_library(&mut wam);
+
+    let error = |wam: &mut Machine, query: &str| match wam.query(query).next() {
+        Some(Err(PrologError::Exception(Term::Compound(_, args)))) => args[0].to_string(),
+        answer => panic!("expected an exception, got {:?}", answer),
+    };
+
+    assert_eq!(
+        error(&mut wam, "add_ints(a, 1, _)"),
+        "type_error(integer,a)"
+    );
+    assert_eq!(error(&mut wam, "add_ints(_, 1, _)"), "instantiation_error");
+    assert_eq!(
+        error(&mut wam, "add_ints(4000000000, 1, _)"),
+        "representation_error(term)"
+    );
+    assert_eq!(
+        error(&mut wam, "counter_next(1, _)"),
+        "type_error(pointer,1)"
+    );
+    assert_eq!(
+        error(&mut wam, "ffi:use_foreign_library(\"no_such_library.so\")"),
+        "existence_error(source_sink,\"no_such_library.so\")"
+    );
+    assert_eq!(
+        error(&mut wam, "ffi:foreign(no_such_function, [], void)"),
+        "existence_error(foreign_function,no_such_function)"
+    );
+    assert_eq!(
+        error(&mut wam, "ffi:foreign(add_ints, [int, short], int)"),
+        "domain_error(foreign_type,short)"
+    );
+    assert_eq!(
+        error(&mut wam, "ffi:foreign(add_ints, [int, void], int)"),
+        "domain_error(foreign_type,void)"
+    );
+}
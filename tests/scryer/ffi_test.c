@@ -0,0 +1,58 @@
+/* The library that tests/scryer/ffi.rs loads through library(ffi). */
+
+#include <stdlib.h>
+#include <string.h>
+
+int add_ints(int a, int b) {
+    return a + b;
+}
+
+long add_longs(long a, long b) {
+    return a + b;
+}
+
+int sum_ints(int a, int b, int c, int d, int e, int f, int g, int h) {
+    return a + b + c + d + e + f + g + h;
+}
+
+double scale(int factor, double x) {
+    return factor * x;
+}
+
+double weighted_sum(double a, int wa, double b, int wb) {
+    return a * wa + b * wb;
+}
+
+int string_length(const char *s) {
+    return (int) strlen(s);
+}
+
+const char *greeting(void) {
+    return "hello";
+}
+
+struct counter {
+    int count;
+};
+
+struct counter *counter_new(int start) {
+    struct counter *c = malloc(sizeof(struct counter));
+    c->count = start;
+    return c;
+}
+
+int counter_next(struct counter *c) {
+    return c->count++;
+}
+
+void counter_free(struct counter *c) {
+    free(c);
+}
+
+void reverse_into(const char *s, char *buf) {
+    size_t len = strlen(s);
+
+    for (size_t i = 0; i < len; i++) {
+        buf[i] = s[len - i - 1];
+    }
+}
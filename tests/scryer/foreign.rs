@@ -0,0 +1,117 @@
+use scryer_prolog::machine::embedding::*;
+use scryer_prolog::machine::foreign::*;
+use scryer_prolog::machine::{Machine, Stream};
+
+fn machine() -> Machine {
+    Machine::new(
+        Stream::from(""),
+        Stream::from(String::new()),
+        Stream::from(String::new()),
+    )
+}
+
+fn int(n: usize) -> Term {
+    Term::Integer(Integer::from(n))
+}
+
+#[test]
+fn det_predicates() {
+    let mut wam = machine();
+
+    wam.register_foreign(
+        "user",
+        "double",
+        2,
+        Box::new(|ctx| match ctx.arg(0) {
+            Term::Integer(n) => {
+                if ctx.unify(1, &Term::Integer(n * 2)) {
+                    ForeignResult::Succeed
+                } else {
+                    ForeignResult::Fail
+                }
+            }
+            _ => ForeignResult::Fail,
+        }),
+    )
+    .unwrap();
+
+    let answers: Vec<_> = wam.query("double(21, X)").collect();
+    assert_eq!(
+        answers,
+        vec![Ok(Bindings::from([("X".to_string(), int(42))]))]
+    );
+
+    assert_eq!(wam.query("double(21, 41)").count(), 0);
+    assert_eq!(wam.query("double(a, _)").count(), 0);
+}
+
+#[test]
+fn nondet_predicates() {
+    let mut wam = machine();
+
+    wam.register_foreign(
+        "user",
+        "upto",
+        2,
+        Box::new(|ctx| {
+            let n = ctx.redo_state().unwrap_or(1);
+
+            let max = match ctx.arg(0) {
+                Term::Integer(max) => max.to_usize().unwrap(),
+                _ => return ForeignResult::Fail,
+            };
+
+            if n > max || !ctx.unify(1, &int(n)) {
+                ForeignResult::Fail
+            } else if n == max {
+                ForeignResult::Succeed
+            } else {
+                ForeignResult::Redo(n + 1)
+            }
+        }),
+    )
+    .unwrap();
+
+    let answers: Vec<Term> = wam
+        .query("upto(3, X)")
+        .map(|answer| answer.unwrap()["X"].clone())
+        .collect();
+
+    assert_eq!(answers, vec![int(1), int(2), int(3)]);
+    assert_eq!(wam.query("upto(3, X), X > 1, !").count(), 1);
+}
+
+#[test]
+fn foreign_exceptions() {
+    let mut wam = machine();
+
+    wam.register_foreign(
+        "lists_ext",
+        "oops",
+        0,
+        Box::new(|_| ForeignResult::Throw(Term::Atom("oops".to_string()))),
+    )
+    .unwrap();
+
+    match wam.query("lists_ext:oops").next() {
+        Some(Err(PrologError::Exception(Term::Atom(ball)))) => {
+            assert_eq!(ball, "oops");
+        }
+        answer => panic!("expected an exception, got {:?}", answer),
+    }
+
+    assert_eq!(wam.query("catch(lists_ext:oops, oops, true)").count(), 1);
+}
+
+#[test]
+fn failed_registrations() {
+    let mut wam = machine();
+    let succeed = || -> ForeignFn { Box::new(|_| ForeignResult::Succeed) };
+
+    // a failed registration leaves nothing behind to replace.
+    assert!(wam.register_foreign("user", ",", 2, succeed()).is_err());
+    assert!(wam.register_foreign("user", ",", 2, succeed()).is_err());
+
+    wam.register_foreign("user", "ok", 0, succeed()).unwrap();
+    assert_eq!(wam.query("ok").count(), 1);
+}
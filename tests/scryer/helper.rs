@@ -29,9 +29,19 @@ impl Expectable for &[u8] {
 /// Tests whether the file can be successfully loaded
 /// and produces the expected output during it
 pub(crate) fn load_module_test<T: Expectable>(file: &str, expected: T) {
+    load_module_test_with_input(file, "", expected)
+}
+
+/// Like load_module_test, with the supplied input read from
+/// user_input
+pub(crate) fn load_module_test_with_input<T: Expectable>(
+    file: &str,
+    input: &'static str,
+    expected: T,
+) {
     use scryer_prolog::*;
 
-    let input = machine::Stream::from("");
+    let input = machine::Stream::from(input);
     let output = machine::Stream::from(String::new());
     let error = machine::Stream::from(String::new());
 
@@ -50,6 +60,17 @@ pub(crate) fn load_module_test<T: Expectable>(file: &str, expected: T) {
 
 pub const SCRYER_PROLOG: &str = "scryer-prolog";
 
+/// The scryer-prolog executable, keeping its boot image under the
+/// target directory rather than in the cache directory of the user
+pub fn scryer_prolog() -> Command {
+    let mut cmd = Command::cargo_bin(SCRYER_PROLOG).unwrap();
+    cmd.env(
+        "XDG_CACHE_HOME",
+        std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cache"),
+    );
+    cmd
+}
+
 pub fn run_top_level_test_no_args<
     S: Into<Vec<u8>>,
     O: assert_cmd::assert::IntoOutputPredicate<P>,
@@ -75,8 +96,7 @@ pub fn run_top_level_test_with_args<
     stdin: S,
     expected_stdout: O,
 ) {
-    Command::cargo_bin(SCRYER_PROLOG)
-        .unwrap()
+    scryer_prolog()
         .args(args)
         .write_stdin(stdin)
         .assert()
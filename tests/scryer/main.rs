@@ -1,4 +1,9 @@
 mod helper;
 
+mod boot_image;
+mod embedding;
+mod ffi;
+mod foreign;
 mod issues;
 mod src_tests;
+mod standalone;
@@ -1,4 +1,6 @@
-use crate::helper::{load_module_test, run_top_level_test_with_args};
+use crate::helper::{
+    load_module_test, load_module_test_with_input, run_top_level_test_with_args, scryer_prolog,
+};
 
 #[test]
 fn builtins() {
@@ -10,16 +12,255 @@ fn call_with_inference_limit() {
     load_module_test("src/tests/call_with_inference_limit.pl", "");
 }
 
+#[test]
+fn diag() {
+    load_module_test("src/tests/diag.pl", "diag tests passed\n");
+}
+
 #[test]
 fn facts() {
     load_module_test("src/tests/facts.pl", "");
 }
 
+#[test]
+fn garbage_collection() {
+    load_module_test(
+        "src/tests/garbage_collection.pl",
+        "garbage collection tests passed\n",
+    );
+}
+
 #[test]
 fn hello_world() {
     load_module_test("src/tests/hello_world.pl", "Hello World!\n");
 }
 
+#[test]
+fn indexing() {
+    load_module_test("src/tests/indexing.pl", "indexing tests passed\n");
+}
+
+#[test]
+fn memory_limits() {
+    load_module_test("src/tests/memory_limits.pl", "memory limit tests passed\n");
+}
+
+#[test]
+fn time_limits() {
+    load_module_test("src/tests/time_limits.pl", "time limit tests passed\n");
+}
+
+#[test]
+fn debugger() {
+    load_module_test(
+        "src/tests/debugger.pl",
+        concat!(
+            "   Call: (1) debugger_tests:p(2)\n",
+            "   Call: (2) debugger_tests:q(2)\n",
+            "   Exit: (2) debugger_tests:q(2)\n",
+            "   Call: (2) debugger_tests:r(2)\n",
+            "   Exit: (2) debugger_tests:r(2)\n",
+            "   Exit: (1) debugger_tests:p(2)\n",
+            "   Call: (1) debug:nodebug\n",
+            "   Call: (1) builtins:catch(debugger_tests:s(1),ball,debugger_tests:true)\n",
+            "   Call: (2) debugger_tests:s(1)\n",
+            "   Call: (3) builtins:throw(ball)\n",
+            "   Exception: (3) builtins:throw(ball) raises ball\n",
+            "   Exception: (2) debugger_tests:s(1) raises ball\n",
+            "   Exit: (1) builtins:catch(debugger_tests:s(1),ball,debugger_tests:true)\n",
+            "   Call: (1) debug:nodebug\n",
+            "   Call: (1) debugger_tests:r(1)\n",
+            "   Fail: (1) debugger_tests:r(1)\n",
+            "The debugger traces calls to spy points.\n",
+            "Spy points:\n",
+            "   r/1\n",
+            "Leashed ports: []\n",
+            "The debugger is off.\n",
+            "There are no spy points.\n",
+            "Leashed ports: []\n",
+            "debugger tests passed\n",
+        ),
+    );
+}
+
+#[test]
+fn debugger_commands() {
+    // one command for each leashed port: creep (c), skip (s), leap
+    // (l), retry (r), fail (f) and nodebug (n).
+    load_module_test_with_input(
+        "src/tests/debugger_commands.pl",
+        concat!("cccccccccccccn", "sccn", "lllll", "ccccrccfcccn"),
+        concat!(
+            "   Call: (1) debugger_command_tests:t ? \n",
+            "   Call: (2) debugger_command_tests:b ? \n",
+            "   Exit: (2) debugger_command_tests:b ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Redo: (2) debugger_command_tests:b ? \n",
+            "   Exit: (2) debugger_command_tests:b ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Redo: (2) debugger_command_tests:b ? \n",
+            "   Fail: (2) debugger_command_tests:b ? \n",
+            "   Fail: (1) debugger_command_tests:t ? \n",
+            "   Call: (1) builtins:true ? \n",
+            "   Exit: (1) builtins:true ? \n",
+            "   Call: (1) debugger_command_tests:t ? \n",
+            "   Fail: (1) debugger_command_tests:t ? \n",
+            "   Call: (1) builtins:true ? \n",
+            "   Exit: (1) builtins:true ? \n",
+            "   Call: (1) debugger_command_tests:t ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Call: (1) debugger_command_tests:t ? \n",
+            "   Call: (2) debugger_command_tests:b ? \n",
+            "   Exit: (2) debugger_command_tests:b ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Call: (2) debugger_command_tests:c(2) ? \n",
+            "   Fail: (2) debugger_command_tests:c(2) ? \n",
+            "   Redo: (2) debugger_command_tests:b ? \n",
+            "   Fail: (2) debugger_command_tests:b ? \n",
+            "   Fail: (1) debugger_command_tests:t ? \n",
+            "   Call: (1) builtins:true ? \n",
+            "   Exit: (1) builtins:true ? \n",
+            "debugger command tests passed\n",
+        ),
+    );
+}
+
+#[test]
+fn profiler() {
+    load_module_test("src/tests/profiler.pl", "profiler tests passed\n");
+}
+
+#[test]
+fn coverage() {
+    load_module_test("src/tests/coverage.pl", "coverage tests passed\n");
+}
+
+#[test]
+fn clause_properties() {
+    load_module_test(
+        "src/tests/clause_properties.pl",
+        "clause property tests passed\n",
+    );
+}
+
+#[test]
+fn backtraces() {
+    load_module_test("src/tests/backtraces.pl", "backtrace tests passed\n");
+}
+
+#[test]
+fn statistics() {
+    load_module_test("src/tests/statistics.pl", "statistics tests passed\n");
+}
+
+#[test]
+fn conditional_compilation() {
+    load_module_test(
+        "src/tests/conditional_compilation.pl",
+        "conditional compilation tests passed\n",
+    );
+}
+
+#[test]
+fn include() {
+    load_module_test("src/tests/include.pl", "include tests passed\n");
+}
+
+#[test]
+fn autoload() {
+    load_module_test("src/tests/autoload.pl", "autoload tests passed\n");
+}
+
+fn set_modified(path: &std::path::Path, time: std::time::SystemTime) {
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+// make/0 compares modification times to the second, so the files
+// replacing those loaded are dated later explicitly rather than
+// waited for.
+#[test]
+fn make() {
+    use std::time::{Duration, SystemTime};
+
+    // the test replaces the files of src/tests/make by their _v2
+    // versions, so it runs on a copy of them.
+    let dir = std::env::temp_dir().join(format!("scryer-make-{}", std::process::id()));
+    let make_dir = dir.join("make");
+
+    std::fs::create_dir_all(&make_dir).unwrap();
+    std::fs::copy("src/tests/make.pl", dir.join("make.pl")).unwrap();
+
+    let loaded = SystemTime::now() - Duration::from_secs(60);
+
+    for entry in std::fs::read_dir("src/tests/make").unwrap() {
+        let path = entry.unwrap().path();
+        let copy = make_dir.join(path.file_name().unwrap());
+
+        std::fs::copy(&path, &copy).unwrap();
+
+        if !path.to_str().unwrap().ends_with("_v2.pl") {
+            set_modified(&copy, loaded);
+        }
+    }
+
+    load_module_test(
+        dir.join("make.pl").to_str().unwrap(),
+        "caught: error(syntax_error(incomplete_reduction),read_term/3:2)\n\
+         make tests passed\n",
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn make_scryerrc() {
+    use std::time::{Duration, SystemTime};
+
+    let home = std::env::temp_dir().join(format!("scryer-make-home-{}", std::process::id()));
+    let scryerrc = home.join(".scryerrc");
+    let replacement = home.join("scryerrc_v2.pl");
+
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::write(&scryerrc, "rc(1).\n").unwrap();
+    std::fs::write(&replacement, "rc(2).\n").unwrap();
+
+    set_modified(&scryerrc, SystemTime::now() - Duration::from_secs(60));
+
+    let input = format!(
+        "rc(X).\n\
+         use_module(library(files)), rename_file(\"{}\", \"{}\").\n\
+         make.\n\
+         rc(X).\n",
+        replacement.display(),
+        scryerrc.display()
+    );
+
+    scryer_prolog()
+        .env("HOME", &home)
+        .write_stdin(input)
+        .assert()
+        .stdout("   X = 1.\n   true.\n   true.\n   X = 2.\n")
+        .success();
+
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn threads() {
+    load_module_test("src/tests/threads.pl", "thread tests passed\n");
+}
+
 #[test]
 fn syntax_error() {
     load_module_test(
@@ -0,0 +1,23 @@
+use crate::helper::scryer_prolog;
+use assert_cmd::Command;
+use std::path::PathBuf;
+
+// --compile-exe writes an executable that runs main/0 with all of its
+// arguments, without reading its source again.
+#[test]
+fn compile_exe() {
+    let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("standalone_main");
+
+    scryer_prolog()
+        .args(&["--compile-exe", "tests-pl/standalone_main.pl", "-o"])
+        .arg(&exe)
+        .write_stdin("")
+        .assert()
+        .success();
+
+    Command::new(&exe)
+        .args(&["a", "b c", "--", "-x"])
+        .assert()
+        .stdout("4 arguments: [\"a\",\"b c\",\"--\",\"-x\"]\n")
+        .success();
+}